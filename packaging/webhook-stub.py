@@ -0,0 +1,55 @@
+#!/usr/bin/env python3
+"""Minimal receiver for testing the diskmon webhook notifier.
+
+Prints every POST with its headers and checks the X-Diskmon-Signature header when a secret is
+given. Reply with another status to try out retries.
+
+    python3 packaging/webhook-stub.py [--port 8080] [--secret SECRET] [--status 200]
+
+and point a notifier at it:
+
+    notifiers:
+      - type: webhook
+        url: http://127.0.0.1:8080/diskmon
+        secret: SECRET
+"""
+
+import argparse
+import hashlib
+import hmac
+import json
+from http.server import BaseHTTPRequestHandler, HTTPServer
+
+parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+parser.add_argument("--port", type=int, default=8080)
+parser.add_argument("--secret", help="shared secret to verify the signature with")
+parser.add_argument("--signature-header", default="X-Diskmon-Signature")
+parser.add_argument("--status", type=int, default=200, help="HTTP status to answer with")
+args = parser.parse_args()
+
+
+class Handler(BaseHTTPRequestHandler):
+    def do_POST(self):
+        body = self.rfile.read(int(self.headers.get("Content-Length", 0)))
+        print(f"--- {self.command} {self.path}")
+        for name, value in self.headers.items():
+            print(f"{name}: {value}")
+        if args.secret:
+            expected = "sha256=" + hmac.new(args.secret.encode(), body, hashlib.sha256).hexdigest()
+            given = self.headers.get(args.signature_header, "")
+            print("signature:", "OK" if hmac.compare_digest(given, expected) else "MISMATCH")
+        try:
+            payload = json.loads(body)
+            print(f"event={payload.get('event')} status={payload.get('status')} "
+                  f"disks={len(payload.get('disks', []))} alerts={len(payload.get('alerts', []))} "
+                  f"resolved={len(payload.get('resolved', []))}")
+        except ValueError:
+            print("body is not JSON:", body[:200])
+        self.send_response(args.status)
+        self.end_headers()
+
+    def log_message(self, format, *args):
+        pass
+
+
+HTTPServer(("127.0.0.1", args.port), Handler).serve_forever()
@@ -0,0 +1,456 @@
+// Alert evaluation.
+//
+// Every check (free space, inodes, fill rate forecast, SMART health, sector counters, temperature, failing attributes
+// and the NVMe health log) produces alerts with a warning or critical severity. Console output,
+// `--json`, the report email and the exit code all work from the same list.
+//
+// Evaluation also looks at the previous run (see state.rs): free space and inode alerts that are
+// already active only clear once they are clear_margin past their threshold, and a condition has
+// to hold for alert_after_checks consecutive runs before it fires.
+
+use std::fmt;
+
+use crate::DiskInfo;
+use crate::config::Config;
+use crate::smart::HealthStatus;
+use crate::state::DiskHistory;
+use crate::units::{ByteSize, format_elapsed};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Warning => "WARNING",
+            Severity::Critical => "CRITICAL",
+        }
+    }
+
+    /// Process exit code for the worst severity of a run (Nagios convention; 0 means OK).
+    pub fn exit_code(severity: Option<Severity>) -> i32 {
+        match severity {
+            None => 0,
+            Some(Severity::Warning) => 1,
+            Some(Severity::Critical) => 2,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What an alert is about. Together with the disk this identifies an alert across runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    LowSpace,
+    LowInodes,
+    SmartHealth,
+    SmartUnknown,
+    SmartAttributes,
+    ReallocatedSectors,
+    PendingSectors,
+    UncorrectableSectors,
+    Temperature,
+    NvmeCriticalWarning,
+    NvmeSpare,
+    NvmeWear,
+    FullSoon,
+    CounterIncrease,
+}
+
+impl Condition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Condition::LowSpace => "low_space",
+            Condition::LowInodes => "low_inodes",
+            Condition::SmartHealth => "smart_health",
+            Condition::SmartUnknown => "smart_unknown",
+            Condition::SmartAttributes => "smart_attributes",
+            Condition::ReallocatedSectors => "reallocated_sectors",
+            Condition::PendingSectors => "pending_sectors",
+            Condition::UncorrectableSectors => "uncorrectable_sectors",
+            Condition::Temperature => "temperature",
+            Condition::NvmeCriticalWarning => "nvme_critical_warning",
+            Condition::NvmeSpare => "nvme_spare",
+            Condition::NvmeWear => "nvme_wear",
+            Condition::FullSoon => "full_soon",
+            Condition::CounterIncrease => "counter_increase",
+        }
+    }
+
+    /// Conditions that come from the disk's health data rather than the filesystem.
+    pub fn is_smart(&self) -> bool {
+        !matches!(self, Condition::LowSpace | Condition::LowInodes | Condition::FullSoon)
+    }
+
+    /// Conditions describing a change since the previous run rather than an ongoing state. They
+    /// fire without waiting for alert_after_checks and clear without a resolved notification.
+    pub fn is_event(&self) -> bool {
+        matches!(self, Condition::CounterIncrease)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Alert {
+    pub condition: Condition,
+    pub severity: Severity,
+    pub message: String,
+    pub checks: u32, // Consecutive runs this condition has held, including this one
+}
+
+/// Warning and critical limits for everything except free space, which comes from the
+/// per-filesystem threshold rules.
+#[derive(Debug, Clone)]
+pub struct Limits {
+    pub inode_warning_percent: f64,
+    pub inode_critical_percent: Option<f64>,
+    pub temperature_warning: i64,
+    pub temperature_critical: i64,
+    pub sector_warning: Option<u64>, // Off unless configured: counter_increase reports new sectors
+    pub sector_critical: u64,
+    pub smart_attribute_warning_margin: u64,
+    pub nvme_percentage_used_limit: f64,
+    pub smart_enabled: bool,
+    pub alert_on_unknown: bool,
+    pub inode_clear_margin: f64,
+    pub alert_after_checks: u32,
+    pub counter_increase_critical: u64,
+}
+
+impl Limits {
+    pub fn from_config(cfg: &Config) -> Self {
+        Limits {
+            inode_warning_percent: cfg.inode_threshold_percent.unwrap_or(10.0),
+            inode_critical_percent: cfg.critical_inode_threshold_percent,
+            temperature_warning: cfg.temperature_warning.unwrap_or(55),
+            temperature_critical: cfg.temperature_critical.unwrap_or(65),
+            sector_warning: cfg.sector_warning_count,
+            sector_critical: cfg.sector_critical_count.unwrap_or(50),
+            smart_attribute_warning_margin: cfg.smart_attribute_warning_margin.unwrap_or(0),
+            nvme_percentage_used_limit: cfg.nvme_percentage_used_limit.unwrap_or(90.0),
+            smart_enabled: cfg.smart_enabled.unwrap_or(true),
+            alert_on_unknown: cfg.send_mail_on_unknown_status.unwrap_or(false),
+            inode_clear_margin: cfg.clear_margin.unwrap_or(0.0),
+            alert_after_checks: cfg.alert_after_checks.unwrap_or(1),
+            counter_increase_critical: cfg.counter_increase_critical.unwrap_or(10),
+        }
+    }
+
+    fn tier<T: PartialOrd>(value: T, warning: T, critical: T) -> Option<Severity> {
+        if value >= critical {
+            Some(Severity::Critical)
+        } else if value >= warning {
+            Some(Severity::Warning)
+        } else {
+            None
+        }
+    }
+
+    fn inode_severity(&self, free_percent: f64) -> Option<Severity> {
+        match self.inode_critical_percent {
+            Some(critical) if free_percent < critical => Some(Severity::Critical),
+            _ if free_percent < self.inode_warning_percent => Some(Severity::Warning),
+            _ => None,
+        }
+    }
+}
+
+// Severity of a threshold alert given the previous run: a tier that was already active stays
+// active while the held evaluation (threshold plus clear margin) still crosses it.
+fn with_hysteresis(current: Option<Severity>, held: Option<Severity>, previous: Option<Severity>) -> Option<Severity> {
+    match previous {
+        Some(previous) => current.max(held.min(Some(previous))),
+        None => current,
+    }
+}
+
+/// Evaluate every check for one disk. `history` holds the alerts recorded for it on the previous run.
+pub fn evaluate(disk: &DiskInfo, limits: &Limits, history: &DiskHistory) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+    let mut push = |condition, severity, message: String| alerts.push(Alert { condition, severity, message, checks: 1 });
+    let previous = |condition| history.get(&condition).map(|(severity, _)| *severity);
+
+    let thresholds = &disk.thresholds;
+    let space_severity = with_hysteresis(
+        thresholds.space_severity(disk.free_space_percent, disk.available_space),
+        thresholds.held_space_severity(disk.free_space_percent, disk.available_space, disk.total_space),
+        previous(Condition::LowSpace),
+    );
+    if let Some(severity) = space_severity {
+        push(Condition::LowSpace, severity, thresholds.space_reason(severity, disk.free_space_percent, disk.available_space));
+    }
+
+    // Filesystems without inode counts (btrfs, ZFS) have no inode_free_percent and never alert
+    if let Some(percent) = disk.inode_free_percent {
+        let severity = with_hysteresis(
+            limits.inode_severity(percent),
+            limits.inode_severity(percent - limits.inode_clear_margin),
+            previous(Condition::LowInodes),
+        );
+        match severity {
+            Some(Severity::Critical) => push(Condition::LowInodes, Severity::Critical,
+                format!("low inodes ({:.2}% free, critical below {}%)", percent, limits.inode_critical_percent.unwrap_or_default())),
+            Some(Severity::Warning) => push(Condition::LowInodes, Severity::Warning,
+                format!("low inodes ({:.2}% free, warning below {}%)", percent, limits.inode_warning_percent)),
+            None => {}
+        }
+    }
+
+    if let Some(forecast) = &disk.forecast
+        && let Some(full_in) = forecast.full_in
+    {
+        let tiers = [
+            (Severity::Critical, thresholds.critical_if_full_within),
+            (Severity::Warning, thresholds.alert_if_full_within),
+        ];
+        if let Some((severity, Some(within))) = tiers.into_iter().find(|(_, within)| within.is_some_and(|w| full_in <= w.0)) {
+            push(Condition::FullSoon, severity, format!("full in ~{} at {}/day ({} within {}, rule '{}')",
+                 format_elapsed(full_in), ByteSize(-forecast.bytes_per_day as u64), severity, within, thresholds.rule));
+        }
+    }
+
+    if !limits.smart_enabled {
+        return count_checks(alerts, history);
+    }
+    let smart = &disk.smart;
+
+    match smart.health {
+        HealthStatus::Failing => push(Condition::SmartHealth, Severity::Critical, format!("SMART status: {}", smart.health)),
+        HealthStatus::Warning => push(Condition::SmartHealth, Severity::Warning, format!("SMART status: {}", smart.health)),
+        HealthStatus::Unknown if limits.alert_on_unknown => {
+            push(Condition::SmartUnknown, Severity::Warning, "SMART status: Unknown".to_string())
+        }
+        _ => {}
+    }
+
+    let counters = [
+        (Condition::ReallocatedSectors, smart.reallocated_sectors, "reallocated sectors"),
+        (Condition::PendingSectors, smart.pending_sectors, "pending sectors"),
+        (Condition::UncorrectableSectors, smart.uncorrectable_sectors, "uncorrectable sectors"),
+    ];
+    for (condition, count, name) in counters {
+        let Some(count) = count else { continue };
+        let warning = limits.sector_warning.unwrap_or(limits.sector_critical);
+        if let Some(severity) = Limits::tier(count, warning, limits.sector_critical) {
+            push(condition, severity, format!("{} {} detected", count, name));
+        }
+    }
+
+    let increases: Vec<_> = disk.counter_changes.iter().filter(|c| c.increase() > 0).collect();
+    if !increases.is_empty() {
+        let severity = if increases.iter().any(|c| c.increase() >= limits.counter_increase_critical) {
+            Severity::Critical
+        } else {
+            Severity::Warning
+        };
+        let changes: Vec<String> = increases.iter()
+            .map(|c| format!("{} {} (+{})", c.counter.label(), c, c.increase()))
+            .collect();
+        push(Condition::CounterIncrease, severity, format!("SMART counters increased: {}", changes.join(", ")));
+    }
+
+    if let Some(temp) = smart.temperature
+        && let Some(severity) = Limits::tier(temp, limits.temperature_warning, limits.temperature_critical)
+    {
+        let limit = match severity {
+            Severity::Critical => limits.temperature_critical,
+            Severity::Warning => limits.temperature_warning,
+        };
+        push(Condition::Temperature, severity, format!("temperature {} C ({} at {} C)", temp, severity, limit));
+    }
+
+    let failing: Vec<String> = smart
+        .failing_attributes()
+        .map(|a| format!("{} {} ({} <= {})", a.id, a.name, a.value, a.thresh))
+        .collect();
+    // Attributes approaching their threshold only warn
+    let degrading: Vec<String> = smart
+        .degrading_attributes(limits.smart_attribute_warning_margin)
+        .map(|a| format!("{} {} ({} near {})", a.id, a.name, a.value, a.thresh))
+        .collect();
+    if !failing.is_empty() {
+        push(Condition::SmartAttributes, Severity::Critical,
+             format!("SMART attribute(s) at or below threshold: {}", failing.join(", ")));
+    } else if !degrading.is_empty() {
+        push(Condition::SmartAttributes, Severity::Warning,
+             format!("SMART attribute(s) degrading: {}", degrading.join(", ")));
+    }
+
+    if let Some(nvme) = &smart.nvme {
+        if nvme.critical_warning != 0 {
+            push(Condition::NvmeCriticalWarning, Severity::Critical,
+                 format!("NVMe critical warning 0x{:02X} ({})", nvme.critical_warning, nvme.critical_warnings().join(", ")));
+        }
+        if nvme.spare_below_threshold() {
+            push(Condition::NvmeSpare, Severity::Critical,
+                 format!("NVMe available spare {}% below threshold {}%", nvme.available_spare, nvme.available_spare_threshold));
+        }
+        let used = f64::from(nvme.percentage_used);
+        if used > limits.nvme_percentage_used_limit {
+            // Past 100% the drive has exceeded its rated endurance
+            let severity = if used >= 100.0 { Severity::Critical } else { Severity::Warning };
+            push(Condition::NvmeWear, severity,
+                 format!("NVMe percentage used {}% above limit {:.0}%", nvme.percentage_used, limits.nvme_percentage_used_limit));
+        }
+    }
+
+    count_checks(alerts, history)
+}
+
+fn count_checks(mut alerts: Vec<Alert>, history: &DiskHistory) -> Vec<Alert> {
+    for alert in &mut alerts {
+        alert.checks = history.get(&alert.condition).map_or(1, |(_, checks)| checks.saturating_add(1));
+    }
+    alerts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::State;
+    use crate::testing;
+
+    const CONFIG: &str = "threshold_percent: 10\ncritical_threshold_percent: 5\ninode_threshold_percent: 10\nclear_margin: 2";
+
+    fn limits(yaml: &str) -> Limits {
+        Limits::from_config(&testing::config(yaml))
+    }
+
+    fn history(condition: Condition, severity: Severity, checks: u32) -> DiskHistory {
+        DiskHistory::from([(condition, (severity, checks))])
+    }
+
+    // Free space as a percentage of a 1000 byte filesystem
+    fn space(free_percent: f64, history: &DiskHistory) -> Option<Severity> {
+        let disk = testing::disk("/srv", 1_000, (free_percent * 10.0) as u64, testing::thresholds(CONFIG));
+        evaluate(&disk, &limits(CONFIG), history).iter()
+            .find(|a| a.condition == Condition::LowSpace)
+            .map(|a| a.severity)
+    }
+
+    fn inodes(free_percent: f64, history: &DiskHistory) -> Option<Alert> {
+        let mut disk = testing::disk("/srv", 1_000, 500, testing::thresholds(CONFIG));
+        disk.inode_free_percent = Some(free_percent);
+        evaluate(&disk, &limits(CONFIG), history).into_iter().find(|a| a.condition == Condition::LowInodes)
+    }
+
+    #[test]
+    fn hysteresis_holds_only_active_tiers() {
+        use Severity::{Critical, Warning};
+        // Nothing active before: only the current evaluation counts
+        assert_eq!(with_hysteresis(None, Some(Warning), None), None);
+        assert_eq!(with_hysteresis(Some(Warning), Some(Critical), None), Some(Warning));
+        // An active tier holds while the held evaluation still crosses it
+        assert_eq!(with_hysteresis(None, Some(Warning), Some(Warning)), Some(Warning));
+        assert_eq!(with_hysteresis(None, None, Some(Warning)), None);
+        assert_eq!(with_hysteresis(Some(Warning), Some(Critical), Some(Critical)), Some(Critical));
+        assert_eq!(with_hysteresis(Some(Warning), Some(Warning), Some(Critical)), Some(Warning));
+        // The held evaluation never raises the severity above the previous one
+        assert_eq!(with_hysteresis(Some(Warning), Some(Critical), Some(Warning)), Some(Warning));
+        assert_eq!(with_hysteresis(Some(Critical), Some(Critical), Some(Warning)), Some(Critical));
+    }
+
+    #[test]
+    fn low_space_fires_below_threshold_and_clears_above_margin() {
+        let none = DiskHistory::new();
+        let warning = history(Condition::LowSpace, Severity::Warning, 1);
+        assert_eq!(space(9.5, &none), Some(Severity::Warning));
+        assert_eq!(space(4.5, &none), Some(Severity::Critical));
+        assert_eq!(space(11.0, &none), None);
+        // Active warning: held until free space is above 10% + 2%
+        assert_eq!(space(11.0, &warning), Some(Severity::Warning));
+        assert_eq!(space(11.9, &warning), Some(Severity::Warning));
+        assert_eq!(space(12.0, &warning), None);
+        // Active critical drops to warning above 5% + 2%, then clears above 12%
+        let critical = history(Condition::LowSpace, Severity::Critical, 1);
+        assert_eq!(space(6.5, &critical), Some(Severity::Critical));
+        assert_eq!(space(7.5, &critical), Some(Severity::Warning));
+        assert_eq!(space(12.5, &critical), None);
+    }
+
+    #[test]
+    fn low_inodes_use_the_clear_margin() {
+        let none = DiskHistory::new();
+        let warning = history(Condition::LowInodes, Severity::Warning, 1);
+        let alert = inodes(9.0, &none).unwrap();
+        assert_eq!((alert.severity, alert.message.as_str()), (Severity::Warning, "low inodes (9.00% free, warning below 10%)"));
+        assert!(inodes(11.0, &none).is_none());
+        assert_eq!(inodes(11.0, &warning).map(|a| a.severity), Some(Severity::Warning));
+        assert!(inodes(12.5, &warning).is_none());
+    }
+
+    #[test]
+    fn checks_count_consecutive_runs() {
+        let disk = testing::disk("/srv", 1_000, 50, testing::thresholds(CONFIG));
+        let alerts = evaluate(&disk, &limits(CONFIG), &DiskHistory::new());
+        assert_eq!(alerts[0].checks, 1);
+        let alerts = evaluate(&disk, &limits(CONFIG), &history(Condition::LowSpace, Severity::Critical, 2));
+        assert_eq!(alerts[0].checks, 3);
+        // Another condition's count doesn't carry over
+        let alerts = evaluate(&disk, &limits(CONFIG), &history(Condition::LowInodes, Severity::Warning, 2));
+        assert_eq!(alerts[0].checks, 1);
+    }
+
+    #[test]
+    fn alerts_fire_after_alert_after_checks_runs() {
+        let cfg = format!("{}\nalert_after_checks: 3", CONFIG);
+        let limits = limits(&cfg);
+        let thresholds = testing::thresholds(&cfg);
+        let fs = testing::filesystem("/srv", "/dev/sdb1", 1_000, 80);
+        let mut state = State::default();
+        let mut fired = Vec::new();
+        for run in 0..4 {
+            let disk = DiskInfo::new("/srv".to_string(), fs.clone(), thresholds.clone(), None, Default::default(), &limits, &state);
+            fired.push((disk.alerts.len(), disk.pending_alerts.len()));
+            state.update(std::slice::from_ref(&disk), run);
+        }
+        assert_eq!(fired, [(0, 1), (0, 1), (1, 0), (1, 0)]);
+
+        // A run without the condition starts the count again
+        let mut clear = fs.clone();
+        clear.available_space = 500;
+        let disk = DiskInfo::new("/srv".to_string(), clear, thresholds.clone(), None, Default::default(), &limits, &state);
+        state.update(std::slice::from_ref(&disk), 4);
+        let disk = DiskInfo::new("/srv".to_string(), fs, thresholds, None, Default::default(), &limits, &state);
+        assert_eq!((disk.alerts.len(), disk.pending_alerts[0].checks), (0, 1));
+    }
+
+    #[test]
+    fn sector_warning_tier_is_opt_in() {
+        let sectors = |yaml: &str, count: u64| {
+            let mut disk = testing::disk("/srv", 1_000, 500, testing::thresholds(yaml));
+            disk.smart.reallocated_sectors = Some(count);
+            evaluate(&disk, &limits(yaml), &DiskHistory::new()).iter()
+                .find(|a| a.condition == Condition::ReallocatedSectors)
+                .map(|a| a.severity)
+        };
+        assert_eq!(sectors("{}", 3), None);
+        assert_eq!(sectors("{}", 49), None);
+        assert_eq!(sectors("{}", 50), Some(Severity::Critical));
+        assert_eq!(sectors("sector_warning_count: 1", 0), None);
+        assert_eq!(sectors("sector_warning_count: 1", 3), Some(Severity::Warning));
+        assert_eq!(sectors("sector_warning_count: 1\nsector_critical_count: 10", 10), Some(Severity::Critical));
+    }
+}
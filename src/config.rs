@@ -6,21 +6,55 @@ pub const CONFIG_PATH: &str = "config.yaml";
 
 #[derive(serde::Deserialize, Debug)]
 pub struct Config {
-    pub mail_enabled: bool,
-    pub smtp_server: String,
-    pub smtp_port: u16,
-    pub smtp_user: String,
-    pub smtp_pass: String,
-    pub email_from: String,
-    pub email_to: String,
+    // SMTP settings of the email notifier; required only while an email notifier is in use
+    pub mail_enabled: Option<bool>, // Set to false to stop sending email (default: true)
+    pub smtp_server: Option<String>,
+    pub smtp_port: Option<u16>,
+    pub smtp_user: Option<String>, // Leave out or blank when the server needs no authentication
+    pub smtp_pass: Option<String>,
+    pub email_from: Option<String>,
+    pub email_to: Option<String>, // Comma-separated recipients
     pub smtp_security: Option<String>, // "none", "starttls", "ssl"
     pub threshold_percent: Option<f64>, // Disk space threshold percentage
+    pub critical_threshold_percent: Option<f64>, // Critical disk space threshold percentage
+    pub min_free: Option<crate::units::ByteSize>, // Absolute free space threshold, e.g. "20GiB"
+    pub critical_min_free: Option<crate::units::ByteSize>, // Critical absolute free space threshold
+    pub threshold_require: Option<crate::rules::Require>, // "either" (default) or "both" of threshold_percent and min_free
+    pub inode_threshold_percent: Option<f64>, // Free inode threshold percentage (default: 10)
+    pub critical_inode_threshold_percent: Option<f64>, // Critical free inode threshold percentage
+    pub temperature_warning: Option<i64>, // Disk temperature warning level in Celsius (default: 55)
+    pub temperature_critical: Option<i64>, // Disk temperature critical level in Celsius (default: 65)
+    pub sector_warning_count: Option<u64>, // Reallocated/pending/uncorrectable sectors for a warning (default: none, only the critical tier)
+    pub sector_critical_count: Option<u64>, // Reallocated/pending/uncorrectable sectors for a critical alert (default: 50)
+    pub counter_increase_critical: Option<u64>, // Increase of a SMART error counter since the last run for a critical alert (default: 10)
     pub send_mail_on_unknown_status: Option<bool>,
     pub debug: Option<bool>, // Enable debug output
     pub health_check_enabled: Option<bool>, // Enable/disable disk health checks (default: true)
     pub smart_enabled: Option<bool>, // Enable/disable SMART-based alerts (default: true)
     pub friendly_name: Option<String>, // New: single friendly name
     pub excluded_disks: Option<Vec<String>>, // List of disks to exclude (drive letters or device names)
+    pub monitor_removable: Option<bool>, // Also monitor filesystems under /media, /mnt and /run/media (default: false)
+    pub smart_attribute_warning_margin: Option<u64>, // Warn when a SMART attribute's value is within this many points of its threshold (default: 0, off)
+    pub nvme_percentage_used_limit: Option<f64>, // Alert when NVMe wear (percentage used) exceeds this (default: 90)
+    pub rules: Option<Vec<crate::rules::Rule>>, // Per-filesystem thresholds, first match wins
+    pub state_file: Option<String>, // Where notified alerts are remembered between runs (default: /var/lib/diskmon/state.json)
+    pub renotify_interval: Option<crate::units::HumanDuration>, // Repeat a notification for an unchanged alert after this long (default: 24h)
+    pub send_resolved: Option<bool>, // Send a RESOLVED email when an alert clears (default: true)
+    pub clear_margin: Option<f64>, // Percentage points above a space/inode threshold before an active alert clears (default: 0)
+    pub alert_after_checks: Option<u32>, // Consecutive checks a condition must hold before it fires (default: 1)
+    pub history_enabled: Option<bool>, // Record every run's samples in the history database (default: true)
+    pub history_db: Option<String>, // SQLite history database (default: /var/lib/diskmon/history.db)
+    pub history_retention: Option<crate::units::HumanDuration>, // Delete samples older than this (default: 90d)
+    pub history_interval: Option<crate::units::HumanDuration>, // Minimum time between recorded samples, 0 records every run (default: 5m)
+    pub forecast_window: Option<crate::units::HumanDuration>, // History used to estimate the fill rate (default: 7d)
+    pub alert_if_full_within: Option<crate::units::HumanDuration>, // Alert when a filesystem is forecast to be full within this long
+    pub critical_if_full_within: Option<crate::units::HumanDuration>, // Report as critical when forecast to be full within this long
+    pub check_interval: Option<crate::units::HumanDuration>, // Daemon mode: how often space and inodes are checked (default: 60s)
+    pub smart_interval: Option<crate::units::HumanDuration>, // Daemon mode: how often SMART data is refreshed (default: 6h)
+    pub daily_report_time: Option<String>, // Daemon mode: local time "HH:MM" of the daily full report (default: none)
+    pub http_listen: Option<String>, // Daemon mode: serve /metrics and /health on this address, e.g. "127.0.0.1:9586" (default: off)
+    pub http_token: Option<String>, // Require "Authorization: Bearer <token>" for the HTTP endpoints
+    pub notifiers: Option<Vec<crate::notifiers::NotifierConfig>>, // Notification channels (default: email only)
 }
 
 pub fn load_config<P: AsRef<Path>>(path: P) -> Result<Config, String> {
@@ -65,27 +99,33 @@ fn apply_env_overrides(mut config: Config) -> Config {
     // Override SMTP credentials from environment variables if available
     if let Ok(smtp_user) = env::var("DISKMON_SMTP_USER") {
         if !smtp_user.trim().is_empty() {
-            config.smtp_user = smtp_user;
+            config.smtp_user = Some(smtp_user);
         }
     }
     
     if let Ok(smtp_pass) = env::var("DISKMON_SMTP_PASS") {
         if !smtp_pass.trim().is_empty() {
-            config.smtp_pass = smtp_pass;
+            config.smtp_pass = Some(smtp_pass);
         }
     }
     
     if let Ok(email_from) = env::var("DISKMON_EMAIL_FROM") {
         if !email_from.trim().is_empty() {
-            config.email_from = email_from;
+            config.email_from = Some(email_from);
         }
     }
     
     if let Ok(email_to) = env::var("DISKMON_EMAIL_TO") {
         if !email_to.trim().is_empty() {
-            config.email_to = email_to;
+            config.email_to = Some(email_to);
         }
     }
+
+    if let Ok(http_token) = env::var("DISKMON_HTTP_TOKEN")
+        && !http_token.trim().is_empty()
+    {
+        config.http_token = Some(http_token);
+    }
     
     config
 }
@@ -94,22 +134,14 @@ fn validate_config(config: &Config) -> Result<(), String> {
     let mut missing_keys = Vec::new();
     let mut warnings = Vec::new();
     
-    // Check for empty required string fields (except smtp_user and smtp_pass)
-    if config.smtp_server.trim().is_empty() {
-        missing_keys.push("smtp_server");
-    }
-    if config.email_from.trim().is_empty() {
-        missing_keys.push("email_from");
-    }
-    if config.email_to.trim().is_empty() {
-        missing_keys.push("email_to");
-    }
-    
-    // Check port is valid
-    if config.smtp_port == 0 {
-        missing_keys.push("smtp_port (must be 1-65535)");
+    // SMTP settings are only needed while an email notifier is in use; email_to only when one of
+    // them has no recipients of its own
+    let email_notifiers = crate::notifiers::email_notifiers(config);
+    if !email_notifiers.is_empty() {
+        let needs_email_to = email_notifiers.iter().any(|settings| settings.email_to.is_none());
+        validate_email_settings(config, needs_email_to, &mut missing_keys, &mut warnings);
     }
-    
+
     // Validate threshold_percent if provided
     if let Some(threshold) = config.threshold_percent {
         if threshold < 1.0 || threshold > 100.0 {
@@ -117,40 +149,144 @@ fn validate_config(config: &Config) -> Result<(), String> {
         }
     }
     
-    // Validate smtp_security
-    if let Some(ref sec) = config.smtp_security {
-        let sec = sec.to_lowercase();
-        if sec != "none" && sec != "starttls" && sec != "ssl" {
-            missing_keys.push("smtp_security (must be one of: none, starttls, ssl)");
+    // Validate critical_threshold_percent if provided
+    if config.critical_threshold_percent.is_some_and(|t| !(1.0..=100.0).contains(&t)) {
+        missing_keys.push("critical_threshold_percent (must be between 1.0 and 100.0)");
+    }
+    if let (Some(warning), Some(critical)) = (config.threshold_percent, config.critical_threshold_percent)
+        && critical > warning
+    {
+        missing_keys.push("critical_threshold_percent (must not be above threshold_percent)");
+    }
+    if let (Some(warning), Some(critical)) = (config.min_free, config.critical_min_free)
+        && critical > warning
+    {
+        missing_keys.push("critical_min_free (must not be above min_free)");
+    }
+
+    // Validate inode_threshold_percent if provided
+    if config.inode_threshold_percent.is_some_and(|t| !(1.0..=100.0).contains(&t)) {
+        missing_keys.push("inode_threshold_percent (must be between 1.0 and 100.0)");
+    }
+    if config.critical_inode_threshold_percent.is_some_and(|t| !(1.0..=100.0).contains(&t)) {
+        missing_keys.push("critical_inode_threshold_percent (must be between 1.0 and 100.0)");
+    }
+
+    // Warning/critical pairs are compared with the other key's default when only one is set;
+    // the error names the key that was set
+    match (config.inode_threshold_percent, config.critical_inode_threshold_percent) {
+        (Some(warning), Some(critical)) if critical > warning => {
+            missing_keys.push("critical_inode_threshold_percent (must not be above inode_threshold_percent)");
         }
-        if sec == "none" {
-            warnings.push("SMTP security is set to 'none'. This is insecure and not recommended.".to_string());
+        (None, Some(critical)) if critical > 10.0 => {
+            missing_keys.push("critical_inode_threshold_percent (must not be above the default inode_threshold_percent of 10)");
         }
-    }
-    
-    // Validate email addresses (basic check)
-    if !config.email_from.contains('@') {
-        missing_keys.push("email_from (must be a valid email address)");
+        _ => {}
     }
 
-    // Validate email_to: allow comma-separated recipients in a single string
-    let mut email_to_count = 0usize;
-    let mut email_to_invalid = false;
-    for addr in config.email_to.split(',') {
-        let a = addr.trim();
-        if a.is_empty() { continue; }
-        email_to_count += 1;
-        if !a.contains('@') {
-            email_to_invalid = true;
+    // Validate warning/critical pairs for temperature and sector counters
+    match (config.temperature_warning, config.temperature_critical) {
+        (Some(warning), Some(critical)) if warning > critical => {
+            missing_keys.push("temperature_critical (must not be below temperature_warning)");
+        }
+        (Some(warning), None) if warning > 65 => {
+            missing_keys.push("temperature_warning (must not be above the default temperature_critical of 65)");
+        }
+        (None, Some(critical)) if critical < 55 => {
+            missing_keys.push("temperature_critical (must not be below the default temperature_warning of 55)");
         }
+        _ => {}
     }
-    if email_to_count == 0 {
-        missing_keys.push("email_to (must be a valid email address)");
+    if config.sector_warning_count == Some(0) {
+        missing_keys.push("sector_warning_count (must be at least 1)");
     }
-    if email_to_invalid {
-        missing_keys.push("email_to (one or more recipients appear invalid)");
+    if config.sector_critical_count == Some(0) {
+        missing_keys.push("sector_critical_count (must be at least 1)");
     }
-    
+    match (config.sector_warning_count, config.sector_critical_count) {
+        (Some(warning), Some(critical)) if warning > critical => {
+            missing_keys.push("sector_critical_count (must not be below sector_warning_count)");
+        }
+        (Some(warning), None) if warning > 50 => {
+            missing_keys.push("sector_warning_count (must not be above the default sector_critical_count of 50)");
+        }
+        _ => {}
+    }
+    if config.counter_increase_critical == Some(0) {
+        missing_keys.push("counter_increase_critical (must be at least 1)");
+    }
+
+    // Validate nvme_percentage_used_limit if provided (NVMe reports 0-255%)
+    if config.nvme_percentage_used_limit.is_some_and(|l| !(1.0..=255.0).contains(&l)) {
+        missing_keys.push("nvme_percentage_used_limit (must be between 1.0 and 255.0)");
+    }
+
+    // Validate rules
+    let default_thresholds = crate::rules::Thresholds::defaults(config);
+    let rule_errors: Vec<String> = config.rules.iter().flatten().enumerate()
+        .flat_map(|(i, rule)| rule.validate(i, &default_thresholds))
+        .collect();
+    missing_keys.extend(rule_errors.iter().map(String::as_str));
+
+    // Validate hysteresis settings
+    if config.clear_margin.is_some_and(|m| !(0.0..=100.0).contains(&m)) {
+        missing_keys.push("clear_margin (must be between 0.0 and 100.0)");
+    }
+    if config.alert_after_checks == Some(0) {
+        missing_keys.push("alert_after_checks (must be at least 1)");
+    }
+
+    // Validate state_file if provided
+    if config.state_file.as_ref().is_some_and(|p| p.trim().is_empty()) {
+        missing_keys.push("state_file (must not be empty)");
+    }
+
+    // Validate history settings
+    if config.history_db.as_ref().is_some_and(|p| p.trim().is_empty()) {
+        missing_keys.push("history_db (must not be empty)");
+    }
+    if config.history_retention.is_some_and(|r| r.0 == 0) {
+        missing_keys.push("history_retention (must be greater than 0)");
+    }
+
+    // Validate forecast settings
+    if config.forecast_window.is_some_and(|w| w.0 < 3600) {
+        missing_keys.push("forecast_window (must be at least 1h)");
+    }
+    if let (Some(warning), Some(critical)) = (config.alert_if_full_within, config.critical_if_full_within)
+        && critical > warning
+    {
+        missing_keys.push("critical_if_full_within (must not be above alert_if_full_within)");
+    }
+
+    // Validate daemon settings
+    if config.check_interval.is_some_and(|i| i.0 < 10) {
+        missing_keys.push("check_interval (must be at least 10s)");
+    }
+    if config.smart_interval.is_some_and(|i| i.0 < 60) {
+        missing_keys.push("smart_interval (must be at least 1m)");
+    }
+    if config.daily_report_time.as_deref().is_some_and(|t| crate::daemon::parse_report_time(t).is_none()) {
+        missing_keys.push("daily_report_time (must be a time of day as HH:MM)");
+    }
+
+    // Validate HTTP endpoint settings
+    if config.http_listen.as_deref().is_some_and(|addr| addr.parse::<std::net::SocketAddr>().is_err()) {
+        missing_keys.push("http_listen (must be an address and port, e.g. 127.0.0.1:9586)");
+    }
+    if config.http_token.as_ref().is_some_and(|t| t.trim().is_empty()) {
+        missing_keys.push("http_token (must not be empty)");
+    }
+
+    // Validate notifiers
+    let notifier_errors: Vec<String> = config.notifiers.iter().flatten().enumerate()
+        .flat_map(|(i, notifier)| notifier.validate(i))
+        .collect();
+    missing_keys.extend(notifier_errors.iter().map(String::as_str));
+    if config.notifiers.as_ref().is_some_and(|n| n.is_empty()) {
+        warnings.push("notifiers is an empty list. No notifications will be sent.".to_string());
+    }
+
     // Warn if debug is enabled
     if config.debug.unwrap_or(false) {
         warnings.push("Debug mode is enabled. This may expose sensitive information in logs.".to_string());
@@ -178,9 +314,10 @@ fn validate_config(config: &Config) -> Result<(), String> {
                     warnings.push(format!("Invalid excluded disk '{}': must be a drive letter like 'C:'", disk));
                 }
             } else {
-                // Should be like "sda", "nvme0n1", etc.
-                if disk.contains('/') || disk.is_empty() {
-                    warnings.push(format!("Invalid excluded disk '{}': must be a device name like 'sda' or 'nvme0n1'", disk));
+                // Should be a device name like "sda", "nvme0n1", "md0", "vg0-data", a /dev path or a mount point
+                let disk = disk.trim();
+                if disk.contains('/') && !disk.starts_with('/') {
+                    warnings.push(format!("Invalid excluded disk '{}': must be a device name like 'sda' or 'nvme0n1', a /dev path or a mount point", disk));
                 }
             }
         }
@@ -194,3 +331,57 @@ fn validate_config(config: &Config) -> Result<(), String> {
     }
     Ok(())
 }
+
+fn validate_email_settings(config: &Config, needs_email_to: bool, missing_keys: &mut Vec<&str>, warnings: &mut Vec<String>) {
+    // smtp_user and smtp_pass may be left out for servers without authentication
+    if config.smtp_server.as_ref().is_none_or(|s| s.trim().is_empty()) {
+        missing_keys.push("smtp_server");
+    }
+    match config.smtp_port {
+        None => missing_keys.push("smtp_port"),
+        Some(0) => missing_keys.push("smtp_port (must be 1-65535)"),
+        Some(_) => {}
+    }
+
+    // Validate smtp_security
+    if let Some(ref sec) = config.smtp_security {
+        let sec = sec.to_lowercase();
+        if sec != "none" && sec != "starttls" && sec != "ssl" {
+            missing_keys.push("smtp_security (must be one of: none, starttls, ssl)");
+        }
+        if sec == "none" {
+            warnings.push("SMTP security is set to 'none'. This is insecure and not recommended.".to_string());
+        }
+    }
+
+    // Validate email addresses (basic check)
+    match config.email_from.as_deref().map(str::trim) {
+        None | Some("") => missing_keys.push("email_from"),
+        Some(email_from) if !email_from.contains('@') => missing_keys.push("email_from (must be a valid email address)"),
+        Some(_) => {}
+    }
+
+    // Validate email_to: allow comma-separated recipients in a single string
+    let Some(email_to) = &config.email_to else {
+        if needs_email_to {
+            missing_keys.push("email_to");
+        }
+        return;
+    };
+    let mut email_to_count = 0usize;
+    let mut email_to_invalid = false;
+    for addr in email_to.split(',') {
+        let a = addr.trim();
+        if a.is_empty() { continue; }
+        email_to_count += 1;
+        if !a.contains('@') {
+            email_to_invalid = true;
+        }
+    }
+    if email_to_count == 0 {
+        missing_keys.push("email_to (must be a valid email address)");
+    }
+    if email_to_invalid {
+        missing_keys.push("email_to (one or more recipients appear invalid)");
+    }
+}
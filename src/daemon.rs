@@ -0,0 +1,294 @@
+// Daemon mode.
+//
+// `diskmon-mail daemon` keeps running instead of relying on a cron job or timer: free space and
+// inodes are checked every check_interval, SMART data is refreshed every smart_interval and reused
+// in between, and the full report that `--force-mail` sends is mailed daily at daily_report_time.
+// The history database stays open between checks and gets a sample every history_interval.
+// Alert state is saved after every check, so SIGTERM or Ctrl-C simply stop the loop once the
+// current check has finished. SIGHUP reloads config.yaml, including debug; a config that fails
+// validation is rejected and the running one kept. Under systemd (Type=notify) the daemon reports
+// readiness and status and pings the watchdog while it waits and after each step of a check
+// (collecting disks and SMART data, notifying, the daily report), so a step that hangs gets the
+// service restarted. WatchdogSec has to cover the longest step.
+// With http_listen set, /metrics and /health serve the results of the last check (see http.rs).
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local, NaiveTime};
+use log::{debug, error, info, warn};
+
+use crate::smart::{HealthMethod, SmartReport};
+use crate::units::HumanDuration;
+use crate::{config, history, http, metrics, notifiers, state, system, systemd};
+
+/// Parse daily_report_time ("HH:MM", local time).
+pub fn parse_report_time(text: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(text.trim(), "%H:%M").ok()
+}
+
+// The first occurrence of `time` strictly after `now`. A time skipped by a DST change falls back
+// to the same time on the following day.
+fn next_report(time: NaiveTime, now: DateTime<Local>) -> DateTime<Local> {
+    let mut date = now.date_naive();
+    loop {
+        if let Some(at) = date.and_time(time).and_local_timezone(Local).earliest()
+            && at > now
+        {
+            return at;
+        }
+        date = date.succ_opt().unwrap_or(date);
+    }
+}
+
+// Timing settings from the config, recomputed after a reload
+struct Schedule {
+    check_interval: HumanDuration,
+    smart_interval: HumanDuration,
+    report_time: Option<NaiveTime>,
+}
+
+impl Schedule {
+    fn from_config(cfg: &config::Config) -> Schedule {
+        Schedule {
+            check_interval: cfg.check_interval.unwrap_or(HumanDuration(60)),
+            smart_interval: cfg.smart_interval.unwrap_or(HumanDuration(6 * 3600)),
+            report_time: cfg.daily_report_time.as_deref().and_then(parse_report_time),
+        }
+    }
+
+    fn next_report(&self) -> Option<DateTime<Local>> {
+        self.report_time.map(|time| next_report(time, Local::now()))
+    }
+
+    fn describe(&self, report_due: Option<DateTime<Local>>) -> String {
+        format!("checking every {}, SMART every {}, daily report {}",
+                self.check_interval, self.smart_interval,
+                report_due.map_or("disabled".to_string(), |at| format!("next at {}", at.format("%d-%m-%Y %H:%M"))))
+    }
+}
+
+enum Signal {
+    Terminate(&'static str),
+    Reload,
+}
+
+// Signals the daemon reacts to. The listeners are installed once up front so that a signal
+// arriving during a check is not lost.
+struct Signals {
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    interrupt: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    hangup: tokio::signal::unix::Signal,
+}
+
+impl Signals {
+    fn install() -> Result<Signals, String> {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+            Ok(Signals {
+                terminate: signal(SignalKind::terminate()).map_err(|e| format!("Failed to install SIGTERM handler: {e}"))?,
+                interrupt: signal(SignalKind::interrupt()).map_err(|e| format!("Failed to install SIGINT handler: {e}"))?,
+                hangup: signal(SignalKind::hangup()).map_err(|e| format!("Failed to install SIGHUP handler: {e}"))?,
+            })
+        }
+        #[cfg(not(unix))]
+        Ok(Signals {})
+    }
+
+    async fn next(&mut self) -> Signal {
+        #[cfg(unix)]
+        tokio::select! {
+            _ = self.terminate.recv() => Signal::Terminate("SIGTERM"),
+            _ = self.interrupt.recv() => Signal::Terminate("SIGINT"),
+            _ = self.hangup.recv() => Signal::Reload,
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = tokio::signal::ctrl_c().await;
+            Signal::Terminate("Ctrl-C")
+        }
+    }
+}
+
+// Tell the systemd watchdog that a check made progress. Pings only come from finished steps, so a
+// step that hangs (a smartctl call stuck in the kernel, a notifier that never returns) stops them
+// and gets the daemon restarted.
+fn progress(watchdog: Option<Duration>) {
+    if watchdog.is_some() {
+        systemd::notify("WATCHDOG=1");
+    }
+}
+
+fn load_state(path: &Path) -> state::State {
+    state::State::load(path).unwrap_or_else(|e| {
+        warn!("{}; starting with an empty alert state", e);
+        state::State::default()
+    })
+}
+
+/// Run checks until a termination signal arrives. Returns the process exit code.
+pub async fn run(mut cfg: config::Config, system_info: &system::SystemInfo, smart_timeout: u64, textfile_dir: Option<&Path>,
+                 mut debug: bool) -> i32 {
+    let mut signals = match Signals::install() {
+        Ok(signals) => signals,
+        Err(e) => {
+            error!("{}", e);
+            return crate::EXIT_ERROR;
+        }
+    };
+
+    let mut schedule = Schedule::from_config(&cfg);
+    let mut state_path = PathBuf::from(cfg.state_file.as_deref().unwrap_or(state::DEFAULT_STATE_PATH));
+    let mut alert_state = load_state(&state_path);
+    let mut usage_history = crate::open_history(&cfg);
+
+    let mut smart_cache: HashMap<String, SmartReport> = HashMap::new();
+    let mut smart_refreshed: Option<Instant> = None;
+    let mut report_due = schedule.next_report();
+    let watchdog = systemd::watchdog_interval();
+
+    let shared = http::Shared::new(cfg.http_token.clone());
+    let http_listen = cfg.http_listen.clone();
+    if let Some(addr) = &http_listen {
+        match http::bind(addr).await {
+            Ok(listener) => {
+                info!("Serving /metrics and /health on http://{}{}", addr,
+                      if cfg.http_token.is_some() { " (bearer token required)" } else { "" });
+                tokio::spawn(http::serve(listener, shared.clone()));
+            }
+            Err(e) => {
+                error!("{}", e);
+                return crate::EXIT_ERROR;
+            }
+        }
+    }
+
+    info!("Daemon started: {}", schedule.describe(report_due));
+    if let Some(interval) = watchdog {
+        info!("systemd watchdog enabled, pinging every {}s", interval.as_secs_f64());
+    }
+    systemd::notify("READY=1\nSTATUS=Running the first check");
+
+    loop {
+        let refresh_smart = smart_refreshed.is_none_or(|at| at.elapsed() >= schedule.smart_interval.as_duration());
+        let cache = (!refresh_smart).then_some(&smart_cache);
+        progress(watchdog);
+        let disks = crate::get_monitored_disks(&cfg, debug, smart_timeout, &alert_state, cache, usage_history.as_ref()).await;
+        progress(watchdog);
+        if refresh_smart {
+            smart_cache.clear();
+            smart_refreshed = Some(Instant::now());
+        }
+        // Keep new readings until the next refresh, except timeouts: those drives are probed again
+        // on the next check instead of reporting a stale timeout for the whole smart_interval
+        let mut cached = 0;
+        for disk in disks.iter().filter(|disk| disk.smart.method != HealthMethod::Timeout) {
+            if let Entry::Vacant(entry) = smart_cache.entry(disk.smart_target()) {
+                entry.insert(disk.smart.clone());
+                cached += 1;
+            }
+        }
+        if debug && cached > 0 {
+            debug!("Cached SMART data for {} disk(s)", cached);
+        }
+
+        if disks.is_empty() {
+            warn!("No monitored disks found");
+            systemd::notify("WATCHDOG=1\nSTATUS=No monitored disks found");
+        } else {
+            crate::record_history(usage_history.as_mut(), &cfg, &disks, debug);
+            if let Some(dir) = textfile_dir
+                && let Err(e) = metrics::write_textfile(dir, &disks, crate::unix_now())
+            {
+                error!("{}", e);
+            }
+            let (sent, errors) = crate::process_alerts(&cfg, &disks, system_info, &mut alert_state, &state_path, debug, true).await;
+            shared.update(&disks, &alert_state, system_info, crate::unix_now());
+            let firing: usize = disks.iter().map(|disk| disk.alerts.len()).sum();
+            if debug || sent > 0 || errors {
+                info!("Checked {} disk(s): {} active alert(s), {} notification(s) sent{}",
+                      disks.len(), firing, sent, if errors { ", with errors" } else { "" });
+            }
+            systemd::notify(&format!("WATCHDOG=1\nSTATUS=Last check {}: {} disk(s), {} active alert(s)",
+                                     crate::format_local_time(crate::unix_now()), disks.len(), firing));
+
+            if let (Some(time), Some(due)) = (schedule.report_time, report_due)
+                && Local::now() >= due
+            {
+                info!("Sending the daily report");
+                let report = notifiers::Report::disks(&cfg, notifiers::Kind::Forced, system_info, &disks, debug);
+                for (name, e) in notifiers::send(&cfg, &report).await.failed {
+                    error!("Failed to send daily report via {}: {}", name, e);
+                }
+                progress(watchdog);
+                report_due = Some(next_report(time, Local::now()));
+            }
+        }
+
+        // Wait for the next check, pinging the watchdog and handling signals meanwhile
+        let next_check = tokio::time::Instant::now() + schedule.check_interval.as_duration();
+        let stop = loop {
+            let ping = async {
+                match watchdog {
+                    Some(interval) => tokio::time::sleep(interval).await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                _ = tokio::time::sleep_until(next_check) => break false,
+                _ = ping => systemd::notify("WATCHDOG=1"),
+                signal = signals.next() => match signal {
+                    Signal::Terminate(name) => {
+                        info!("Received {}, shutting down", name);
+                        break true;
+                    }
+                    Signal::Reload => {
+                        systemd::notify("RELOADING=1");
+                        match config::load_config(config::CONFIG_PATH) {
+                            Ok(new_cfg) => {
+                                let new_state_path = PathBuf::from(new_cfg.state_file.as_deref().unwrap_or(state::DEFAULT_STATE_PATH));
+                                if new_state_path != state_path {
+                                    alert_state = load_state(&new_state_path);
+                                    state_path = new_state_path;
+                                }
+                                if new_cfg.history_enabled != cfg.history_enabled
+                                    || history::database_path(&new_cfg) != history::database_path(&cfg)
+                                {
+                                    usage_history = crate::open_history(&new_cfg);
+                                }
+                                if new_cfg.http_listen != http_listen {
+                                    warn!("http_listen changed; restart the daemon to listen on the new address");
+                                }
+                                shared.set_token(new_cfg.http_token.clone());
+                                cfg = new_cfg;
+                                debug = cfg.debug.unwrap_or(false);
+                                log::set_max_level(crate::log_level(debug));
+                                schedule = Schedule::from_config(&cfg);
+                                report_due = schedule.next_report();
+                                info!("Reloaded {}: {}", config::CONFIG_PATH, schedule.describe(report_due));
+                                systemd::notify("READY=1");
+                                // Check right away with the new settings
+                                break false;
+                            }
+                            Err(e) => {
+                                error!("Rejected reloaded configuration, keeping the current one: {}", e);
+                                systemd::notify("READY=1");
+                            }
+                        }
+                    }
+                },
+            }
+        };
+        if stop {
+            break;
+        }
+    }
+    systemd::notify("STOPPING=1");
+    0
+}
@@ -0,0 +1,171 @@
+// Disk-full forecasting.
+//
+// The fill rate of a filesystem is estimated from its available_space history with the Theil-Sen
+// estimator (median of pairwise slopes), which ignores one-off spikes such as a large temporary
+// file that was deleted again. From the rate and the current available space we extrapolate when
+// the filesystem crosses its warning threshold and when it is full.
+
+use crate::alerts::Severity;
+use crate::rules::Thresholds;
+use crate::units::{ByteSize, format_elapsed};
+
+// Pairwise slopes are quadratic in the number of points; longer series are thinned out evenly
+const MAX_POINTS: usize = 200;
+const MIN_POINTS: usize = 3;
+const MIN_SPAN_SECS: i64 = 3600;
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct Forecast {
+    pub bytes_per_day: f64, // Change of available space per day, negative while filling up
+    pub samples: usize,
+    pub span_secs: u64, // Time covered by the samples
+    pub full_in: Option<u64>, // Seconds until available space reaches 0, None when not filling up
+    pub threshold_in: Option<u64>, // Seconds until the warning threshold is crossed, None when not filling up or already crossed
+}
+
+impl Forecast {
+    pub fn describe(&self) -> String {
+        if self.bytes_per_day >= 0.0 {
+            return format!("not filling up (+{}/day over {})", ByteSize(self.bytes_per_day as u64), format_elapsed(self.span_secs));
+        }
+        let mut text = format!("filling at {}/day over {}", ByteSize(-self.bytes_per_day as u64), format_elapsed(self.span_secs));
+        if let Some(secs) = self.threshold_in {
+            text.push_str(&format!(", threshold in ~{}", format_elapsed(secs)));
+        }
+        if let Some(secs) = self.full_in {
+            text.push_str(&format!(", full in ~{}", format_elapsed(secs)));
+        }
+        text
+    }
+}
+
+fn theil_sen_slope(points: &[(i64, u64)]) -> Option<f64> {
+    let mut slopes = Vec::with_capacity(points.len() * (points.len() - 1) / 2);
+    for (i, (t1, a1)) in points.iter().enumerate() {
+        for (t2, a2) in &points[i + 1..] {
+            if t2 != t1 {
+                slopes.push((*a2 as f64 - *a1 as f64) / (t2 - t1) as f64);
+            }
+        }
+    }
+    if slopes.is_empty() {
+        return None;
+    }
+    slopes.sort_by(f64::total_cmp);
+    let mid = slopes.len() / 2;
+    Some(if slopes.len().is_multiple_of(2) { (slopes[mid - 1] + slopes[mid]) / 2.0 } else { slopes[mid] })
+}
+
+/// Estimate from (timestamp, available_space) points, oldest first, ending with the current
+/// measurement. None when there is too little history.
+pub fn estimate(points: &[(i64, u64)], thresholds: &Thresholds, total: u64) -> Option<Forecast> {
+    let (first, last) = (points.first()?, points.last()?);
+    let span = last.0 - first.0;
+    if points.len() < MIN_POINTS || span < MIN_SPAN_SECS {
+        return None;
+    }
+    let thinned: Vec<(i64, u64)> = if points.len() > MAX_POINTS {
+        (0..MAX_POINTS).map(|i| points[i * (points.len() - 1) / (MAX_POINTS - 1)]).collect()
+    } else {
+        points.to_vec()
+    };
+    let per_second = theil_sen_slope(&thinned)?;
+
+    let available = last.1 as f64;
+    let threshold = thresholds.limit_bytes(Severity::Warning, total).unwrap_or(0) as f64;
+    let (full_in, threshold_in) = if per_second < 0.0 {
+        let eta = |target: f64| (available > target).then(|| ((available - target) / -per_second) as u64);
+        (eta(0.0), eta(threshold))
+    } else {
+        (None, None)
+    };
+    Some(Forecast {
+        bytes_per_day: per_second * 86_400.0,
+        samples: points.len(),
+        span_secs: span as u64,
+        full_in,
+        threshold_in,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const HOUR: i64 = 3_600;
+
+    fn thresholds(warning_percent: f64) -> Thresholds {
+        testing::thresholds(&format!("threshold_percent: {}", warning_percent))
+    }
+
+    // Available space shrinking by 1000 bytes an hour from 500000
+    fn filling(hours: i64) -> Vec<(i64, u64)> {
+        (0..=hours).map(|h| (h * HOUR, (500_000 - h * 1_000) as u64)).collect()
+    }
+
+    fn assert_close(actual: Option<u64>, expected: u64) {
+        let actual = actual.expect("no estimate");
+        assert!(actual.abs_diff(expected) <= 1, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn slope_is_median_of_pairwise_slopes() {
+        assert_eq!(theil_sen_slope(&[(0, 100), (10, 80), (20, 60)]), Some(-2.0));
+        // Even number of slopes: mean of the middle two
+        assert_eq!(theil_sen_slope(&[(0, 0), (1, 1), (2, 4)]), Some(2.0));
+        assert_eq!(theil_sen_slope(&[(5, 100), (5, 200)]), None);
+    }
+
+    #[test]
+    fn slope_ignores_a_single_spike() {
+        let mut points = filling(4);
+        points[2].1 = 100_000; // Large temporary file, deleted again
+        let slope = theil_sen_slope(&points).unwrap();
+        assert!((slope * HOUR as f64 + 1_000.0).abs() < 1e-6, "{}", slope);
+    }
+
+    #[test]
+    fn estimate_needs_enough_history() {
+        let limits = thresholds(10.0);
+        assert!(estimate(&[], &limits, 1_000_000).is_none());
+        assert!(estimate(&filling(1), &limits, 1_000_000).is_none());
+        let short: Vec<(i64, u64)> = (0..10).map(|i| (i * 60, 500_000 - i as u64)).collect();
+        assert!(estimate(&short, &limits, 1_000_000).is_none());
+        assert!(estimate(&filling(2), &limits, 1_000_000).is_some());
+    }
+
+    #[test]
+    fn estimate_extrapolates_full_and_threshold() {
+        let forecast = estimate(&filling(4), &thresholds(10.0), 1_000_000).unwrap();
+        assert!((forecast.bytes_per_day + 24_000.0).abs() < 1e-6);
+        assert_eq!((forecast.samples, forecast.span_secs), (5, 4 * HOUR as u64));
+        // 496000 bytes left at 1000 an hour, the warning threshold is at 100000
+        assert_close(forecast.full_in, 496 * HOUR as u64);
+        assert_close(forecast.threshold_in, 396 * HOUR as u64);
+        assert!(forecast.describe().contains(", threshold in ~16d 12h, full in ~20d 16h"), "{}", forecast.describe());
+    }
+
+    #[test]
+    fn estimate_without_threshold_eta_once_crossed() {
+        let forecast = estimate(&filling(4), &thresholds(60.0), 1_000_000).unwrap();
+        assert!(forecast.full_in.is_some());
+        assert_eq!(forecast.threshold_in, None);
+    }
+
+    #[test]
+    fn estimate_not_filling_up() {
+        let points: Vec<(i64, u64)> = filling(4).into_iter().rev().enumerate().map(|(i, (_, a))| (i as i64 * HOUR, a)).collect();
+        let forecast = estimate(&points, &thresholds(10.0), 1_000_000).unwrap();
+        assert!(forecast.bytes_per_day > 0.0);
+        assert_eq!((forecast.full_in, forecast.threshold_in), (None, None));
+        assert!(forecast.describe().starts_with("not filling up"));
+    }
+
+    #[test]
+    fn estimate_thins_long_series() {
+        let forecast = estimate(&filling(400), &thresholds(10.0), 1_000_000).unwrap();
+        assert_eq!(forecast.samples, 401);
+        assert!((forecast.bytes_per_day + 24_000.0).abs() < 1e-6);
+    }
+}
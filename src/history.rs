@@ -0,0 +1,405 @@
+// Usage history.
+//
+// Every run appends one sample per monitored filesystem (space, inodes, temperature and SMART
+// counters) to a local SQLite database, at most once per history_interval, and prunes samples
+// older than history_retention. The daemon keeps the database open for its whole lifetime. The
+// `history` subcommand prints per-mount time series and min/max/avg over a window, or exports
+// them as CSV or JSON.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use colored::*;
+use rusqlite::{Connection, params};
+
+use crate::DiskInfo;
+use crate::config::Config;
+use crate::units::{ByteSize, HumanDuration};
+
+#[cfg(windows)]
+pub const DEFAULT_HISTORY_PATH: &str = "C:\\ProgramData\\diskmon\\history.db";
+#[cfg(not(windows))]
+pub const DEFAULT_HISTORY_PATH: &str = "/var/lib/diskmon/history.db";
+
+const DEFAULT_RETENTION: HumanDuration = HumanDuration(90 * 86_400);
+const DEFAULT_INTERVAL: HumanDuration = HumanDuration(5 * 60);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS samples (
+    timestamp INTEGER NOT NULL,
+    mount_point TEXT NOT NULL,
+    display_name TEXT NOT NULL,
+    file_system TEXT NOT NULL,
+    total_space INTEGER NOT NULL,
+    available_space INTEGER NOT NULL,
+    free_space_percent REAL NOT NULL,
+    total_inodes INTEGER NOT NULL,
+    free_inodes INTEGER NOT NULL,
+    inode_free_percent REAL,
+    temperature INTEGER,
+    reallocated_sectors INTEGER,
+    pending_sectors INTEGER,
+    uncorrectable_sectors INTEGER,
+    media_errors INTEGER,
+    health TEXT NOT NULL,
+    severity TEXT
+);
+CREATE INDEX IF NOT EXISTS samples_mount_time ON samples (mount_point, timestamp);
+CREATE INDEX IF NOT EXISTS samples_time ON samples (timestamp);
+";
+
+/// Output format of the `history` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum HistoryFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+/// Arguments of the `history` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct HistoryArgs {
+    /// Only show this mount point (can be repeated)
+    #[arg(long = "mount")]
+    pub mounts: Vec<String>,
+    /// Time window to show, e.g. 24h, 7d or 4w
+    #[arg(long, default_value = "7d")]
+    pub since: HumanDuration,
+    /// Output format
+    #[arg(long, value_enum, default_value = "table")]
+    pub format: HistoryFormat,
+    /// Only print min/max/avg per mount point
+    #[arg(long)]
+    pub summary: bool,
+}
+
+/// One filesystem observed on one run. Timestamps are Unix seconds.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Sample {
+    pub timestamp: i64,
+    pub mount_point: String,
+    pub display_name: String,
+    pub file_system: String,
+    pub total_space: u64,
+    pub available_space: u64,
+    pub free_space_percent: f64,
+    pub total_inodes: u64,
+    pub free_inodes: u64,
+    pub inode_free_percent: Option<f64>,
+    pub temperature: Option<i64>,
+    pub reallocated_sectors: Option<u64>,
+    pub pending_sectors: Option<u64>,
+    pub uncorrectable_sectors: Option<u64>,
+    pub media_errors: Option<u64>,
+    pub health: String,
+    pub severity: Option<String>,
+}
+
+impl Sample {
+    pub fn from_disk(disk: &DiskInfo, timestamp: i64) -> Self {
+        Sample {
+            timestamp,
+            mount_point: disk.mount_point.clone(),
+            display_name: disk.display_name.clone(),
+            file_system: disk.file_system.clone(),
+            total_space: disk.total_space,
+            available_space: disk.available_space,
+            free_space_percent: disk.free_space_percent,
+            total_inodes: disk.total_inodes,
+            free_inodes: disk.free_inodes,
+            inode_free_percent: disk.inode_free_percent,
+            temperature: disk.smart.temperature,
+            reallocated_sectors: disk.smart.reallocated_sectors,
+            pending_sectors: disk.smart.pending_sectors,
+            uncorrectable_sectors: disk.smart.uncorrectable_sectors,
+            media_errors: disk.smart.nvme.as_ref().map(|n| n.media_errors),
+            health: disk.smart.health.as_str().to_string(),
+            severity: disk.severity().map(|s| s.as_str().to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct Stats {
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+}
+
+impl Stats {
+    fn of(values: impl Iterator<Item = f64>) -> Option<Stats> {
+        let (mut min, mut max, mut sum, mut count) = (f64::INFINITY, f64::NEG_INFINITY, 0.0, 0usize);
+        for value in values {
+            min = min.min(value);
+            max = max.max(value);
+            sum += value;
+            count += 1;
+        }
+        (count > 0).then(|| Stats { min, max, avg: sum / count as f64 })
+    }
+}
+
+/// min/max/avg of one mount point's samples.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Summary {
+    pub samples: usize,
+    pub first: i64,
+    pub last: i64,
+    pub free_space_percent: Option<Stats>,
+    pub available_space: Option<Stats>,
+    pub inode_free_percent: Option<Stats>,
+    pub temperature: Option<Stats>,
+}
+
+impl Summary {
+    fn of(samples: &[Sample]) -> Summary {
+        Summary {
+            samples: samples.len(),
+            first: samples.first().map_or(0, |s| s.timestamp),
+            last: samples.last().map_or(0, |s| s.timestamp),
+            free_space_percent: Stats::of(samples.iter().map(|s| s.free_space_percent)),
+            available_space: Stats::of(samples.iter().map(|s| s.available_space as f64)),
+            inode_free_percent: Stats::of(samples.iter().filter_map(|s| s.inode_free_percent)),
+            temperature: Stats::of(samples.iter().filter_map(|s| s.temperature).map(|t| t as f64)),
+        }
+    }
+}
+
+pub struct History {
+    conn: Connection,
+    last_recorded: Option<i64>, // Timestamp of the newest samples, to space them history_interval apart
+}
+
+impl History {
+    /// Open (and create if needed) the history database.
+    pub fn open(path: &Path) -> Result<History, String> {
+        if let Some(dir) = path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create history directory {}: {e}", dir.display()))?;
+        }
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open history database {}: {e}", path.display()))?;
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| format!("Failed to initialize history database {}: {e}", path.display()))?;
+        let last_recorded = conn.query_row("SELECT MAX(timestamp) FROM samples", [], |row| row.get(0))
+            .map_err(|e| format!("Failed to query history database {}: {e}", path.display()))?;
+        Ok(History { conn, last_recorded })
+    }
+
+    /// Append this run's samples and apply the retention policy. Returns how many old samples
+    /// were pruned, or None when the newest samples are younger than history_interval.
+    pub fn record_run(&mut self, cfg: &Config, disks: &[DiskInfo], now: i64) -> Result<Option<usize>, String> {
+        let interval = cfg.history_interval.unwrap_or(DEFAULT_INTERVAL).0 as i64;
+        // Allow 10% of jitter, so runs scheduled exactly one interval apart are all recorded
+        if self.last_recorded.is_some_and(|last| now - last < interval - interval / 10) {
+            return Ok(None);
+        }
+        let samples: Vec<Sample> = disks.iter().map(|disk| Sample::from_disk(disk, now)).collect();
+        self.record(&samples)?;
+        self.last_recorded = Some(now);
+        let retention = cfg.history_retention.unwrap_or(DEFAULT_RETENTION);
+        self.prune(now.saturating_sub(retention.0 as i64)).map(Some)
+    }
+
+    pub fn record(&mut self, samples: &[Sample]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| format!("Failed to record history: {e}"))?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO samples (timestamp, mount_point, display_name, file_system, total_space, available_space,
+                    free_space_percent, total_inodes, free_inodes, inode_free_percent, temperature, reallocated_sectors,
+                    pending_sectors, uncorrectable_sectors, media_errors, health, severity)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+            ).map_err(|e| format!("Failed to record history: {e}"))?;
+            for s in samples {
+                insert.execute(params![
+                    s.timestamp, s.mount_point, s.display_name, s.file_system, s.total_space, s.available_space,
+                    s.free_space_percent, s.total_inodes, s.free_inodes, s.inode_free_percent, s.temperature,
+                    s.reallocated_sectors, s.pending_sectors, s.uncorrectable_sectors, s.media_errors, s.health,
+                    s.severity,
+                ]).map_err(|e| format!("Failed to record history: {e}"))?;
+            }
+        }
+        tx.commit().map_err(|e| format!("Failed to record history: {e}"))
+    }
+
+    /// Delete samples older than `before`, returning how many were removed.
+    pub fn prune(&self, before: i64) -> Result<usize, String> {
+        self.conn.execute("DELETE FROM samples WHERE timestamp < ?1", params![before])
+            .map_err(|e| format!("Failed to prune history: {e}"))
+    }
+
+    /// (timestamp, available_space) of one mount point since `since`, oldest first.
+    pub fn available_series(&self, mount_point: &str, since: i64) -> Result<Vec<(i64, u64)>, String> {
+        let mut query = self.conn.prepare(
+            "SELECT timestamp, available_space FROM samples
+             WHERE mount_point = ?1 AND timestamp >= ?2 ORDER BY timestamp",
+        ).map_err(|e| format!("Failed to query history: {e}"))?;
+        let rows = query.query_map(params![mount_point, since], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Failed to query history: {e}"))?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(|e| format!("Failed to read history: {e}"))
+    }
+
+    /// Samples since `since`, oldest first, optionally limited to some mount points.
+    pub fn samples(&self, since: i64, mounts: &[String]) -> Result<Vec<Sample>, String> {
+        let mut query = self.conn.prepare(
+            "SELECT timestamp, mount_point, display_name, file_system, total_space, available_space,
+                free_space_percent, total_inodes, free_inodes, inode_free_percent, temperature, reallocated_sectors,
+                pending_sectors, uncorrectable_sectors, media_errors, health, severity
+             FROM samples WHERE timestamp >= ?1 ORDER BY mount_point, timestamp",
+        ).map_err(|e| format!("Failed to query history: {e}"))?;
+        let rows = query.query_map(params![since], |row| {
+            Ok(Sample {
+                timestamp: row.get(0)?,
+                mount_point: row.get(1)?,
+                display_name: row.get(2)?,
+                file_system: row.get(3)?,
+                total_space: row.get(4)?,
+                available_space: row.get(5)?,
+                free_space_percent: row.get(6)?,
+                total_inodes: row.get(7)?,
+                free_inodes: row.get(8)?,
+                inode_free_percent: row.get(9)?,
+                temperature: row.get(10)?,
+                reallocated_sectors: row.get(11)?,
+                pending_sectors: row.get(12)?,
+                uncorrectable_sectors: row.get(13)?,
+                media_errors: row.get(14)?,
+                health: row.get(15)?,
+                severity: row.get(16)?,
+            })
+        }).map_err(|e| format!("Failed to query history: {e}"))?;
+        let samples = rows.collect::<Result<Vec<_>, _>>().map_err(|e| format!("Failed to read history: {e}"))?;
+        Ok(samples.into_iter().filter(|s| mounts.is_empty() || mounts.contains(&s.mount_point)).collect())
+    }
+}
+
+pub fn database_path(cfg: &Config) -> &Path {
+    Path::new(cfg.history_db.as_deref().unwrap_or(DEFAULT_HISTORY_PATH))
+}
+
+#[derive(serde::Serialize)]
+struct MountHistory {
+    mount_point: String,
+    display_name: String,
+    summary: Summary,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    samples: Option<Vec<Sample>>,
+}
+
+/// Run the `history` subcommand.
+pub fn run(cfg: &Config, args: &HistoryArgs, now: i64) -> Result<(), String> {
+    let path = database_path(cfg);
+    if !path.exists() {
+        return Err(format!("No history database at {} (history is recorded on every check run)", path.display()));
+    }
+    let history = History::open(path)?;
+    let since = now.saturating_sub(args.since.0 as i64);
+    let samples = history.samples(since, &args.mounts)?;
+
+    let mut by_mount: BTreeMap<String, Vec<Sample>> = BTreeMap::new();
+    for sample in samples {
+        by_mount.entry(sample.mount_point.clone()).or_default().push(sample);
+    }
+
+    match args.format {
+        HistoryFormat::Json => {
+            let mounts: Vec<MountHistory> = by_mount.into_values().map(|samples| MountHistory {
+                mount_point: samples[0].mount_point.clone(),
+                display_name: samples[samples.len() - 1].display_name.clone(),
+                summary: Summary::of(&samples),
+                samples: (!args.summary).then_some(samples),
+            }).collect();
+            let json = serde_json::to_string_pretty(&mounts)
+                .map_err(|e| format!("Failed to serialize history: {e}"))?;
+            println!("{}", json);
+        }
+        HistoryFormat::Csv => print_csv(&by_mount, args.summary),
+        HistoryFormat::Table => {
+            if by_mount.is_empty() {
+                println!("No samples in the last {}.", args.since);
+            }
+            for samples in by_mount.values() {
+                print_table(samples, args.summary);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn opt<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn print_csv(by_mount: &BTreeMap<String, Vec<Sample>>, summary: bool) {
+    if summary {
+        println!("mount_point,samples,first,last,free_percent_min,free_percent_max,free_percent_avg,\
+                  available_min,available_max,available_avg,inode_free_percent_min,inode_free_percent_max,\
+                  inode_free_percent_avg,temperature_min,temperature_max,temperature_avg");
+        for (mount_point, samples) in by_mount {
+            let s = Summary::of(samples);
+            let stats = |stats: Option<Stats>, precision: usize| match stats {
+                Some(st) => format!("{:.p$},{:.p$},{:.p$}", st.min, st.max, st.avg, p = precision),
+                None => ",,".to_string(),
+            };
+            println!("{},{},{},{},{},{},{},{}", csv_field(mount_point), s.samples, s.first, s.last,
+                     stats(s.free_space_percent, 2), stats(s.available_space, 0), stats(s.inode_free_percent, 2),
+                     stats(s.temperature, 1));
+        }
+        return;
+    }
+    println!("timestamp,time,mount_point,file_system,total_space,available_space,free_space_percent,total_inodes,\
+              free_inodes,inode_free_percent,temperature,reallocated_sectors,pending_sectors,uncorrectable_sectors,\
+              media_errors,health,severity");
+    for s in by_mount.values().flatten() {
+        println!("{},{},{},{},{},{},{:.2},{},{},{},{},{},{},{},{},{},{}",
+                 s.timestamp, csv_field(&crate::format_local_time(s.timestamp)), csv_field(&s.mount_point),
+                 csv_field(&s.file_system), s.total_space, s.available_space, s.free_space_percent, s.total_inodes,
+                 s.free_inodes, opt(s.inode_free_percent.map(|p| format!("{:.2}", p))), opt(s.temperature),
+                 opt(s.reallocated_sectors), opt(s.pending_sectors), opt(s.uncorrectable_sectors), opt(s.media_errors),
+                 s.health, opt(s.severity.as_deref()));
+    }
+}
+
+fn print_table(samples: &[Sample], summary_only: bool) {
+    let summary = Summary::of(samples);
+    let latest = &samples[samples.len() - 1];
+    println!("\n{} {} sample(s), {} .. {}",
+             latest.display_name.cyan().bold(),
+             summary.samples,
+             crate::format_local_time(summary.first),
+             crate::format_local_time(summary.last));
+    if !summary_only {
+        println!("  {:<19} {:>7} {:>12} {:>8} {:>5} {:>7} {:>7} {:>7} {:<8}",
+                 "TIME", "FREE%", "AVAILABLE", "INODES%", "TEMP", "REALLOC", "PENDING", "UNCORR", "HEALTH");
+        for s in samples {
+            println!("  {:<19} {:>7.2} {:>12} {:>8} {:>5} {:>7} {:>7} {:>7} {:<8}",
+                     crate::format_local_time(s.timestamp), s.free_space_percent, ByteSize(s.available_space).to_string(),
+                     opt(s.inode_free_percent.map(|p| format!("{:.2}", p))), opt(s.temperature),
+                     opt(s.reallocated_sectors), opt(s.pending_sectors), opt(s.uncorrectable_sectors), s.health);
+        }
+    }
+    if let Some(st) = summary.free_space_percent {
+        println!("  Free space %:     min {:.2}  max {:.2}  avg {:.2}", st.min, st.max, st.avg);
+    }
+    if let Some(st) = summary.available_space {
+        println!("  Available:        min {}  max {}  avg {}",
+                 ByteSize(st.min as u64), ByteSize(st.max as u64), ByteSize(st.avg as u64));
+    }
+    if let Some(st) = summary.inode_free_percent {
+        println!("  Free inodes %:    min {:.2}  max {:.2}  avg {:.2}", st.min, st.max, st.avg);
+    }
+    if let Some(st) = summary.temperature {
+        println!("  Temperature (C):  min {:.0}  max {:.0}  avg {:.1}", st.min, st.max, st.avg);
+    }
+}
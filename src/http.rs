@@ -0,0 +1,253 @@
+// HTTP endpoints for daemon mode.
+//
+// With http_listen set, the daemon serves the results of its last check:
+//   /metrics  the Prometheus metrics of `--format prometheus`, always in the text format 0.0.4
+//   /health   JSON with the status, the disks as in `--json` and the alert state
+// Scrapes never trigger a collection; both documents are rendered once per check and served from
+// memory, so a slow smartctl call can't hold up Prometheus. A minimal HTTP/1.1 server on the
+// existing tokio runtime is enough for this: GET/HEAD only, one request per connection.
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use log::{debug, warn};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::state::{AlertRecord, State};
+use crate::{DiskInfo, metrics, system};
+
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Prometheus accepts this even when its Accept header prefers OpenMetrics
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+#[derive(serde::Serialize)]
+struct Health<'a> {
+    status: &'static str, // "ok", "warning" or "critical"
+    last_check: i64,
+    system_info: &'a system::SystemInfo,
+    disks: &'a [DiskInfo],
+    alert_state: &'a BTreeMap<String, AlertRecord>,
+}
+
+// Documents rendered after the last check
+struct Snapshot {
+    metrics: String,
+    health: String,
+}
+
+/// State shared between the daemon loop and the HTTP server.
+pub struct Shared {
+    snapshot: RwLock<Option<Snapshot>>,
+    token: RwLock<Option<String>>,
+}
+
+impl Shared {
+    pub fn new(token: Option<String>) -> Arc<Shared> {
+        Arc::new(Shared {
+            snapshot: RwLock::new(None),
+            token: RwLock::new(token),
+        })
+    }
+
+    /// Replace the served documents with the results of a check made at `now`.
+    pub fn update(&self, disks: &[DiskInfo], alert_state: &State, system_info: &system::SystemInfo, now: i64) {
+        let health = Health {
+            status: disks.iter().filter_map(|d| d.severity()).max().map_or("ok", |s| s.as_str()),
+            last_check: now,
+            system_info,
+            disks,
+            alert_state: &alert_state.alerts,
+        };
+        let health = match serde_json::to_string_pretty(&health) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to serialize /health response: {}", e);
+                return;
+            }
+        };
+        let snapshot = Snapshot { metrics: metrics::render(disks, now), health };
+        *self.snapshot.write().unwrap_or_else(|e| e.into_inner()) = Some(snapshot);
+    }
+
+    pub fn set_token(&self, token: Option<String>) {
+        *self.token.write().unwrap_or_else(|e| e.into_inner()) = token;
+    }
+
+    fn authorized(&self, authorization: Option<&str>) -> bool {
+        let token = self.token.read().unwrap_or_else(|e| e.into_inner());
+        let Some(token) = token.as_deref() else { return true };
+        authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| constant_time_eq(given.trim().as_bytes(), token.as_bytes()))
+    }
+}
+
+// Compare without returning early, so response times don't reveal how much of the token matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub async fn bind(addr: &str) -> Result<TcpListener, String> {
+    let addr: SocketAddr = addr.parse().map_err(|e| format!("Invalid http_listen address {addr}: {e}"))?;
+    TcpListener::bind(addr).await.map_err(|e| format!("Failed to listen on {addr}: {e}"))
+}
+
+/// Accept connections until the task is dropped.
+pub async fn serve(listener: TcpListener, shared: Arc<Shared>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                let shared = Arc::clone(&shared);
+                tokio::spawn(async move {
+                    if let Err(e) = handle(stream, &shared).await {
+                        debug!("HTTP connection from {} failed: {}", peer, e);
+                    }
+                });
+            }
+            Err(e) => {
+                warn!("Failed to accept HTTP connection: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+}
+
+// Read the request line and headers; a body, if any, is ignored
+async fn read_request(stream: &mut TcpStream) -> Result<Request, String> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_HEAD {
+            return Err("request head too large".to_string());
+        }
+        let n = stream.read(&mut buf).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Err("connection closed before the request was complete".to_string());
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+
+    parse_request(&String::from_utf8_lossy(&head))
+}
+
+fn parse_request(head: &str) -> Result<Request, String> {
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or("").split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return Err("malformed request line".to_string());
+    };
+    let mut request = Request {
+        method: method.to_string(),
+        path: target.split('?').next().unwrap_or(target).to_string(),
+        authorization: None,
+    };
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else { continue };
+        if name.eq_ignore_ascii_case("authorization") {
+            request.authorization = Some(value.trim().to_string());
+        }
+    }
+    Ok(request)
+}
+
+async fn handle(mut stream: TcpStream, shared: &Shared) -> Result<(), String> {
+    let request = tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream))
+        .await
+        .map_err(|_| "timed out reading the request".to_string())??;
+
+    let (status, content_type, body, extra_headers) = respond(&request, shared);
+    let mut response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n{}\r\n",
+        status, content_type, body.len(), extra_headers
+    );
+    if request.method != "HEAD" {
+        response.push_str(&body);
+    }
+    stream.write_all(response.as_bytes()).await.map_err(|e| e.to_string())?;
+    stream.shutdown().await.map_err(|e| e.to_string())
+}
+
+fn respond(request: &Request, shared: &Shared) -> (&'static str, &'static str, String, &'static str) {
+    const TEXT: &str = "text/plain; charset=utf-8";
+    if request.method != "GET" && request.method != "HEAD" {
+        return ("405 Method Not Allowed", TEXT, "Method not allowed\n".to_string(), "Allow: GET, HEAD\r\n");
+    }
+    if !matches!(request.path.as_str(), "/metrics" | "/health") {
+        return ("404 Not Found", TEXT, "Not found; try /metrics or /health\n".to_string(), "");
+    }
+    if !shared.authorized(request.authorization.as_deref()) {
+        return ("401 Unauthorized", TEXT, "Unauthorized\n".to_string(), "WWW-Authenticate: Bearer\r\n");
+    }
+
+    let snapshot = shared.snapshot.read().unwrap_or_else(|e| e.into_inner());
+    let Some(snapshot) = snapshot.as_ref() else {
+        return ("503 Service Unavailable", TEXT, "The first check has not finished yet\n".to_string(), "Retry-After: 10\r\n");
+    };
+    if request.path == "/health" {
+        return ("200 OK", "application/json", snapshot.health.clone(), "");
+    }
+    ("200 OK", PROMETHEUS_CONTENT_TYPE, snapshot.metrics.clone(), "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(path: &str, authorization: Option<&str>) -> Request {
+        Request { method: "GET".to_string(), path: path.to_string(), authorization: authorization.map(str::to_string) }
+    }
+
+    #[test]
+    fn parse_request_line_and_headers() {
+        let head = "GET /metrics?name[]=x HTTP/1.1\r\nHost: nas:9187\r\nauthorization:  Bearer s3cret \r\nAccept: */*\r\n\r\n";
+        assert_eq!(parse_request(head), Ok(get("/metrics", Some("Bearer s3cret"))));
+
+        let request = parse_request("HEAD /health HTTP/1.0\r\nX-Note: a: b\r\n\r\n").unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str(), request.authorization), ("HEAD", "/health", None));
+    }
+
+    #[test]
+    fn parse_request_rejects_malformed_request_line() {
+        assert!(parse_request("\r\n\r\n").is_err());
+        assert!(parse_request("GET\r\nHost: nas\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn respond_checks_method_path_and_token() {
+        let shared = Shared::new(Some("s3cret".to_string()));
+        let status = |request: &Request| respond(request, &shared).0;
+        let post = Request { method: "POST".to_string(), ..get("/metrics", None) };
+        assert_eq!(status(&post), "405 Method Not Allowed");
+        assert_eq!(status(&get("/", None)), "404 Not Found");
+        assert_eq!(status(&get("/metrics", None)), "401 Unauthorized");
+        assert_eq!(status(&get("/metrics", Some("Bearer wrong"))), "401 Unauthorized");
+        assert_eq!(status(&get("/metrics", Some("s3cret"))), "401 Unauthorized");
+        assert_eq!(status(&get("/metrics", Some("Bearer s3cret"))), "503 Service Unavailable");
+
+        *shared.snapshot.write().unwrap() = Some(Snapshot { metrics: "m\n".to_string(), health: "{}".to_string() });
+        assert_eq!(respond(&get("/metrics", Some("Bearer s3cret")), &shared).1, PROMETHEUS_CONTENT_TYPE);
+        assert_eq!(respond(&get("/health", Some("Bearer s3cret")), &shared).2, "{}");
+        shared.set_token(None);
+        assert_eq!(status(&get("/health", None)), "200 OK");
+    }
+
+    #[test]
+    fn constant_time_eq_compares_whole_input() {
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(!constant_time_eq(b"token", b"tokem"));
+        assert!(!constant_time_eq(b"token", b"token2"));
+        assert!(constant_time_eq(b"", b""));
+    }
+}
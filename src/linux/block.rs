@@ -0,0 +1,164 @@
+// Block device resolution via sysfs.
+//
+// Filesystems sit on partitions, LVM logical volumes, dm-crypt mappings or md arrays. SMART data
+// lives on the physical disks underneath, so we walk /sys/class/block from the filesystem device
+// down through partition parents and `slaves/` links until we reach whole disks.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SYS_CLASS_BLOCK: &str = "/sys/class/block";
+const DEV_DISK_BY_LABEL: &str = "/dev/disk/by-label";
+
+/// The device stack underneath a filesystem.
+#[derive(Debug, Clone, Default)]
+pub struct BlockStack {
+    /// Kernel name of the filesystem device (e.g. "sda1", "dm-0", "md127")
+    pub device: String,
+    /// Every device visited on the way down, including `device` and the physical disks
+    pub members: Vec<String>,
+    /// Whole physical disks at the bottom of the stack (e.g. "sda", "nvme0n1")
+    pub disks: Vec<String>,
+}
+
+impl BlockStack {
+    /// True if the stack contains a software RAID (md) device or spans several disks.
+    pub fn is_raid(&self) -> bool {
+        self.members.iter().any(|m| m.starts_with("md")) || self.disks.len() > 1
+    }
+
+    /// Device-mapper names (e.g. "vg0-root") for dm devices in the stack.
+    pub fn dm_names(&self) -> Vec<String> {
+        self.members
+            .iter()
+            .filter(|m| m.starts_with("dm-"))
+            .filter_map(|m| fs::read_to_string(Path::new(SYS_CLASS_BLOCK).join(m).join("dm/name")).ok())
+            .map(|n| n.trim().to_string())
+            .collect()
+    }
+}
+
+/// Resolve a device path such as `/dev/sda1`, `/dev/mapper/vg0-root` or `/dev/md0`.
+pub fn resolve(device: &str) -> Option<BlockStack> {
+    let name = kernel_name(device)?;
+    resolve_kernel_name(&name)
+}
+
+/// Resolve a device by its major:minor number (as found in /proc/self/mountinfo).
+pub fn resolve_dev_number(major: u32, minor: u32) -> Option<BlockStack> {
+    let link = fs::canonicalize(format!("/sys/dev/block/{}:{}", major, minor)).ok()?;
+    let name = link.file_name()?.to_str()?.to_string();
+    resolve_kernel_name(&name)
+}
+
+fn resolve_kernel_name(name: &str) -> Option<BlockStack> {
+    if !Path::new(SYS_CLASS_BLOCK).join(name).exists() {
+        return None;
+    }
+    let mut stack = BlockStack {
+        device: name.to_string(),
+        ..Default::default()
+    };
+    walk(name, &mut stack, 0);
+    Some(stack)
+}
+
+/// Filesystem label of a device, from the udev-maintained /dev/disk/by-label symlinks.
+pub fn label(device: &str) -> Option<String> {
+    let target = fs::canonicalize(device).ok()?;
+    fs::read_dir(DEV_DISK_BY_LABEL)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .find(|e| fs::canonicalize(e.path()).is_ok_and(|p| p == target))
+        .and_then(|e| e.file_name().to_str().map(unescape_udev))
+}
+
+// udev encodes unsafe characters in link names as \xNN (e.g. spaces become \x20)
+fn unescape_udev(name: &str) -> String {
+    let mut out = Vec::with_capacity(name.len());
+    let bytes = name.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\'
+            && bytes.get(i + 1) == Some(&b'x')
+            && let Some(value) = name.get(i + 2..i + 4).and_then(|h| u8::from_str_radix(h, 16).ok())
+        {
+            out.push(value);
+            i += 4;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Map a /dev path to its kernel block device name, following /dev/mapper and by-id symlinks.
+fn kernel_name(device: &str) -> Option<String> {
+    let path = fs::canonicalize(device).unwrap_or_else(|_| PathBuf::from(device));
+    let name = path.file_name()?.to_str()?.to_string();
+    if Path::new(SYS_CLASS_BLOCK).join(&name).exists() {
+        return Some(name);
+    }
+    // /dev/root and friends: fall back to the device number of the node, if it exists
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if let Ok(meta) = fs::metadata(&path) {
+            let rdev = meta.rdev();
+            let (major, minor) = (dev_major(rdev), dev_minor(rdev));
+            let link = fs::canonicalize(format!("/sys/dev/block/{}:{}", major, minor)).ok()?;
+            return link.file_name()?.to_str().map(str::to_string);
+        }
+    }
+    None
+}
+
+// glibc's encoding of dev_t (see gnu_dev_major/gnu_dev_minor)
+fn dev_major(dev: u64) -> u32 {
+    (((dev >> 32) & 0xffff_f000) | ((dev >> 8) & 0x0000_0fff)) as u32
+}
+
+fn dev_minor(dev: u64) -> u32 {
+    (((dev >> 12) & 0xffff_ff00) | (dev & 0x0000_00ff)) as u32
+}
+
+fn walk(name: &str, stack: &mut BlockStack, depth: usize) {
+    // Guard against unexpected cycles in sysfs
+    if depth > 16 || stack.members.iter().any(|m| m == name) {
+        return;
+    }
+    stack.members.push(name.to_string());
+    let sys_path = Path::new(SYS_CLASS_BLOCK).join(name);
+
+    // Partition: the parent directory of the resolved sysfs node is the whole disk
+    if sys_path.join("partition").exists()
+        && let Some(parent) = fs::canonicalize(&sys_path)
+            .ok()
+            .and_then(|p| p.parent().and_then(|p| p.file_name()).and_then(|n| n.to_str()).map(str::to_string))
+    {
+        walk(&parent, stack, depth + 1);
+        return;
+    }
+
+    // Stacked device (dm, md, bcache...): recurse into everything it is built from
+    let mut slaves: Vec<String> = fs::read_dir(sys_path.join("slaves"))
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.file_name().to_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    if !slaves.is_empty() {
+        slaves.sort();
+        for slave in slaves {
+            walk(&slave, stack, depth + 1);
+        }
+        return;
+    }
+
+    if !stack.disks.iter().any(|d| d == name) {
+        stack.disks.push(name.to_string());
+    }
+}
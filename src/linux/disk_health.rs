@@ -1,223 +1,151 @@
 use std::fs;
+use std::io::Read;
 use std::path::Path;
-use std::process::Command;
-
-pub fn get_smart_status(disk_name: &str, debug: bool) -> (Option<String>, Option<String>, Option<String>, Option<String>, bool, Option<u64>, Option<u64>, Option<i64>, Option<u64>, Option<u64>, String) {
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::smart::{HealthMethod, HealthStatus, SmartReport};
+use super::block;
+use super::smartctl::{self, SmartctlOutput};
+
+/// Collect SMART data for the physical disk(s) behind `disk_name`, which is either a device
+/// path (e.g. `/dev/sda1`, `/dev/mapper/vg0-root`) or a mount point. smartctl, dmesg and fsck
+/// calls still running after `timeout` are killed.
+pub fn get_smart_status(disk_name: &str, debug: bool, timeout: Duration) -> SmartReport {
+    let deadline = Instant::now() + timeout;
     if debug {
         println!("[DEBUG] Getting SMART status for: {}", disk_name);
     }
 
-    let mut health_method = "unknown".to_string();
-
-    // Check if smartmontools is installed
-    let smartctl_available = Command::new("smartctl").arg("--version").output().is_ok();
-    // Do not print smartmontools detection here; only print debug output if debug is true
-
-    // Map mount point to device name using /proc/mounts
-    let device_name = if let Ok(mounts) = fs::read_to_string("/proc/mounts") {
-        let mut found_device = None;
-        for line in mounts.lines() {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 2 && parts[1] == disk_name {
-                found_device = Some(parts[0].to_string());
-                break;
-            }
-        }
-        found_device
+    // Accept mount points as well as devices by looking them up in /proc/mounts
+    let device_name = if disk_name.starts_with("/dev/") {
+        Some(disk_name.to_string())
     } else {
-        None
+        fs::read_to_string("/proc/mounts").ok().and_then(|mounts| {
+            mounts.lines().find_map(|line| {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                (parts.len() >= 2 && parts[1] == disk_name).then(|| parts[0].to_string())
+            })
+        })
     };
 
     let device_name = match device_name {
         Some(device) if device.starts_with("/dev/") => device,
         _ => {
             if debug {
-                println!("[DEBUG] Could not determine device for mount point: {}", disk_name);
+                println!("[DEBUG] Could not determine device for: {}", disk_name);
             }
-            return (None, None, None, None, false, None, None, None, None, None, health_method);
+            return SmartReport::unavailable(HealthMethod::Unknown);
+        }
+    };
+
+    // Walk the block device stack (partition, LVM, dm-crypt, md) down to the physical disks
+    let Some(stack) = block::resolve(&device_name) else {
+        if debug {
+            println!("[DEBUG] Could not resolve block device in sysfs: {}", device_name);
         }
+        return SmartReport::unavailable(HealthMethod::Unknown);
     };
 
     if debug {
-        println!("[DEBUG] Found device: {}", device_name);
+        println!("[DEBUG] Found device: {} (stack: {:?}, disks: {:?})", device_name, stack.members, stack.disks);
+    }
+    let is_raid = stack.is_raid();
+    if is_raid && debug {
+        println!("[DEBUG] RAID device detected: {}", device_name);
     }
 
-    // Extract device name without partition (e.g., /dev/sda1 -> /dev/sda, /dev/mmcblk0p1 -> /dev/mmcblk0)
-    let device_base = if let Some(name) = device_name.split('/').last() {
-        if name.starts_with("mmcblk") {
-            // For MMC devices, remove partition number (e.g., mmcblk0p1 -> mmcblk0)
-            let base = name.chars().take_while(|c| !c.is_ascii_digit() || *c == '0').collect::<String>();
-            format!("/dev/{}", base)
-        } else if name.starts_with("nvme") {
-            // For NVMe devices, remove partition number (e.g., nvme0n1p1 -> nvme0n1)
-            let parts: Vec<&str> = name.split('p').collect();
-            format!("/dev/{}", parts[0])
-        } else if name.starts_with("sd") || name.starts_with("hd") {
-            // For SATA/IDE devices, remove partition number (e.g., sda1 -> sda)
-            let base = name.chars().take_while(|c| c.is_alphabetic()).collect::<String>();
-            format!("/dev/{}", base)
-        } else {
-            device_name.clone()
+    // Probe every physical disk and report the one in the worst state
+    let mut worst: Option<SmartReport> = None;
+    for disk in &stack.disks {
+        let mut report = probe_disk(&device_name, disk, debug, deadline);
+        report.is_raid = is_raid;
+        if worst.as_ref().is_none_or(|w| report.health.severity() > w.health.severity()) {
+            worst = Some(report);
         }
-    } else {
-        device_name.clone()
-    };
+    }
+    worst.unwrap_or_else(|| SmartReport::unavailable(HealthMethod::Unknown))
+}
+
+/// Collect SMART data for one physical disk (`disk` is its kernel name, e.g. "sda").
+/// `device_name` is the filesystem device, used for the read-only fsck check.
+fn probe_disk(device_name: &str, disk: &str, debug: bool, deadline: Instant) -> SmartReport {
+    let mut report = SmartReport::unavailable(HealthMethod::Unknown);
+    let device_base = format!("/dev/{}", disk);
 
     if debug {
         println!("[DEBUG] Device base: {}", device_base);
     }
 
-    let mut smart_status = None;
-    let mut serial_number = None;
-    let mut model = None;
-    let mut brand = None;
-    let mut is_raid = false;
-    let mut power_on_hours = None;
-    let mut reallocated_sectors = None;
-    let mut temperature = None;
-    let mut pending_sectors = None;
-    let mut uncorrectable_sectors = None;
-
-    // Check for RAID indicators
-    if device_name.contains("md") || device_name.contains("dm-") {
-        is_raid = true;
-        if debug {
-            println!("[DEBUG] RAID device detected: {}", device_name);
-        }
-    }
+    // Check if smartmontools is installed
+    let smartctl_available = run_until("smartctl", &["--version"], deadline).is_some();
+    // Do not print smartmontools detection here; only print debug output if debug is true
+
 
     // First, try to use smartctl if available
     if smartctl_available {
-        health_method = "smartmontools".to_string();
+        report.method = HealthMethod::Smartmontools;
         if debug {
             println!("[DEBUG] Using smartctl for device: {}", device_base);
         }
         
-        // Special handling for different device types
-        let smartctl_args = if device_base.contains("mmcblk") {
-            // For MMC/SD cards, try different device types
-            vec![
-                vec!["-H", "-i", &device_base],
-                vec!["-H", "-i", "-d", "auto", &device_base],
-                vec!["-H", "-i", "-d", "sat", &device_base],
-            ]
-        } else if device_base.contains("nvme") {
-            // For NVMe devices
-            vec![
-                vec!["-H", "-i", &device_base],
-                vec!["-H", "-i", "-d", "nvme", &device_base],
-            ]
+        // Device type hints to try, in order, for each kind of device
+        let device_types: &[Option<&str>] = if device_base.contains("nvme") {
+            &[None, Some("nvme")]
         } else {
-            // For SATA/IDE devices
-            vec![
-                vec!["-H", "-i", &device_base],
-                vec!["-H", "-i", "-d", "auto", &device_base],
-                vec!["-H", "-i", "-d", "sat", &device_base],
-            ]
+            // SATA/IDE devices and MMC/SD cards
+            &[None, Some("auto"), Some("sat")]
         };
 
-        // Try different smartctl command variations
-        for args in smartctl_args {
-            if debug {
-                println!("[DEBUG] Trying smartctl with args: {:?}", args);
+        for device_type in device_types {
+            // Prefer JSON output (smartmontools 7+), fall back to the text report for older builds
+            let mut json_args = vec!["-j", "-a"];
+            let mut text_args = vec!["-H", "-i", "-A"];
+            if let Some(device_type) = device_type {
+                json_args.extend(["-d", device_type]);
+                text_args.extend(["-d", device_type]);
             }
-            
-            if let Ok(smartctl_output) = Command::new("smartctl").args(&args).output() {
-                if smartctl_output.status.success() || smartctl_output.status.code() == Some(4) {
-                    // Exit code 4 means some SMART or other ATA command failed, but basic info might be available
-                    if let Ok(output_str) = String::from_utf8(smartctl_output.stdout) {
-                        if debug {
-                            println!("[DEBUG] smartctl output: {}", output_str);
-                        }
+            json_args.push(&device_base);
+            text_args.push(&device_base);
 
-                        // Parse SMART status from smartctl output
-                        for line in output_str.lines() {
-                            let line = line.trim();
-                            
-                            // Check for SMART overall-health self-assessment
-                            if line.contains("SMART overall-health self-assessment test result:") {
-                                if line.contains("PASSED") {
-                                    smart_status = Some("OK".to_string());
-                                } else if line.contains("FAILED") {
-                                    smart_status = Some("FAILING".to_string());
-                                } else {
-                                    smart_status = Some("WARNING".to_string());
-                                }
-                            }
-                            
-                            // Alternative SMART status formats
-                            if line.contains("SMART Health Status:") {
-                                if line.contains("OK") {
-                                    smart_status = Some("OK".to_string());
-                                } else {
-                                    smart_status = Some("WARNING".to_string());
-                                }
-                            }
-                            
-                            // Check for device model
-                            if line.starts_with("Device Model:") || line.starts_with("Model Number:") {
-                                model = Some(line.split(':').nth(1).unwrap_or("").trim().to_string());
-                            }
-                            
-                            // Check for serial number
-                            if line.starts_with("Serial Number:") {
-                                serial_number = Some(line.split(':').nth(1).unwrap_or("").trim().to_string());
-                            }
-                            
-                            // Check for vendor/product
-                            if line.starts_with("Vendor:") {
-                                brand = Some(line.split(':').nth(1).unwrap_or("").trim().to_string());
-                            }
+            if debug {
+                println!("[DEBUG] Trying smartctl with args: {:?}", json_args);
+            }
+            if let Some(output_str) = run_smartctl(&json_args, deadline) {
+                if debug {
+                    println!("[DEBUG] smartctl JSON output: {}", output_str);
+                }
+                if let Some(parsed) = SmartctlOutput::parse(&output_str) {
+                    parsed.apply_to(&mut report);
+                }
+            }
 
-                            // Check for MMC/SD card specific info
-                            if line.starts_with("Device:") {
-                                model = Some(line.split(':').nth(1).unwrap_or("").trim().to_string());
-                            }
+            // JSON not supported (or gave nothing useful); try the text report
+            if report.health.is_unknown() && report.model.is_none() && report.serial_number.is_none() {
+                if debug {
+                    println!("[DEBUG] Trying smartctl with args: {:?}", text_args);
+                }
+                if let Some(output_str) = run_smartctl(&text_args, deadline) {
+                    if debug {
+                        println!("[DEBUG] smartctl output: {}", output_str);
+                    }
+                    smartctl::parse_text(&output_str, &mut report);
+                }
+            }
 
-                            // Check for SMART attributes
-                            if line.contains("Power_On_Hours") {
-                                if let Ok(value) = line.split(':').nth(1).unwrap_or("").trim().parse::<u64>() {
-                                    power_on_hours = Some(value);
-                                }
-                            }
-                            if line.contains("Reallocated_Sector_Ct") {
-                                if let Ok(value) = line.split(':').nth(1).unwrap_or("").trim().parse::<u64>() {
-                                    reallocated_sectors = Some(value);
-                                }
-                            }
-                            if line.contains("Temperature_Celsius") {
-                                if let Ok(value) = line.split(':').nth(1).unwrap_or("").trim().parse::<i64>() {
-                                    temperature = Some(value);
-                                }
-                            }
-                            if line.contains("Current_Pending_Sector") {
-                                if let Ok(value) = line.split(':').nth(1).unwrap_or("").trim().parse::<u64>() {
-                                    pending_sectors = Some(value);
-                                }
-                            }
-                            if line.contains("Offline_Uncorrectable") {
-                                if let Ok(value) = line.split(':').nth(1).unwrap_or("").trim().parse::<u64>() {
-                                    uncorrectable_sectors = Some(value);
-                                }
-                            }
-                        }
+            // If we got useful information from smartctl, use it
+            if !report.health.is_unknown() || report.model.is_some() || report.serial_number.is_some() {
+                if debug {
+                    println!("[DEBUG] Using smartctl results: SMART={}, Model={:?}, Serial={:?}, Brand={:?}", 
+                             report.health, report.model, report.serial_number, report.brand);
+                }
 
-                        // If we got useful information from smartctl, use it
-                        if smart_status.is_some() || model.is_some() || serial_number.is_some() {
-                            if debug {
-                                println!("[DEBUG] Using smartctl results: SMART={:?}, Model={:?}, Serial={:?}, Brand={:?}", 
-                                         smart_status, model, serial_number, brand);
-                            }
-                            
-                            // If no SMART status but we got device info, assume OK
-                            if smart_status.is_none() && (model.is_some() || serial_number.is_some()) {
-                                smart_status = Some("OK".to_string());
-                            }
-                            
-                            return (smart_status, serial_number, brand, model, is_raid, power_on_hours, reallocated_sectors, temperature, pending_sectors, uncorrectable_sectors, health_method);
-                        }
-                    }
+                // If no SMART status but we got device info, assume OK
+                if report.health.is_unknown() {
+                    report.health = HealthStatus::Ok;
                 }
+
+                return report;
             }
         }
         
@@ -228,19 +156,18 @@ pub fn get_smart_status(disk_name: &str, debug: bool) -> (Option<String>, Option
 
     // Special handling for Raspberry Pi SD cards and MMC devices
     if device_base.contains("mmcblk") {
-        health_method = "kernel".to_string();
+        report.method = HealthMethod::Kernel;
         if debug {
             println!("[DEBUG] MMC/SD card detected, using specialized detection methods");
         }
         
         // Check dmesg for MMC/SD card errors
-        if let Ok(dmesg_output) = Command::new("dmesg").output() {
+        if let Some(dmesg_output) = run_until("dmesg", &[], deadline) {
             if let Ok(dmesg_str) = String::from_utf8(dmesg_output.stdout) {
-                let device_short = device_base.split('/').last().unwrap_or("");
                 let mut error_count = 0;
                 
                 for line in dmesg_str.lines().rev().take(1000) { // Check last 1000 lines
-                    if line.to_lowercase().contains(device_short) {
+                    if line.to_lowercase().contains(disk) {
                         if line.to_lowercase().contains("error") || 
                            line.to_lowercase().contains("fail") || 
                            line.to_lowercase().contains("timeout") ||
@@ -254,12 +181,12 @@ pub fn get_smart_status(disk_name: &str, debug: bool) -> (Option<String>, Option
                 }
                 
                 if error_count > 0 {
-                    smart_status = Some("WARNING".to_string());
+                    report.health = HealthStatus::Warning;
                     if debug {
                         println!("[DEBUG] Found {} MMC errors in dmesg", error_count);
                     }
                 } else {
-                    smart_status = Some("OK".to_string());
+                    report.health = HealthStatus::Ok;
                     if debug {
                         println!("[DEBUG] No MMC errors found in dmesg");
                     }
@@ -268,12 +195,11 @@ pub fn get_smart_status(disk_name: &str, debug: bool) -> (Option<String>, Option
         }
         
         // Try to get MMC device info from sysfs
-        let device_short = device_base.split('/').last().unwrap_or("");
-        let sysfs_path = format!("/sys/block/{}/device", device_short);
+        let sysfs_path = format!("/sys/block/{}/device", disk);
         if Path::new(&sysfs_path).exists() {
             // Read MMC device name
             if let Ok(name_data) = fs::read_to_string(format!("{}/name", sysfs_path)) {
-                model = Some(name_data.trim().to_string());
+                report.model = Some(name_data.trim().to_string());
             }
             
             // Read MMC CID (Card Identification) for serial
@@ -282,7 +208,7 @@ pub fn get_smart_status(disk_name: &str, debug: bool) -> (Option<String>, Option
                 if cid_data.len() >= 32 {
                     let serial_hex = &cid_data[18..26]; // Serial number is at specific position
                     if let Ok(serial_num) = u32::from_str_radix(serial_hex, 16) {
-                        serial_number = Some(format!("{:08X}", serial_num));
+                        report.serial_number = Some(format!("{:08X}", serial_num));
                     }
                 }
             }
@@ -290,7 +216,7 @@ pub fn get_smart_status(disk_name: &str, debug: bool) -> (Option<String>, Option
             // Read MMC manufacturer ID
             if let Ok(manfid_data) = fs::read_to_string(format!("{}/manfid", sysfs_path)) {
                 if let Ok(manfid) = manfid_data.trim().parse::<u32>() {
-                    brand = Some(match manfid {
+                    report.brand = Some(match manfid {
                         0x01 => "Panasonic".to_string(),
                         0x02 => "Toshiba".to_string(),
                         0x03 => "SanDisk".to_string(),
@@ -308,41 +234,41 @@ pub fn get_smart_status(disk_name: &str, debug: bool) -> (Option<String>, Option
             }
         }
         
-        if smart_status.is_some() {
+        if !report.health.is_unknown() {
             if debug {
-                println!("[DEBUG] Using MMC-specific results: SMART={:?}, Model={:?}, Serial={:?}, Brand={:?}", 
-                         smart_status, model, serial_number, brand);
+                println!("[DEBUG] Using MMC-specific results: SMART={}, Model={:?}, Serial={:?}, Brand={:?}", 
+                         report.health, report.model, report.serial_number, report.brand);
             }
-            return (smart_status, serial_number, brand, model, is_raid, power_on_hours, reallocated_sectors, temperature, pending_sectors, uncorrectable_sectors, health_method);
+            return report;
         }
     }
 
     // Fallback to kernel-based methods
-    health_method = "kernel".to_string();
+    report.method = HealthMethod::Kernel;
     if debug {
         println!("[DEBUG] Using kernel-based health detection");
     }
 
     // Try to read from /sys/block/{device}/device/
-    let sysfs_path = format!("/sys/block/{}/device", device_base);
+    let sysfs_path = format!("/sys/block/{}/device", disk);
     if Path::new(&sysfs_path).exists() {
         // Read model
         if let Ok(model_data) = fs::read_to_string(format!("{}/model", sysfs_path)) {
-            model = Some(model_data.trim().to_string());
+            report.model = Some(model_data.trim().to_string());
         }
 
         // Read serial
         if let Ok(serial_data) = fs::read_to_string(format!("{}/serial", sysfs_path)) {
-            serial_number = Some(serial_data.trim().to_string());
+            report.serial_number = Some(serial_data.trim().to_string());
         }
 
         // Read vendor
         if let Ok(vendor_data) = fs::read_to_string(format!("{}/vendor", sysfs_path)) {
-            brand = Some(vendor_data.trim().to_string());
+            report.brand = Some(vendor_data.trim().to_string());
         }
 
         // Check for SMART status in /sys/block/{device}/queue/
-        let queue_path = format!("/sys/block/{}/queue", device_base);
+        let queue_path = format!("/sys/block/{}/queue", disk);
         if Path::new(&queue_path).exists() {
             // Try to read some basic health indicators
             if let Ok(rotational) = fs::read_to_string(format!("{}/rotational", queue_path)) {
@@ -353,11 +279,6 @@ pub fn get_smart_status(disk_name: &str, debug: bool) -> (Option<String>, Option
             }
         }
 
-        // Check for RAID indicators
-        if device_name.contains("md") || device_name.contains("dm-") {
-            is_raid = true;
-        }
-
         // Try to read SMART attributes from /sys/block/{device}/device/
         let smart_path = format!("{}/smart_attributes", sysfs_path);
         if Path::new(&smart_path).exists() {
@@ -365,7 +286,7 @@ pub fn get_smart_status(disk_name: &str, debug: bool) -> (Option<String>, Option
                 // Parse SMART attributes if available
                 for line in smart_data.lines() {
                     if line.contains("FAILING_NOW") || line.contains("Pre-fail") {
-                        smart_status = Some("FAILING".to_string());
+                        report.health = HealthStatus::Failing;
                         break;
                     }
                 }
@@ -373,18 +294,18 @@ pub fn get_smart_status(disk_name: &str, debug: bool) -> (Option<String>, Option
         }
 
         // If no SMART status found, try alternative methods
-        if smart_status.is_none() {
+        if report.health.is_unknown() {
             // Check for any error indicators in /sys/block/{device}/
-            let error_path = format!("/sys/block/{}/stat", device_base);
+            let error_path = format!("/sys/block/{}/stat", disk);
             if let Ok(stat_data) = fs::read_to_string(error_path) {
                 let parts: Vec<&str> = stat_data.split_whitespace().collect();
                 if parts.len() >= 4 {
                     // Check for I/O errors (field 3 in /proc/diskstats)
                     if let Ok(io_errors) = parts[3].parse::<u64>() {
                         if io_errors > 0 {
-                            smart_status = Some("WARNING".to_string());
+                            report.health = HealthStatus::Warning;
                         } else {
-                            smart_status = Some("OK".to_string());
+                            report.health = HealthStatus::Ok;
                         }
                     }
                 }
@@ -392,17 +313,17 @@ pub fn get_smart_status(disk_name: &str, debug: bool) -> (Option<String>, Option
         }
 
         // If still no status, try reading from /proc/diskstats
-        if smart_status.is_none() {
+        if report.health.is_unknown() {
             if let Ok(diskstats) = fs::read_to_string("/proc/diskstats") {
                 for line in diskstats.lines() {
                     let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 14 && parts[2] == device_base {
+                    if parts.len() >= 14 && parts[2] == disk {
                         // Check for I/O errors (field 12)
                         if let Ok(io_errors) = parts[11].parse::<u64>() {
                             if io_errors > 0 {
-                                smart_status = Some("WARNING".to_string());
+                                report.health = HealthStatus::Warning;
                             } else {
-                                smart_status = Some("OK".to_string());
+                                report.health = HealthStatus::Ok;
                             }
                         }
                         break;
@@ -412,9 +333,9 @@ pub fn get_smart_status(disk_name: &str, debug: bool) -> (Option<String>, Option
         }
 
         // Additional kernel-based health checks
-        if smart_status.is_none() {
+        if report.health.is_unknown() {
             // Check dmesg for disk errors
-            if let Ok(dmesg_output) = Command::new("dmesg").output() {
+            if let Some(dmesg_output) = run_until("dmesg", &[], deadline) {
                 if let Ok(dmesg_str) = String::from_utf8(dmesg_output.stdout) {
                     // Look for recent disk-related errors
                     let error_patterns = [
@@ -432,7 +353,7 @@ pub fn get_smart_status(disk_name: &str, debug: bool) -> (Option<String>, Option
                              line.to_lowercase().contains("warning") ||
                              line.to_lowercase().contains("i/o error"))
                         }) {
-                            smart_status = Some("WARNING".to_string());
+                            report.health = HealthStatus::Warning;
                             if debug {
                                 println!("[DEBUG] Found disk errors in dmesg for {}", device_base);
                             }
@@ -443,13 +364,11 @@ pub fn get_smart_status(disk_name: &str, debug: bool) -> (Option<String>, Option
             }
 
             // Check for filesystem errors (read-only check)
-            if let Ok(fsck_output) = Command::new("fsck")
-                .args(&["-n", &device_name])
-                .output() {
+            if let Some(fsck_output) = run_until("fsck", &["-n", device_name], deadline) {
                 if !fsck_output.status.success() {
                     if let Ok(fsck_str) = String::from_utf8(fsck_output.stderr) {
                         if fsck_str.contains("error") || fsck_str.contains("corruption") {
-                            smart_status = Some("WARNING".to_string());
+                            report.health = HealthStatus::Warning;
                             if debug {
                                 println!("[DEBUG] Found filesystem errors for {}", device_name);
                             }
@@ -459,16 +378,65 @@ pub fn get_smart_status(disk_name: &str, debug: bool) -> (Option<String>, Option
             }
 
             // If still no status, default to OK
-            if smart_status.is_none() {
-                smart_status = Some("OK".to_string());
+            if report.health.is_unknown() {
+                report.health = HealthStatus::Ok;
             }
         }
     }
 
     if debug {
-        println!("[DEBUG] Kernel-based results: SMART={:?}, Model={:?}, Serial={:?}, Brand={:?}, RAID={}", 
-                 smart_status, model, serial_number, brand, is_raid);
+        println!("[DEBUG] Kernel-based results: SMART={}, Model={:?}, Serial={:?}, Brand={:?}, RAID={}", 
+                 report.health, report.model, report.serial_number, report.brand, report.is_raid);
     }
 
-    (smart_status, serial_number, brand, model, is_raid, power_on_hours, reallocated_sectors, temperature, pending_sectors, uncorrectable_sectors, health_method)
+    report
+}
+
+/// Run a command and collect its output. It is killed once `deadline` passes, so a hung drive
+/// doesn't hold the calling (blocking pool) thread forever; None if it couldn't be started or was
+/// killed.
+fn run_until(program: &str, args: &[&str], deadline: Instant) -> Option<Output> {
+    let mut child = Command::new(program).args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+    // Drain the pipes on their own threads so a long report can't block the command on a full pipe
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(50)),
+            _ => {
+                let _ = child.kill();
+                // A process stuck in the kernel only exits once the I/O returns; reap it then
+                std::thread::spawn(move || child.wait());
+                return None;
+            }
+        }
+    };
+    Some(Output { status, stdout: stdout.join().ok()?.ok()?, stderr: stderr.join().ok()?.ok()? })
+}
+
+fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> std::thread::JoinHandle<std::io::Result<Vec<u8>>> {
+    std::thread::spawn(move || {
+        let mut output = Vec::new();
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_end(&mut output)?;
+        }
+        Ok(output)
+    })
+}
+
+/// Run smartctl and return stdout if it produced anything worth parsing.
+fn run_smartctl(args: &[&str], deadline: Instant) -> Option<String> {
+    let output = run_until("smartctl", args, deadline)?;
+    // smartctl's exit status is a bit mask; bits 0-1 mean the command line was rejected or the
+    // device could not be opened. Higher bits report disk problems but the output is still valid.
+    if output.status.code().is_some_and(|code| code & 0b11 != 0) && output.stdout.is_empty() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok().filter(|s| !s.trim().is_empty())
 }
@@ -1,4 +1,7 @@
+pub mod block;
 pub mod disk_health;
+pub mod mounts;
+pub mod smartctl;
 pub use disk_health::get_smart_status;
 
 pub fn is_virtualized() -> bool {
@@ -0,0 +1,226 @@
+// Native filesystem enumeration.
+//
+// Mounts come from /proc/self/mountinfo (device, fstype, options and the bind-mount root) and
+// sizes from statvfs(3), so we don't depend on `df` being GNU coreutils or on its locale output.
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use crate::system::FilesystemInfo;
+use super::block;
+
+const MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+
+// Kernel-internal and memory-backed filesystems that never need monitoring
+const PSEUDO_FS: &[&str] = &[
+    "tmpfs", "devtmpfs", "proc", "sysfs", "cgroup", "cgroup2", "overlay", "squashfs",
+    "securityfs", "rpc_pipefs", "fusectl", "mqueue", "hugetlbfs", "autofs", "binfmt_misc",
+    "devpts", "debugfs", "tracefs", "configfs", "pstore", "bpf", "efivarfs", "nsfs",
+    "ramfs", "selinuxfs", "fuse.gvfsd-fuse", "fuse.portal", "fuse.lxcfs", "nfsd", "zram",
+];
+
+// Network filesystems are skipped: statvfs on a dead server can block indefinitely
+const NETWORK_FS: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "ncpfs", "afs", "ceph", "glusterfs",
+    "fuse.sshfs", "fuse.glusterfs", "9p", "lustre",
+];
+
+/// One line of /proc/self/mountinfo (see proc(5)).
+#[derive(Debug, Clone)]
+pub struct MountEntry {
+    pub major: u32,
+    pub minor: u32,
+    pub root: String,
+    pub mount_point: String,
+    pub mount_options: String,
+    pub fs_type: String,
+    pub source: String,
+}
+
+/// Filesystem statistics from statvfs. Sizes are in bytes, inode counts are absolute.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsStats {
+    pub total: u64,
+    pub free: u64,
+    pub available: u64,
+    pub total_inodes: u64,
+    pub free_inodes: u64,
+}
+
+pub fn read_mountinfo() -> Result<Vec<MountEntry>, String> {
+    let data = fs::read_to_string(MOUNTINFO_PATH)
+        .map_err(|e| format!("Failed to read {MOUNTINFO_PATH}: {e}"))?;
+    Ok(data.lines().filter_map(parse_mountinfo_line).collect())
+}
+
+// Format: ID PARENT MAJ:MIN ROOT MOUNT_POINT OPTIONS [OPTIONAL...] - FSTYPE SOURCE SUPER_OPTIONS
+fn parse_mountinfo_line(line: &str) -> Option<MountEntry> {
+    let (before, after) = line.split_once(" - ")?;
+    let fields: Vec<&str> = before.split(' ').collect();
+    let tail: Vec<&str> = after.split(' ').collect();
+    if fields.len() < 6 || tail.len() < 2 {
+        return None;
+    }
+    let (major, minor) = fields[2].split_once(':')?;
+    Some(MountEntry {
+        major: major.parse().ok()?,
+        minor: minor.parse().ok()?,
+        root: unescape(fields[3]),
+        mount_point: unescape(fields[4]),
+        mount_options: fields[5].to_string(),
+        fs_type: tail[0].to_string(),
+        source: unescape(tail[1]),
+    })
+}
+
+// mountinfo escapes space, tab, newline and backslash as octal (\040, \011, \012, \134)
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 4 <= bytes.len() && bytes[i + 1..i + 4].iter().all(|b| (b'0'..=b'7').contains(b)) {
+            let value = bytes[i + 1..i + 4].iter().fold(0u32, |acc, b| acc * 8 + u32::from(b - b'0'));
+            out.push(value as u8);
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// statvfs field widths differ between targets (u32 on 32-bit glibc), hence the explicit widening
+#[allow(clippy::useless_conversion)]
+pub fn statvfs(path: &Path) -> Option<FsStats> {
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: c_path is a valid NUL-terminated string and buf is a properly sized out-parameter
+    let mut buf: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut buf) } != 0 {
+        return None;
+    }
+    let fragment = u64::from(buf.f_frsize);
+    Some(FsStats {
+        total: u64::from(buf.f_blocks).saturating_mul(fragment),
+        free: u64::from(buf.f_bfree).saturating_mul(fragment),
+        available: u64::from(buf.f_bavail).saturating_mul(fragment),
+        total_inodes: u64::from(buf.f_files),
+        free_inodes: u64::from(buf.f_ffree),
+    })
+}
+
+fn is_monitored_fs(entry: &MountEntry) -> bool {
+    !PSEUDO_FS.contains(&entry.fs_type.as_str())
+        && !NETWORK_FS.contains(&entry.fs_type.as_str())
+        && !entry.mount_point.starts_with("/proc/")
+        && !entry.mount_point.starts_with("/sys/")
+}
+
+/// Enumerate local, real filesystems. Bind mounts and repeated mounts of the same filesystem are
+/// reported once, preferring the mount of the filesystem root and then the shortest path.
+pub fn list_filesystems(debug: bool) -> Result<Vec<FilesystemInfo>, String> {
+    let entries = read_mountinfo()?;
+
+    let mut by_device: HashMap<(u32, u32), MountEntry> = HashMap::new();
+    let mut order = Vec::new();
+    for entry in entries.into_iter().filter(is_monitored_fs) {
+        let key = (entry.major, entry.minor);
+        match by_device.get(&key) {
+            None => {
+                order.push(key);
+                by_device.insert(key, entry);
+            }
+            Some(existing) => {
+                let better = (entry.root == "/" && existing.root != "/")
+                    || (entry.root == existing.root && entry.mount_point.len() < existing.mount_point.len());
+                if debug {
+                    println!("[DEBUG] Duplicate mount of {}:{} ({}): {} and {}",
+                             key.0, key.1, entry.source, existing.mount_point, entry.mount_point);
+                }
+                if better {
+                    by_device.insert(key, entry);
+                }
+            }
+        }
+    }
+
+    let mut filesystems = Vec::new();
+    for key in order {
+        let Some(entry) = by_device.remove(&key) else { continue };
+        let Some(stats) = statvfs(Path::new(&entry.mount_point)) else {
+            if debug {
+                println!("[DEBUG] statvfs failed for {}", entry.mount_point);
+            }
+            continue;
+        };
+        if stats.total == 0 {
+            continue;
+        }
+        // Some boot setups report the root filesystem as /dev/root, which has no device node;
+        // use the kernel name behind the device number instead
+        let device = if entry.source.starts_with("/dev/") && !Path::new(&entry.source).exists() {
+            block::resolve_dev_number(entry.major, entry.minor)
+                .map(|stack| format!("/dev/{}", stack.device))
+                .unwrap_or(entry.source)
+        } else {
+            entry.source
+        };
+        let label = block::label(&device);
+        filesystems.push(FilesystemInfo {
+            mount_point: entry.mount_point,
+            device,
+            label,
+            fs_type: entry.fs_type,
+            mount_options: entry.mount_options,
+            total_space: stats.total,
+            free_space: stats.free,
+            available_space: stats.available,
+            total_inodes: stats.total_inodes,
+            free_inodes: stats.free_inodes,
+        });
+    }
+    Ok(filesystems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescape_octal_sequences() {
+        assert_eq!(unescape("/mnt/my\\040disk"), "/mnt/my disk");
+        assert_eq!(unescape("/srv/a\\011b\\012c\\134d"), "/srv/a\tb\nc\\d");
+        assert_eq!(unescape("/media/usb\\040"), "/media/usb ");
+        assert_eq!(unescape("/plain/path"), "/plain/path");
+    }
+
+    #[test]
+    fn unescape_keeps_incomplete_or_non_octal_escapes() {
+        assert_eq!(unescape("/mnt/a\\04"), "/mnt/a\\04");
+        assert_eq!(unescape("/mnt/a\\089"), "/mnt/a\\089");
+        assert_eq!(unescape("\\"), "\\");
+    }
+
+    #[test]
+    fn parse_line_with_optional_fields() {
+        let line = "36 35 98:0 /mnt1 /mnt/my\\040data rw,noatime master:1 shared:2 - ext4 /dev/sdb1 rw,errors=continue";
+        let entry = parse_mountinfo_line(line).unwrap();
+        assert_eq!((entry.major, entry.minor), (98, 0));
+        assert_eq!(entry.root, "/mnt1");
+        assert_eq!(entry.mount_point, "/mnt/my data");
+        assert_eq!(entry.mount_options, "rw,noatime");
+        assert_eq!(entry.fs_type, "ext4");
+        assert_eq!(entry.source, "/dev/sdb1");
+    }
+
+    #[test]
+    fn parse_line_rejects_malformed_lines() {
+        assert!(parse_mountinfo_line("36 35 98:0 / /mnt rw ext4 /dev/sdb1 rw").is_none());
+        assert!(parse_mountinfo_line("36 35 980 / /mnt rw - ext4 /dev/sdb1 rw").is_none());
+        assert!(parse_mountinfo_line("").is_none());
+    }
+}
@@ -0,0 +1,577 @@
+// Parsers for smartctl output.
+//
+// smartmontools 7+ can emit JSON (`smartctl -j`), which is what we prefer. Older builds only
+// produce the human-readable report, so a text parser is kept as a fallback.
+
+use serde::Deserialize;
+
+use crate::smart::{HealthStatus, NvmeHealth, SmartAttribute, SmartReport};
+
+// smartctl exit status bits (see smartctl(8), "EXIT STATUS")
+const EXIT_CMDLINE_ERROR: i64 = 1 << 0;
+const EXIT_DEVICE_OPEN_FAILED: i64 = 1 << 1;
+const EXIT_PREFAIL_BELOW_THRESHOLD: i64 = 1 << 4;
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct SmartctlOutput {
+    pub smartctl: SmartctlMeta,
+    pub device: Option<SmartctlDevice>,
+    pub model_family: Option<String>,
+    pub model_name: Option<String>,
+    pub serial_number: Option<String>,
+    #[serde(alias = "vendor")]
+    pub scsi_vendor: Option<String>,
+    #[serde(alias = "product")]
+    pub scsi_product: Option<String>,
+    pub smart_status: Option<SmartctlStatus>,
+    pub power_on_time: Option<PowerOnTime>,
+    pub temperature: Option<Temperature>,
+    pub ata_smart_attributes: Option<AtaSmartAttributes>,
+    pub nvme_smart_health_information_log: Option<NvmeHealthLog>,
+    pub scsi_grown_defect_list: Option<u64>,
+    pub scsi_error_counter_log: Option<ScsiErrorCounterLog>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct SmartctlMeta {
+    pub exit_status: i64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct SmartctlDevice {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub device_type: String,
+    pub protocol: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct SmartctlStatus {
+    pub passed: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct PowerOnTime {
+    pub hours: u64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Temperature {
+    pub current: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct AtaSmartAttributes {
+    pub table: Vec<AtaAttribute>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct AtaAttribute {
+    pub id: u8,
+    pub name: String,
+    pub value: u64,
+    pub worst: u64,
+    pub thresh: u64,
+    pub when_failed: String,
+    pub flags: AtaAttributeFlags,
+    pub raw: AtaRawValue,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct AtaAttributeFlags {
+    pub value: u64,
+    pub string: String,
+    pub prefailure: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct AtaRawValue {
+    pub value: u64,
+    pub string: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct NvmeHealthLog {
+    pub critical_warning: u8,
+    pub temperature: Option<i64>,
+    pub available_spare: u8,
+    pub available_spare_threshold: u8,
+    pub percentage_used: u8,
+    pub data_units_read: u64,
+    pub data_units_written: u64,
+    pub power_on_hours: Option<u64>,
+    pub unsafe_shutdowns: u64,
+    pub media_errors: u64,
+    pub warning_temp_time: u64,
+    pub critical_comp_time: u64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct ScsiErrorCounterLog {
+    pub read: Option<ScsiErrorCounter>,
+    pub write: Option<ScsiErrorCounter>,
+    pub verify: Option<ScsiErrorCounter>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct ScsiErrorCounter {
+    pub total_uncorrected_errors: u64,
+}
+
+impl SmartctlOutput {
+    /// Parse `smartctl -j` output. Returns `None` if the output is not JSON (smartctl < 7)
+    /// or smartctl could not open the device at all.
+    pub fn parse(output: &str) -> Option<Self> {
+        let parsed: SmartctlOutput = serde_json::from_str(output).ok()?;
+        if parsed.smartctl.exit_status & (EXIT_CMDLINE_ERROR | EXIT_DEVICE_OPEN_FAILED) != 0 {
+            return None;
+        }
+        Some(parsed)
+    }
+
+    /// Copy everything we understand into `report`.
+    pub fn apply_to(&self, report: &mut SmartReport) {
+        if let Some(status) = &self.smart_status {
+            report.health = if !status.passed {
+                HealthStatus::Failing
+            } else if self.smartctl.exit_status & EXIT_PREFAIL_BELOW_THRESHOLD != 0 {
+                HealthStatus::Warning
+            } else {
+                HealthStatus::Ok
+            };
+        }
+
+        report.model = non_empty(self.model_name.as_deref())
+            .or_else(|| non_empty(self.scsi_product.as_deref()))
+            .or(report.model.take());
+        report.serial_number = non_empty(self.serial_number.as_deref()).or(report.serial_number.take());
+        report.brand = non_empty(self.scsi_vendor.as_deref())
+            .or_else(|| non_empty(self.model_family.as_deref()))
+            .or(report.brand.take());
+
+        // ATA attributes
+        if let Some(attrs) = &self.ata_smart_attributes {
+            report.smart_attributes = attrs.table.iter().map(|a| SmartAttribute {
+                id: a.id,
+                name: a.name.clone(),
+                flags: a.flags.string.trim().to_string(),
+                value: a.value,
+                worst: a.worst,
+                thresh: a.thresh,
+                raw_value: a.raw.value,
+                raw_string: a.raw.string.clone(),
+                when_failed: a.when_failed.clone(),
+                failing: SmartAttribute::is_below_threshold(a.value, a.thresh) || a.when_failed == "now",
+            }).collect();
+            apply_ata_counters(report);
+        }
+        if let Some(power_on) = &self.power_on_time {
+            report.power_on_hours = Some(power_on.hours);
+        }
+        if let Some(current) = self.temperature.as_ref().and_then(|t| t.current) {
+            report.temperature = Some(current);
+        }
+
+        // NVMe health log
+        if let Some(log) = &self.nvme_smart_health_information_log {
+            report.power_on_hours = report.power_on_hours.or(log.power_on_hours);
+            report.temperature = report.temperature.or(log.temperature);
+            report.nvme = Some(NvmeHealth {
+                critical_warning: log.critical_warning,
+                available_spare: log.available_spare,
+                available_spare_threshold: log.available_spare_threshold,
+                percentage_used: log.percentage_used,
+                data_units_read: log.data_units_read,
+                data_units_written: log.data_units_written,
+                media_errors: log.media_errors,
+                unsafe_shutdowns: log.unsafe_shutdowns,
+                warning_temp_time: log.warning_temp_time,
+                critical_comp_time: log.critical_comp_time,
+            });
+        }
+
+        // SCSI / SAS pages
+        if let Some(defects) = self.scsi_grown_defect_list {
+            report.reallocated_sectors = Some(defects);
+        }
+        if let Some(log) = &self.scsi_error_counter_log {
+            let total: u64 = [&log.read, &log.write, &log.verify]
+                .iter()
+                .filter_map(|c| c.as_ref())
+                .map(|c| c.total_uncorrected_errors)
+                .sum();
+            report.uncorrectable_sectors = Some(total);
+        }
+    }
+}
+
+/// Parse the human-readable `smartctl -H -i -A` report (fallback for smartctl < 7).
+pub fn parse_text(output: &str, report: &mut SmartReport) {
+    for line in output.lines() {
+        let line = line.trim();
+
+        // Check for SMART overall-health self-assessment
+        if line.contains("SMART overall-health self-assessment test result:") {
+            report.health = if line.contains("PASSED") {
+                HealthStatus::Ok
+            } else if line.contains("FAILED") {
+                HealthStatus::Failing
+            } else {
+                HealthStatus::Warning
+            };
+        }
+
+        // Alternative SMART status formats (SCSI/SAS)
+        if line.contains("SMART Health Status:") {
+            report.health = if line.contains("OK") { HealthStatus::Ok } else { HealthStatus::Warning };
+        }
+
+        if let Some(value) = field(line, &["Device Model:", "Model Number:", "Product:", "Device:"]) {
+            report.model = Some(value);
+        }
+        if let Some(value) = field(line, &["Serial Number:", "Serial number:"]) {
+            report.serial_number = Some(value);
+        }
+        if let Some(value) = field(line, &["Vendor:", "Model Family:"]) {
+            report.brand = Some(value);
+        }
+
+        // NVMe health log lines, e.g. "Power On Hours:  1,234"
+        if let Some(value) = field(line, &["Power On Hours:"]) {
+            report.power_on_hours = parse_number(&value);
+        }
+        if let Some(value) = field(line, &["Temperature:", "Current Drive Temperature:"]) {
+            report.temperature = parse_number(&value).map(|v| v as i64);
+        }
+
+        // NVMe SMART/Health log, e.g. "Percentage Used:  3%"
+        if line.starts_with("SMART/Health Information (NVMe Log 0x02") {
+            report.nvme.get_or_insert_with(NvmeHealth::default);
+        }
+        if let Some(nvme) = report.nvme.as_mut() {
+            parse_nvme_line(line, nvme);
+        }
+
+        if let Some(attr) = parse_attribute_row(line) {
+            report.smart_attributes.push(attr);
+        }
+    }
+    apply_ata_counters(report);
+}
+
+fn parse_nvme_line(line: &str, nvme: &mut NvmeHealth) {
+    let Some((key, value)) = line.split_once(':') else { return };
+    let value = value.trim();
+    let number = || parse_number(value).unwrap_or(0);
+    match key.trim() {
+        "Critical Warning" => {
+            nvme.critical_warning = u8::from_str_radix(value.trim_start_matches("0x"), 16).unwrap_or(0);
+        }
+        "Available Spare" => nvme.available_spare = number().min(255) as u8,
+        "Available Spare Threshold" => nvme.available_spare_threshold = number().min(255) as u8,
+        "Percentage Used" => nvme.percentage_used = number().min(255) as u8,
+        "Data Units Read" => nvme.data_units_read = number(),
+        "Data Units Written" => nvme.data_units_written = number(),
+        "Media and Data Integrity Errors" => nvme.media_errors = number(),
+        "Unsafe Shutdowns" => nvme.unsafe_shutdowns = number(),
+        "Warning  Comp. Temperature Time" | "Warning Comp. Temperature Time" => nvme.warning_temp_time = number(),
+        "Critical Comp. Temperature Time" => nvme.critical_comp_time = number(),
+        _ => {}
+    }
+}
+
+/// Parse one row of the ATA attribute table:
+/// ID# ATTRIBUTE_NAME FLAG VALUE WORST THRESH TYPE UPDATED WHEN_FAILED RAW_VALUE
+fn parse_attribute_row(line: &str) -> Option<SmartAttribute> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 10 {
+        return None;
+    }
+    let id = parts[0].parse::<u8>().ok()?;
+    let value = parts[3].parse::<u64>().ok()?;
+    let worst = parts[4].parse::<u64>().unwrap_or(0);
+    let thresh = parts[5].parse::<u64>().unwrap_or(0);
+    let when_failed = match parts[8] {
+        "FAILING_NOW" => "now",
+        "In_the_past" => "past",
+        _ => "",
+    };
+    let raw_string = parts[9..].join(" ");
+    Some(SmartAttribute {
+        id,
+        name: parts[1].to_string(),
+        flags: parts[2].to_string(),
+        value,
+        worst,
+        thresh,
+        raw_value: parse_number(&raw_string).unwrap_or(0),
+        raw_string,
+        when_failed: when_failed.to_string(),
+        failing: SmartAttribute::is_below_threshold(value, thresh) || when_failed == "now",
+    })
+}
+
+/// Fill the well-known counters in `report` from its ATA attribute table.
+fn apply_ata_counters(report: &mut SmartReport) {
+    if let Some(attr) = report.attribute(9) {
+        // Some vendors pack minutes/milliseconds into the upper bytes
+        report.power_on_hours = Some(attr.raw_value & 0xFFFF_FFFF);
+    }
+    if let Some(attr) = report.attribute(5) {
+        report.reallocated_sectors = Some(attr.raw_value);
+    }
+    if let Some(attr) = report.attribute(194).or_else(|| report.attribute(190)) {
+        // Current temperature is the lowest byte; min/max live in the upper bytes
+        report.temperature = Some((attr.raw_value & 0xFF) as i64);
+    }
+    if let Some(attr) = report.attribute(197) {
+        report.pending_sectors = Some(attr.raw_value);
+    }
+    if let Some(attr) = report.attribute(198) {
+        report.uncorrectable_sectors = Some(attr.raw_value);
+    }
+}
+
+fn field(line: &str, prefixes: &[&str]) -> Option<String> {
+    prefixes
+        .iter()
+        .find_map(|p| line.strip_prefix(p))
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Parse the leading number of a value such as "1,234", "36 Celsius" or "12345h+23m".
+fn parse_number(value: &str) -> Option<u64> {
+    let digits: String = value
+        .split_whitespace()
+        .next()?
+        .replace(',', "")
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value.map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ATA_JSON: &str = r#"{
+        "smartctl": { "exit_status": 0 },
+        "device": { "name": "/dev/sda", "type": "sat", "protocol": "ATA" },
+        "model_family": "Western Digital Red",
+        "model_name": "WDC WD40EFRX-68N32N0",
+        "serial_number": "WD-WCC7K0000000",
+        "smart_status": { "passed": true },
+        "power_on_time": { "hours": 31337 },
+        "temperature": { "current": 34 },
+        "ata_smart_attributes": { "table": [
+            { "id": 1, "name": "Raw_Read_Error_Rate", "value": 200, "worst": 200, "thresh": 51, "when_failed": "",
+              "flags": { "value": 47, "string": "POSR-K ", "prefailure": true }, "raw": { "value": 0, "string": "0" } },
+            { "id": 5, "name": "Reallocated_Sector_Ct", "value": 5, "worst": 5, "thresh": 140, "when_failed": "now",
+              "flags": { "value": 51, "string": "PO--CK ", "prefailure": true }, "raw": { "value": 1904, "string": "1904" } },
+            { "id": 194, "name": "Temperature_Celsius", "value": 116, "worst": 103, "thresh": 0, "when_failed": "",
+              "flags": { "value": 34, "string": "-O---K ", "prefailure": false }, "raw": { "value": 34, "string": "34" } },
+            { "id": 197, "name": "Current_Pending_Sector", "value": 200, "worst": 200, "thresh": 0, "when_failed": "",
+              "flags": { "value": 50, "string": "-O--CK ", "prefailure": false }, "raw": { "value": 3, "string": "3" } },
+            { "id": 198, "name": "Offline_Uncorrectable", "value": 100, "worst": 253, "thresh": 0, "when_failed": "",
+              "flags": { "value": 48, "string": "----CK ", "prefailure": false }, "raw": { "value": 1, "string": "1" } }
+        ] }
+    }"#;
+
+    #[test]
+    fn json_ata_report() {
+        let mut report = SmartReport::default();
+        SmartctlOutput::parse(ATA_JSON).unwrap().apply_to(&mut report);
+
+        assert_eq!(report.health, HealthStatus::Ok);
+        assert_eq!(report.model.as_deref(), Some("WDC WD40EFRX-68N32N0"));
+        assert_eq!(report.serial_number.as_deref(), Some("WD-WCC7K0000000"));
+        assert_eq!(report.brand.as_deref(), Some("Western Digital Red"));
+        assert_eq!(report.power_on_hours, Some(31337));
+        assert_eq!(report.temperature, Some(34));
+        assert_eq!(report.reallocated_sectors, Some(1904));
+        assert_eq!(report.pending_sectors, Some(3));
+        assert_eq!(report.uncorrectable_sectors, Some(1));
+
+        assert_eq!(report.smart_attributes.len(), 5);
+        let reallocated = report.attribute(5).unwrap();
+        assert_eq!(reallocated.flags, "PO--CK");
+        assert_eq!(reallocated.when_failed, "now");
+        let failing: Vec<u8> = report.failing_attributes().map(|a| a.id).collect();
+        assert_eq!(failing, [5]);
+    }
+
+    #[test]
+    fn json_health_from_status_and_exit_bits() {
+        let health = |json: &str| {
+            let mut report = SmartReport::default();
+            SmartctlOutput::parse(json).unwrap().apply_to(&mut report);
+            report.health
+        };
+        assert_eq!(health(r#"{"smartctl": {"exit_status": 0}, "smart_status": {"passed": false}}"#), HealthStatus::Failing);
+        assert_eq!(health(r#"{"smartctl": {"exit_status": 16}, "smart_status": {"passed": true}}"#), HealthStatus::Warning);
+        assert_eq!(health(r#"{"smartctl": {"exit_status": 0}}"#), HealthStatus::Unknown);
+    }
+
+    #[test]
+    fn json_rejects_unopened_devices_and_text() {
+        assert!(SmartctlOutput::parse(r#"{"smartctl": {"exit_status": 2}}"#).is_none());
+        assert!(SmartctlOutput::parse(r#"{"smartctl": {"exit_status": 1}}"#).is_none());
+        assert!(SmartctlOutput::parse("smartctl 6.6 2017-11-05 r4594").is_none());
+    }
+
+    #[test]
+    fn json_nvme_health_log() {
+        let json = r#"{
+            "smartctl": { "exit_status": 0 },
+            "model_name": "Samsung SSD 980 PRO 1TB",
+            "serial_number": "S5GXNF0R000000",
+            "smart_status": { "passed": true },
+            "nvme_smart_health_information_log": {
+                "critical_warning": 4, "temperature": 41, "available_spare": 8, "available_spare_threshold": 10,
+                "percentage_used": 97, "data_units_read": 1000, "data_units_written": 2000, "power_on_hours": 8760,
+                "unsafe_shutdowns": 12, "media_errors": 2, "warning_temp_time": 5, "critical_comp_time": 1
+            }
+        }"#;
+        let mut report = SmartReport::default();
+        SmartctlOutput::parse(json).unwrap().apply_to(&mut report);
+
+        assert_eq!(report.power_on_hours, Some(8760));
+        assert_eq!(report.temperature, Some(41));
+        let nvme = report.nvme.unwrap();
+        assert_eq!(nvme.critical_warning, 4);
+        assert_eq!((nvme.available_spare, nvme.available_spare_threshold), (8, 10));
+        assert_eq!(nvme.percentage_used, 97);
+        assert_eq!((nvme.data_units_read, nvme.data_units_written), (1000, 2000));
+        assert_eq!((nvme.media_errors, nvme.unsafe_shutdowns), (2, 12));
+        assert_eq!((nvme.warning_temp_time, nvme.critical_comp_time), (5, 1));
+    }
+
+    #[test]
+    fn json_scsi_counters() {
+        let json = r#"{
+            "smartctl": { "exit_status": 0 },
+            "scsi_vendor": "SEAGATE", "scsi_product": "ST4000NM0023",
+            "scsi_grown_defect_list": 7,
+            "scsi_error_counter_log": { "read": { "total_uncorrected_errors": 2 }, "write": { "total_uncorrected_errors": 1 } }
+        }"#;
+        let mut report = SmartReport::default();
+        SmartctlOutput::parse(json).unwrap().apply_to(&mut report);
+
+        assert_eq!(report.brand.as_deref(), Some("SEAGATE"));
+        assert_eq!(report.model.as_deref(), Some("ST4000NM0023"));
+        assert_eq!(report.reallocated_sectors, Some(7));
+        assert_eq!(report.uncorrectable_sectors, Some(3));
+    }
+
+    #[test]
+    fn text_ata_report() {
+        let output = "\
+smartctl 6.6 2017-11-05 r4594 [x86_64-linux-4.19.0] (local build)
+=== START OF INFORMATION SECTION ===
+Model Family:     Seagate Barracuda 7200.14 (AF)
+Device Model:     ST2000DM001-1CH164
+Serial Number:    Z1E0AAAA
+=== START OF READ SMART DATA SECTION ===
+SMART overall-health self-assessment test result: PASSED
+
+ID# ATTRIBUTE_NAME          FLAG     VALUE WORST THRESH TYPE      UPDATED  WHEN_FAILED RAW_VALUE
+  1 Raw_Read_Error_Rate     0x000f   117   099   006    Pre-fail  Always       -       148285296
+  5 Reallocated_Sector_Ct   0x0033   100   100   010    Pre-fail  Always       -       16
+  9 Power_On_Hours          0x0032   062   062   000    Old_age   Always       -       33604
+ 10 Spin_Retry_Count        0x0013   097   097   097    Pre-fail  Always   FAILING_NOW 12
+184 End-to-End_Error        0x0032   100   100   099    Old_age   Always   In_the_past 0
+194 Temperature_Celsius     0x0022   036   045   000    Old_age   Always       -       36 (0 18 0 0 0)
+197 Current_Pending_Sector  0x0012   100   100   000    Old_age   Always       -       8
+198 Offline_Uncorrectable   0x0010   100   100   000    Old_age   Offline      -       8
+";
+        let mut report = SmartReport::default();
+        parse_text(output, &mut report);
+
+        assert_eq!(report.health, HealthStatus::Ok);
+        assert_eq!(report.model.as_deref(), Some("ST2000DM001-1CH164"));
+        assert_eq!(report.serial_number.as_deref(), Some("Z1E0AAAA"));
+        assert_eq!(report.brand.as_deref(), Some("Seagate Barracuda 7200.14 (AF)"));
+        assert_eq!(report.smart_attributes.len(), 8);
+        assert_eq!(report.power_on_hours, Some(33604));
+        assert_eq!(report.reallocated_sectors, Some(16));
+        assert_eq!(report.temperature, Some(36));
+        assert_eq!((report.pending_sectors, report.uncorrectable_sectors), (Some(8), Some(8)));
+
+        let temperature = report.attribute(194).unwrap();
+        assert_eq!(temperature.raw_string, "36 (0 18 0 0 0)");
+        assert_eq!((temperature.value, temperature.worst, temperature.thresh), (36, 45, 0));
+        assert_eq!(report.attribute(184).unwrap().when_failed, "past");
+        let failing: Vec<u8> = report.failing_attributes().map(|a| a.id).collect();
+        assert_eq!(failing, [10]);
+    }
+
+    #[test]
+    fn text_health_lines() {
+        let health = |line: &str| {
+            let mut report = SmartReport::default();
+            parse_text(line, &mut report);
+            report.health
+        };
+        assert_eq!(health("SMART overall-health self-assessment test result: FAILED!"), HealthStatus::Failing);
+        assert_eq!(health("SMART Health Status: OK"), HealthStatus::Ok);
+        assert_eq!(health("SMART Health Status: FIRMWARE IMPENDING FAILURE [asc=5d, ascq=10]"), HealthStatus::Warning);
+        assert_eq!(health("No health line here"), HealthStatus::Unknown);
+    }
+
+    #[test]
+    fn text_nvme_report() {
+        let output = "\
+=== START OF INFORMATION SECTION ===
+Model Number:                       Samsung SSD 970 EVO Plus 1TB
+Serial Number:                      S4EWNX0N000000
+=== START OF SMART DATA SECTION ===
+SMART overall-health self-assessment test result: PASSED
+
+SMART/Health Information (NVMe Log 0x02)
+Critical Warning:                   0x01
+Temperature:                        38 Celsius
+Available Spare:                    9%
+Available Spare Threshold:          10%
+Percentage Used:                    3%
+Data Units Read:                    12,345,678 [6.32 TB]
+Data Units Written:                 23,456,789 [12.0 TB]
+Power On Hours:                     1,234
+Unsafe Shutdowns:                   56
+Media and Data Integrity Errors:    0
+Warning  Comp. Temperature Time:    7
+Critical Comp. Temperature Time:    0
+";
+        let mut report = SmartReport::default();
+        parse_text(output, &mut report);
+
+        assert_eq!(report.health, HealthStatus::Ok);
+        assert_eq!(report.model.as_deref(), Some("Samsung SSD 970 EVO Plus 1TB"));
+        assert_eq!(report.temperature, Some(38));
+        assert_eq!(report.power_on_hours, Some(1234));
+        assert!(report.smart_attributes.is_empty());
+        let nvme = report.nvme.unwrap();
+        assert_eq!(nvme.critical_warning, 1);
+        assert_eq!((nvme.available_spare, nvme.available_spare_threshold, nvme.percentage_used), (9, 10, 3));
+        assert_eq!((nvme.data_units_read, nvme.data_units_written), (12_345_678, 23_456_789));
+        assert_eq!((nvme.unsafe_shutdowns, nvme.media_errors, nvme.warning_temp_time), (56, 0, 7));
+    }
+}
@@ -1,23 +1,43 @@
 // Author: Monstertov
 // Purpose: Cross-platform disk space monitor and email alert tool (Rust version of diskmon.py)
 
-use lettre::{Message, SmtpTransport, Transport, transport::smtp::authentication::Credentials, transport::smtp::client::Tls, transport::smtp::client::TlsParameters};
 use clap::Parser;
 use colored::*;
+use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH, Duration};
 use tokio::time::timeout;
 use futures::future::join_all;
-use backoff::{ExponentialBackoff, backoff::Backoff};
 use log::{info, warn, error, debug};
 
+mod alerts;
 mod config;
+mod daemon;
+mod forecast;
+mod history;
+mod http;
+mod metrics;
+mod nagios;
+mod notifiers;
+mod rules;
+mod smart;
+mod state;
 mod system;
+mod systemd;
+#[cfg(test)]
+mod testing;
+mod units;
+
+use alerts::{Alert, Condition, Severity};
+use smart::{Counter, CounterChange, HealthMethod, HealthStatus, SmartReport};
 
 #[cfg(target_os = "linux")]
 pub mod linux;
 #[cfg(target_os = "windows")]
 pub mod windows;
 
+// Exit codes: 0 OK, 1 warning, 2 critical (see Severity::exit_code), 3 when the run itself failed
+const EXIT_ERROR: i32 = 3;
+
 /// Cross-platform disk space monitor and email alert tool
 #[derive(Parser)]
 #[command(name = "diskmon-mail")]
@@ -30,33 +50,184 @@ struct Cli {
     /// Display SMART status for all detected disks
     #[arg(long)]
     smart: bool,
-    /// Output results in JSON format
+    /// Output results in JSON format (same as --format json)
     #[arg(long)]
     json: bool,
+    /// Run as a Nagios/Icinga plugin: print one status line with perfdata and exit 0-3 (same as --format nagios)
+    #[arg(long)]
+    nagios: bool,
+    /// Output format: text, json, prometheus or nagios. Machine-readable formats print only the result and send no mail
+    #[arg(long, value_enum, default_value = "text")]
+    format: OutputFormat,
+    /// Also write the metrics to diskmon.prom in this node_exporter textfile collector directory
+    #[arg(long, value_name = "DIR")]
+    textfile_dir: Option<std::path::PathBuf>,
     /// SMART collection timeout in seconds (default: 30)
     #[arg(long, default_value = "30")]
     smart_timeout: u64,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+    Prometheus,
+    Nagios,
+}
+
+impl Cli {
+    fn output_format(&self) -> OutputFormat {
+        if self.nagios {
+            OutputFormat::Nagios
+        } else if self.json {
+            OutputFormat::Json
+        } else {
+            self.format
+        }
+    }
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Show recorded usage history per mount point
+    History(history::HistoryArgs),
+    /// Keep running and check at check_interval, refreshing SMART data at smart_interval
+    Daemon,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
 struct DiskInfo {
     mount_point: String,
+    device: String,
     display_name: String, // Drive letter for Windows, mount point for Unix
+    label: Option<String>,
     free_space_percent: f64,
     total_space: u64,
     available_space: u64,
     file_system: String,
-    smart_status: Option<String>,
-    serial_number: Option<String>,
-    brand: Option<String>,
-    model: Option<String>,
-    is_raid: bool,
-    power_on_hours: Option<u64>,
-    reallocated_sectors: Option<u64>,
-    temperature: Option<i64>,
-    pending_sectors: Option<u64>,
-    uncorrectable_sectors: Option<u64>,
-    health_method: String, // New: method used for health check
+    total_inodes: u64,
+    free_inodes: u64,
+    inode_free_percent: Option<f64>, // None when the filesystem doesn't report inodes (e.g. btrfs)
+    thresholds: rules::Thresholds,
+    forecast: Option<forecast::Forecast>, // None without enough usage history
+    smart: SmartReport,
+    counter_changes: Vec<CounterChange>, // SMART error counters compared with the previous run
+    alerts: Vec<Alert>,
+    pending_alerts: Vec<Alert>, // Conditions that haven't held for alert_after_checks runs yet
+}
+
+impl DiskInfo {
+    fn new(display_name: String, fs: system::FilesystemInfo, thresholds: rules::Thresholds, forecast: Option<forecast::Forecast>,
+           smart: SmartReport, limits: &alerts::Limits, alert_state: &state::State) -> Self {
+        let mut disk = DiskInfo {
+            free_space_percent: fs.free_space_percent(),
+            inode_free_percent: fs.inode_free_percent(),
+            mount_point: fs.mount_point,
+            device: fs.device,
+            display_name,
+            label: fs.label,
+            total_space: fs.total_space,
+            available_space: fs.available_space,
+            file_system: fs.fs_type,
+            total_inodes: fs.total_inodes,
+            free_inodes: fs.free_inodes,
+            thresholds,
+            forecast,
+            counter_changes: alert_state.counter_changes(&smart),
+            smart,
+            alerts: Vec::new(),
+            pending_alerts: Vec::new(),
+        };
+        let history = alert_state.history(&disk.mount_point);
+        (disk.alerts, disk.pending_alerts) = alerts::evaluate(&disk, limits, &history)
+            .into_iter()
+            .partition(|a| a.checks >= limits.alert_after_checks || a.condition.is_event());
+        disk
+    }
+
+    /// What SMART data is collected for: the device on Unix, the drive letter on Windows.
+    fn smart_target(&self) -> String {
+        smart_target(&self.mount_point, &self.device)
+    }
+
+    /// Worst severity among this disk's alerts, None when everything is OK.
+    fn severity(&self) -> Option<Severity> {
+        self.alerts.iter().map(|a| a.severity).max()
+    }
+
+    fn has_alert(&self, condition: Condition) -> bool {
+        self.alerts.iter().any(|a| a.condition == condition)
+    }
+
+    /// A SMART counter for reports, as "previous → current" when it changed since the last run.
+    fn counter_text(&self, counter: Counter) -> Option<String> {
+        match self.counter_changes.iter().find(|c| c.counter == counter) {
+            Some(change) => Some(change.to_string()),
+            None => self.smart.counter(counter).map(|value| value.to_string()),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct JsonAlert {
+    disk: String,
+    mount_point: String,
+    #[serde(flatten)]
+    alert: Alert,
+}
+
+/// The `--json` document, also posted by the webhook notifier.
+#[derive(serde::Serialize)]
+struct JsonOutput<'a> {
+    system_info: &'a system::SystemInfo,
+    disks: &'a [DiskInfo],
+    threshold_percent: f64,
+    inode_threshold_percent: f64,
+    smartctl_available: bool,
+    status: &'static str, // "ok", "warning" or "critical"
+    alerts: Vec<JsonAlert>,
+}
+
+impl<'a> JsonOutput<'a> {
+    fn new(cfg: &config::Config, system_info: &'a system::SystemInfo, disks: &'a [DiskInfo], smartctl_available: bool) -> Self {
+        let alerts = disks.iter()
+            .flat_map(|disk| disk.alerts.iter().map(|alert| JsonAlert {
+                disk: disk.display_name.clone(),
+                mount_point: disk.mount_point.clone(),
+                alert: alert.clone(),
+            }))
+            .collect();
+
+        JsonOutput {
+            system_info,
+            disks,
+            threshold_percent: cfg.threshold_percent.unwrap_or(10.0),
+            inode_threshold_percent: cfg.inode_threshold_percent.unwrap_or(10.0),
+            smartctl_available,
+            status: disks.iter().filter_map(|d| d.severity()).max().map_or("ok", |s| s.as_str()),
+            alerts,
+        }
+    }
+}
+
+/// Whether smartctl can be run (also looked for in its default install location on Windows).
+fn smartctl_available() -> bool {
+    if cfg!(windows) {
+        std::process::Command::new("smartctl").arg("--version").output().is_ok() ||
+        std::process::Command::new("C:\\Program Files\\smartmontools\\bin\\smartctl.exe").arg("--version").output().is_ok()
+    } else {
+        std::process::Command::new("smartctl").arg("--version").output().is_ok()
+    }
+}
+
+fn smart_target(mount_point: &str, device: &str) -> String {
+    if cfg!(windows) {
+        mount_point.to_string()
+    } else {
+        device.to_string()
+    }
 }
 
 // Check if terminal supports colors
@@ -99,12 +270,65 @@ fn init_colors() {
     }
 }
 
-async fn get_monitored_disks(cfg: &config::Config, debug: bool, smart_timeout: u64) -> Vec<DiskInfo> {
+// Check a Unix filesystem against excluded_disks. An entry matches the mount point, the device
+// path, or any block device underneath it (partition, LVM/dm volume, md array or physical disk),
+// so excluding "sda" also excludes /dev/sda1 and LVM volumes that live on sda. A mount point also
+// excludes everything mounted below it ("/boot" covers "/boot/efi"), and entries with * ? or [
+// are globs matched against the mount point, the device and its block stack. Case is ignored.
+fn is_excluded_unix(excluded: &[String], found_excluded: &mut [bool], mount_point: &str, device: &str) -> bool {
+    if excluded.iter().all(|ex| ex.trim().is_empty()) {
+        return false;
+    }
+    let device_short = device.rsplit('/').next().unwrap_or(device);
+    let aliases = system::device_aliases(device);
+    let glob_options = glob::MatchOptions { case_sensitive: false, ..Default::default() };
+    excluded.iter().enumerate().any(|(i, ex)| {
+        let ex = ex.trim();
+        if ex.is_empty() { return false; }
+        let found = if ex.contains(['*', '?', '[']) {
+            glob::Pattern::new(ex).is_ok_and(|p| {
+                [mount_point, device, device_short].into_iter().chain(aliases.iter().map(String::as_str))
+                    .any(|value| p.matches_with(value, glob_options))
+            })
+        } else {
+            let ex_short = ex.strip_prefix("/dev/").unwrap_or(ex);
+            is_under_mount_point(mount_point, ex)
+                || ex.eq_ignore_ascii_case(device)
+                || ex_short.eq_ignore_ascii_case(device_short)
+                || aliases.iter().any(|a| a.eq_ignore_ascii_case(ex_short))
+        };
+        if found { found_excluded[i] = true; }
+        found
+    })
+}
+
+// USB sticks and other removable media are mounted here; skipped unless monitor_removable is set
+fn is_removable_mount(mount_point: &str) -> bool {
+    ["/media/", "/mnt/", "/run/media/"].iter().any(|prefix| mount_point.starts_with(prefix))
+}
+
+// "/boot" matches "/boot" and "/boot/efi" but not "/bootstrap"
+fn is_under_mount_point(mount_point: &str, entry: &str) -> bool {
+    if !entry.starts_with('/') || entry.starts_with("/dev/") {
+        return false;
+    }
+    let entry = if entry == "/" { entry } else { entry.trim_end_matches('/') };
+    let (mount_point, entry) = (mount_point.to_lowercase(), entry.to_lowercase());
+    mount_point == entry
+        || (entry != "/" && mount_point.strip_prefix(&entry).is_some_and(|rest| rest.starts_with('/')))
+}
+
+/// Enumerate the monitored filesystems and evaluate their alerts. SMART data found in
+/// `smart_cache` (keyed by DiskInfo::smart_target) is reused instead of probing the drive again.
+async fn get_monitored_disks(cfg: &config::Config, debug: bool, smart_timeout: u64, alert_state: &state::State,
+                             smart_cache: Option<&HashMap<String, SmartReport>>,
+                             usage_history: Option<&history::History>) -> Vec<DiskInfo> {
     let disks = sysinfo::Disks::new_with_refreshed_list();
     let mut disk_candidates = Vec::new();
     let mut excluded_not_found = Vec::new();
     let excluded = cfg.excluded_disks.clone().unwrap_or_default();
     let mut found_excluded = vec![false; excluded.len()];
+    let monitor_removable = cfg.monitor_removable.unwrap_or(false);
     
     // Check if health checks are enabled (default to true if not specified)
     let health_check_enabled = cfg.health_check_enabled.unwrap_or(true);
@@ -123,161 +347,45 @@ async fn get_monitored_disks(cfg: &config::Config, debug: bool, smart_timeout: u
         }
     }
 
-    // On Unix systems prefer `df` parsing so we include all mount points that `df` shows
-    // (this captures bind mounts, LVM, etc. that sysinfo may not enumerate). If `df` isn't
-    // available or parsing fails, fall back to the sysinfo iteration below.
-    if cfg!(unix) {
-        // Try df -T -P first (modern systems: Debian, Ubuntu, RHEL 8+, etc.)
-        let mut df_parsed = false;
-        if let Ok(output) = std::process::Command::new("df").arg("-T").arg("-P").output() {
-            if output.status.success() {
-                if let Ok(text) = String::from_utf8(output.stdout) {
+    // On Linux, enumerate mounts natively from /proc/self/mountinfo with statvfs sizes. This covers
+    // LVM, md and bind mounts without depending on the format or locale of `df`. If enumeration
+    // fails or isn't supported on this platform, fall back to the sysinfo iteration below.
+    match system::list_filesystems(debug) {
+        Ok(filesystems) => {
+            for fs in filesystems {
+                let display_name = format!("{} ({})", fs.mount_point, fs.device);
+
+                if !monitor_removable && is_removable_mount(&fs.mount_point) {
                     if debug {
-                        debug!("Attempting to parse 'df -T -P' output");
-                    }
-                    // Parse df -T -P format:
-                    // Filesystem Type 1024-blocks Used Available Capacity Mounted on
-                    for (i, line) in text.lines().enumerate() {
-                        if i == 0 { continue; } // Skip header
-                        let parts: Vec<&str> = line.split_whitespace().collect();
-                        if parts.len() < 6 { continue; }
-                        
-                        let filesystem = parts[0].to_string();
-                        let fstype = parts[1].to_string();
-                        let size_k = parts[2];
-                        let available_k = parts[4];
-
-                        // Exclude common pseudo filesystems
-                        let pseudo_fs = ["tmpfs", "devtmpfs", "proc", "sysfs", "cgroup", "overlay", "squashfs", 
-                                       "securityfs", "rpc_pipefs", "fusectl", "mqueue", "hugetlbfs", "autofs", "binfmt_misc"];
-                        if pseudo_fs.contains(&fstype.as_str()) {
-                            continue;
-                        }
-
-                        // Parse sizes (in 1K-blocks)
-                        let total = match size_k.parse::<u64>() {
-                            Ok(v) => v.saturating_mul(1024),
-                            Err(_) => continue,
-                        };
-                        let available = match available_k.parse::<u64>() {
-                            Ok(v) => v.saturating_mul(1024),
-                            Err(_) => continue,
-                        };
-                        if total == 0 { continue; }
-                        let free_space_percent = (available as f64 / total as f64) * 100.0;
-
-                        // Mount point is at index 5+ (collect remaining parts in case of spaces)
-                        let mount_point = parts[5..].join(" ");
-
-                        let display_name = format!("{} ({})", mount_point, filesystem);
-
-                        // Respect excluded_disks config
-                        let is_excluded = excluded.iter().enumerate().any(|(idx, ex)| {
-                            let ex = ex.trim();
-                            if ex.is_empty() { return false; }
-                            let ex_up = ex.to_uppercase();
-                            let mount_up = mount_point.to_uppercase();
-                            let dev_up = filesystem.to_uppercase();
-                            let found = mount_up.contains(&ex_up) || dev_up.contains(&ex_up);
-                            if found { found_excluded[idx] = true; }
-                            found
-                        });
-                        if is_excluded { continue; }
-
-                        if debug {
-                            debug!("[df -T -P] Added disk: {} (total={} bytes, available={} bytes, {}%)", 
-                                   display_name, total, available, free_space_percent);
-                        }
-                        disk_candidates.push((mount_point, display_name, free_space_percent, total, available, fstype, filesystem));
-                        df_parsed = true;
+                        debug!("Skipping removable media mount: {}", display_name);
                     }
+                    continue;
                 }
-            }
-        }
 
-        // Fallback to df -k for RHEL 7 and older systems that don't support df -T
-        if !df_parsed {
-            if let Ok(output) = std::process::Command::new("df").arg("-k").output() {
-                if output.status.success() {
-                    if let Ok(text) = String::from_utf8(output.stdout) {
-                        if debug {
-                            debug!("df -T -P failed or produced no results; attempting 'df -k' (RHEL 7 compatibility)");
-                        }
-                        // Parse df -k format:
-                        // Filesystem 1024-blocks Used Available Use% Mounted on
-                        for (i, line) in text.lines().enumerate() {
-                            if i == 0 { continue; } // Skip header
-                            let parts: Vec<&str> = line.split_whitespace().collect();
-                            if parts.len() < 5 { continue; }
-                            
-                            let filesystem = parts[0].to_string();
-                            let size_k = parts[1];
-                            let available_k = parts[3];
-                            // Mount point is at index 4+ (collect remaining parts)
-                            let mount_point = parts[4..].join(" ");
-
-                            // Try to infer fstype from mount output (rough heuristic)
-                            let fstype = if mount_point == "/" || mount_point.starts_with("/boot") {
-                                "ext4".to_string() // Common root/boot filesystem
-                            } else if filesystem.starts_with("/dev/") {
-                                "ext4".to_string() // Assume ext4 for /dev/ entries
-                            } else {
-                                "unknown".to_string()
-                            };
-
-                            // Exclude pseudo filesystems by device/mount patterns
-                            if filesystem.starts_with("tmpfs") || filesystem.starts_with("devtmpfs") || 
-                               filesystem.starts_with("sysfs") || filesystem.starts_with("proc") ||
-                               filesystem.starts_with("cgroup") || mount_point.starts_with("/sys") ||
-                               mount_point.starts_with("/proc") || mount_point.starts_with("/dev") {
-                                continue;
-                            }
-
-                            let total = match size_k.parse::<u64>() {
-                                Ok(v) => v.saturating_mul(1024),
-                                Err(_) => continue,
-                            };
-                            let available = match available_k.parse::<u64>() {
-                                Ok(v) => v.saturating_mul(1024),
-                                Err(_) => continue,
-                            };
-                            if total == 0 { continue; }
-                            let free_space_percent = (available as f64 / total as f64) * 100.0;
-
-                            let display_name = format!("{} ({})", mount_point, filesystem);
-
-                            let is_excluded = excluded.iter().enumerate().any(|(idx, ex)| {
-                                let ex = ex.trim();
-                                if ex.is_empty() { return false; }
-                                let ex_up = ex.to_uppercase();
-                                let mount_up = mount_point.to_uppercase();
-                                let dev_up = filesystem.to_uppercase();
-                                let found = mount_up.contains(&ex_up) || dev_up.contains(&ex_up);
-                                if found { found_excluded[idx] = true; }
-                                found
-                            });
-                            if is_excluded { continue; }
-
-                            if debug {
-                                debug!("[df -k] Added disk: {} (total={} bytes, available={} bytes, {}%)", 
-                                       display_name, total, available, free_space_percent);
-                            }
-                            disk_candidates.push((mount_point, display_name, free_space_percent, total, available, fstype, filesystem));
-                            df_parsed = true;
-                        }
+                // Respect excluded_disks config
+                if is_excluded_unix(&excluded, &mut found_excluded, &fs.mount_point, &fs.device) {
+                    if debug {
+                        debug!("Excluding disk: {}", display_name);
                     }
+                    continue;
                 }
-            }
-        }
 
-        if debug && disk_candidates.is_empty() {
-            debug!("No disks detected via df parsing; will fall back to sysinfo enumeration");
+                if debug {
+                    debug!("[mountinfo] Added disk: {} (fs={}, total={} bytes, available={} bytes, {:.1}%)",
+                           display_name, fs.fs_type, fs.total_space, fs.available_space, fs.free_space_percent());
+                }
+                disk_candidates.push((display_name, fs));
+            }
+            if debug && disk_candidates.is_empty() {
+                debug!("No filesystems detected via mountinfo; will fall back to sysinfo enumeration");
+            }
         }
+        Err(e) => warn!("Native filesystem enumeration failed, falling back to sysinfo: {}", e),
     }
 
-    // If running on non-Unix or `df` parsing produced no candidates, fall back to sysinfo enumeration
+    // If running on non-Linux or native enumeration produced no candidates, fall back to sysinfo enumeration
     if disk_candidates.is_empty() {
-        for (_disk_idx, disk) in disks.list().iter().enumerate() {
+        for disk in disks.list().iter() {
             let mount_point = match disk.mount_point().to_str() {
                 Some(path) => path.to_string(),
                 None => continue,
@@ -287,10 +395,8 @@ async fn get_monitored_disks(cfg: &config::Config, debug: bool, smart_timeout: u
                 if mount_point.starts_with("\\\\") || mount_point.starts_with("A:") || mount_point.starts_with("B:") {
                     continue;
                 }
-            } else {
-                if mount_point.starts_with("/media/") || mount_point.starts_with("/mnt/") || mount_point.starts_with("/run/media/") {
-                    continue;
-                }
+            } else if !monitor_removable && is_removable_mount(&mount_point) {
+                continue;
             }
 
             let total = disk.total_space();
@@ -300,11 +406,9 @@ async fn get_monitored_disks(cfg: &config::Config, debug: bool, smart_timeout: u
                 continue;
             }
 
-            let free_space_percent = (available as f64 / total as f64) * 100.0;
-
             let display_name = if cfg!(windows) {
                 if mount_point.len() >= 2 && mount_point.chars().nth(1) == Some(':') {
-                    format!("Drive {}", mount_point.chars().nth(0).unwrap().to_uppercase())
+                    format!("Drive {}", mount_point.chars().next().unwrap().to_uppercase())
                 } else {
                     mount_point.clone()
                 }
@@ -324,30 +428,32 @@ async fn get_monitored_disks(cfg: &config::Config, debug: bool, smart_timeout: u
                     found
                 })
             } else {
-                excluded.iter().enumerate().any(|(i, ex)| {
-                    let ex = ex.trim();
-                    if ex.is_empty() { return false; }
-                    let dev = disk.name().to_str().unwrap_or("");
-                    let found = dev == ex;
-                    if found { found_excluded[i] = true; }
-                    found
-                })
+                is_excluded_unix(&excluded, &mut found_excluded, &mount_point, disk.name().to_str().unwrap_or(""))
             };
             if debug && is_excluded {
                 debug!("Excluding disk: {} (display_name: {}, dev: {:?})", mount_point, display_name, disk.name());
             }
             if is_excluded { continue; }
 
-            let file_system = disk.file_system().to_str().unwrap_or("Unknown").to_string();
-
             // Store disk information for parallel SMART collection
-            disk_candidates.push((mount_point, display_name, free_space_percent, total, available, file_system, disk.name().to_str().unwrap_or("").to_string()));
+            disk_candidates.push((display_name, system::FilesystemInfo {
+                mount_point,
+                device: disk.name().to_str().unwrap_or("").to_string(),
+                label: None,
+                fs_type: disk.file_system().to_str().unwrap_or("Unknown").to_string(),
+                mount_options: String::new(),
+                total_space: total,
+                free_space: available,
+                available_space: available,
+                total_inodes: 0,
+                free_inodes: 0,
+            }));
         }
     }
 
     // Collect excluded disks that were not found
     for (i, found) in found_excluded.iter().enumerate() {
-        if !*found {
+        if !*found && !excluded[i].trim().is_empty() {
             excluded_not_found.push(excluded[i].clone());
         }
     }
@@ -355,30 +461,57 @@ async fn get_monitored_disks(cfg: &config::Config, debug: bool, smart_timeout: u
         warn!("The following excluded_disks were not found: {}", excluded_not_found.join(", "));
     }
 
+    // Resolve per-filesystem thresholds: first matching rule wins, else the global thresholds
+    let rules = cfg.rules.clone().unwrap_or_default();
+    let defaults = rules::Thresholds::defaults(cfg);
+
+    // Fill rate forecasts from the usage history plus the current measurement
+    let now = unix_now();
+    let window_start = now.saturating_sub(cfg.forecast_window.map_or(7 * 86_400, |w| w.0) as i64);
+
+    let disk_candidates: Vec<_> = disk_candidates.into_iter().map(|(display_name, fs)| {
+        let thresholds = rules::resolve(&rules, &defaults, &fs);
+        if debug {
+            debug!("Thresholds for {}: {}", display_name, thresholds.describe());
+        }
+        let forecast = usage_history.and_then(|h| {
+            let mut points = h.available_series(&fs.mount_point, window_start)
+                .map_err(|e| warn!("{}", e))
+                .unwrap_or_default();
+            points.push((now, fs.available_space));
+            forecast::estimate(&points, &thresholds, fs.total_space)
+        });
+        if debug && let Some(forecast) = &forecast {
+            debug!("Forecast for {}: {}", display_name, forecast.describe());
+        }
+        (display_name, fs, thresholds, forecast)
+    }).collect();
+    let limits = alerts::Limits::from_config(cfg);
+
     // Parallel SMART status collection with timeout
     if health_check_enabled {
-        let smart_futures = disk_candidates.iter().map(|(mount_point, _, _, _, _, _, disk_name)| {
-            let smart_input = if cfg!(windows) {
-                mount_point.clone()
-            } else {
-                disk_name.clone()
-            };
+        let smart_futures = disk_candidates.iter().map(|(_, fs, _, _)| {
+            let smart_input = smart_target(&fs.mount_point, &fs.device);
+            let cached = smart_cache.and_then(|cache| cache.get(&smart_input)).cloned();
             let timeout_duration = Duration::from_secs(smart_timeout);
             
             async move {
+                if let Some(report) = cached {
+                    return report;
+                }
                 let smart_input_clone = smart_input.clone();
                 let smart_input_clone2 = smart_input.clone();
                 match timeout(timeout_duration, tokio::task::spawn_blocking(move || {
-                    system::get_smart_status(&smart_input, debug)
+                    system::get_smart_status(&smart_input, debug, timeout_duration)
                 })).await {
                     Ok(Ok(result)) => result,
                     Ok(Err(_)) => {
                         warn!("SMART collection task panicked for disk: {}", smart_input_clone);
-                        (None, None, None, None, false, None, None, None, None, None, "error".to_string())
+                        SmartReport::unavailable(HealthMethod::Error)
                     },
                     Err(_) => {
                         warn!("SMART collection timed out for disk: {} ({}s)", smart_input_clone2, smart_timeout);
-                        (None, None, None, None, false, None, None, None, None, None, "timeout".to_string())
+                        SmartReport::unavailable(HealthMethod::Timeout)
                     }
                 }
             }
@@ -387,28 +520,11 @@ async fn get_monitored_disks(cfg: &config::Config, debug: bool, smart_timeout: u
         let smart_results = join_all(smart_futures).await;
 
         // Combine disk info with SMART results
-        let final_disks: Vec<DiskInfo> = disk_candidates.into_iter().zip(smart_results.into_iter())
-            .map(|((mount_point, display_name, free_space_percent, total, available, file_system, _), (smart_status, serial_number, brand, model, is_raid, power_on_hours, reallocated_sectors, temperature, pending_sectors, uncorrectable_sectors, health_method))| {
-                DiskInfo {
-                    mount_point,
-                    display_name,
-                    free_space_percent,
-                    total_space: total,
-                    available_space: available,
-                    file_system,
-                    smart_status,
-                    serial_number,
-                    brand,
-                    model,
-                    is_raid,
-                    power_on_hours,
-                    reallocated_sectors,
-                    temperature,
-                    pending_sectors,
-                    uncorrectable_sectors,
-                    health_method,
-                }
-            }).collect();
+        let final_disks: Vec<DiskInfo> = disk_candidates.into_iter().zip(smart_results)
+            .map(|((display_name, fs, thresholds, forecast), smart)| {
+                DiskInfo::new(display_name, fs, thresholds, forecast, smart, &limits, alert_state)
+            })
+            .collect();
 
         if debug {
             debug!("Final monitored disks:");
@@ -427,27 +543,11 @@ async fn get_monitored_disks(cfg: &config::Config, debug: bool, smart_timeout: u
         final_disks
     } else {
         // Health checks disabled - convert to final format
-        let final_disks: Vec<DiskInfo> = disk_candidates.into_iter().map(|(mount_point, display_name, free_space_percent, total, available, file_system, _)| {
-            DiskInfo {
-                mount_point,
-                display_name,
-                free_space_percent,
-                total_space: total,
-                available_space: available,
-                file_system,
-                smart_status: None,
-                serial_number: None,
-                brand: None,
-                model: None,
-                is_raid: false,
-                power_on_hours: None,
-                reallocated_sectors: None,
-                temperature: None,
-                pending_sectors: None,
-                uncorrectable_sectors: None,
-                health_method: "disabled".to_string(),
-            }
-        }).collect();
+        let final_disks: Vec<DiskInfo> = disk_candidates.into_iter()
+            .map(|(display_name, fs, thresholds, forecast)| {
+                DiskInfo::new(display_name, fs, thresholds, forecast, SmartReport::unavailable(HealthMethod::Disabled), &limits, alert_state)
+            })
+            .collect();
 
         if debug {
             debug!("Final monitored disks (health checks disabled):");
@@ -467,325 +567,179 @@ async fn get_monitored_disks(cfg: &config::Config, debug: bool, smart_timeout: u
     }
 }
 
-async fn send_system_report(cfg: &config::Config, disks: &[DiskInfo], system_info: &system::SystemInfo, forced: bool, debug: bool) -> Result<(), String> {
-    if !cfg.mail_enabled {
-        println!("{} System report: {} disk(s) monitored. Mail not sent.", 
-                 "[TEST MODE]".yellow().bold(), 
-                 disks.len().to_string().cyan());
-        return Ok(());
-    }
-    
-    // Determine friendly name for this device (by hostname)
-    let display_name = cfg.friendly_name.as_deref().unwrap_or(&system_info.hostname);
+/// Log level for the `debug` setting.
+fn log_level(debug: bool) -> log::LevelFilter {
+    if debug { log::LevelFilter::Debug } else { log::LevelFilter::Info }
+}
 
-    let mut subject = if forced {
-        format!("[RAPORT ZILNIC] System Disk Report - {} ({})", display_name, format!("{} {} {}", system_info.os_name, system_info.os_version, system_info.architecture))
-    } else {
-        format!("[ALARMĂ DEPĂSIRE PRAG DE STOCARE ] System Disk Report - {} ({})", display_name, format!("{} {} {}", system_info.os_name, system_info.os_version, system_info.architecture))
-    };
-    
-    let os_info = format!("{} {} {}", system_info.os_name, system_info.os_version, system_info.architecture);
-    let threshold = cfg.threshold_percent.unwrap_or(10.0);
-    
-    // Format current time in DD-MM-YYYY HH:MM:SS format
-    let datetime = match SystemTime::now().duration_since(UNIX_EPOCH) {
-        Ok(duration) => {
-            let secs = duration.as_secs();
-            let datetime = chrono::DateTime::from_timestamp(secs as i64, 0)
-                .unwrap_or_else(|| chrono::Utc::now());
-            let local_datetime = datetime.with_timezone(&chrono::Local);
-            local_datetime.format("%d-%m-%Y %H:%M:%S").to_string()
-        },
-        Err(_) => "unknown time".to_string(),
-    };
-    
-    // Check if smartmontools is available for the email report
-    let smartctl_available = if cfg!(windows) {
-        std::process::Command::new("smartctl").arg("--version").output().is_ok() ||
-        std::process::Command::new("C:\\Program Files\\smartmontools\\bin\\smartctl.exe").arg("--version").output().is_ok()
-    } else {
-        std::process::Command::new("smartctl").arg("--version").output().is_ok()
-    };
+/// Current time as Unix seconds.
+fn unix_now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
 
-    let mut body = format!(
-        "<html><body><pre style=\"font-family: monospace;\">\n\
-         <b>System Disk Report</b>\n\n\
-         <b>Device:</b> {} ({})\n\
-         <b>System:</b> {} {}\n\
-         <b>Hostname:</b> {}\n\
-         <b>Report Time:</b> {}\n\
-         <b>Mode:</b> {}\n\
-         <b>SMART Tools:</b> {}\n\
-         <b>Virtualization:</b> {}\n\n",
-        display_name,
-        system_info.hostname,
-        os_info,
-        if system_info.is_virtualized { "(Virtualized)" } else { "" },
-        system_info.hostname,
-        datetime,
-        if forced { "Forced Report" } else if debug { "Debug Mode" } else { "Normal Scan" },
-        if smartctl_available { "smartmontools detected - enhanced disk health monitoring" } else { "smartmontools not detected - using fallback methods" },
-        if system_info.is_virtualized { "Yes - Running in virtualized environment" } else { "No - Running on physical hardware" }
-    );
-
-    // Add disk summary
-    let total_disks = disks.len();
-    let low_space_disks = disks.iter().filter(|d| d.free_space_percent < threshold).count();
-    let smart_failing_disks = disks.iter().filter(|d| {
-        d.smart_status.as_deref().unwrap_or("OK").to_uppercase() != "OK"
-    }).count();
-    let unknown_smart_disks = disks.iter().filter(|d| d.smart_status.is_none()).count();
-
-    // If any disk is below threshold or SMART failing, mark this report as an alert
-    let alert_present = low_space_disks > 0 || smart_failing_disks > 0;
-    if alert_present {
-        // Prefix subject with a visible alert marker (emoji + label). Coloring the subject is not
-        // widely supported in mail clients, so we use an emoji and [ALERT] prefix instead.
-        subject = format!("🔴 [ALERT] {}", subject);
-        // Inject a visible red banner into the HTML body so it stands out in email clients.
-        // We insert the banner before the existing <pre> so the monospace report follows.
-        body = body.replacen(
-            "<html><body><pre",
-            "<html><body><h2 style=\"color:red; margin-bottom:6px\">ALERT: One or more disks require attention</h2><pre",
-            1,
-        );
-    } else if forced {
-        // Forced report with no alerts: add a green marker to the subject so it's obvious
-        // that this was a forced/test run and no issues were detected.
-        subject = format!("🟢 {}", subject);
+/// Format a Unix timestamp as local time in DD-MM-YYYY HH:MM:SS format.
+fn format_local_time(secs: i64) -> String {
+    match chrono::DateTime::from_timestamp(secs, 0) {
+        Some(datetime) => datetime.with_timezone(&chrono::Local).format("%d-%m-%Y %H:%M:%S").to_string(),
+        None => "unknown time".to_string(),
     }
+}
 
-    body.push_str(&format!(
-        "<b>Disk Summary:\n\
-         - <b>Total Disks:</b> {}\n\
-         - <b>Low Space (<{}%):</b> {}\n\
-         - <b>SMART Failing:</b> {}\n\
-         - <b>SMART Unknown:</b> {}\n\n",
-        total_disks, threshold, low_space_disks, smart_failing_disks, unknown_smart_disks
-    ));
-
-    // Add warnings for RAID and missing health info
-    let mut no_health_info = false;
-    let mut any_raid = false;
-    for disk in disks {
-        if disk.smart_status.is_none() || disk.smart_status.as_deref() == Some("N/A") {
-            no_health_info = true;
-        }
-        if disk.is_raid {
-            any_raid = true;
+/// Open the usage history database, if enabled. Failures are only logged.
+fn open_history(cfg: &config::Config) -> Option<history::History> {
+    if !cfg.history_enabled.unwrap_or(true) {
+        return None;
+    }
+    history::History::open(history::database_path(cfg))
+        .map_err(|e| warn!("{}; forecasts and history recording unavailable", e))
+        .ok()
+}
+
+/// Append this run's measurements to the usage history. Failures are only logged.
+fn record_history(usage_history: Option<&mut history::History>, cfg: &config::Config, disks: &[DiskInfo], debug: bool) {
+    if let Some(usage_history) = usage_history {
+        match usage_history.record_run(cfg, disks, unix_now()) {
+            Ok(Some(pruned)) if debug => debug!("Recorded {} history sample(s), pruned {}", disks.len(), pruned),
+            Ok(None) if debug => debug!("Skipped history samples, the last ones are newer than history_interval"),
+            Ok(_) => {}
+            Err(e) => warn!("{}", e),
         }
     }
-   
-    if any_raid {
-        body.push_str("\nWARNING: RAID device(s) detected. Health information may be unavailable or unreliable. This tool should NOT be used for health monitoring tasks on RAID systems.\n");
+}
+
+/// Compare this run's alerts with the alert state, send the notifications that are due (report
+/// and resolved report) and save the state. Returns the number of reports sent and whether any
+/// step failed. `quiet` skips the console summary of unchanged alerts (used by the daemon).
+async fn process_alerts(cfg: &config::Config, disks: &[DiskInfo], system_info: &system::SystemInfo,
+                        alert_state: &mut state::State, state_path: &std::path::Path, debug: bool, quiet: bool) -> (usize, bool) {
+    let mut alerts_sent = 0;
+    let mut errors_occurred = false;
+
+    // Compare with the alerts notified on earlier runs
+    let renotify_interval = cfg.renotify_interval.unwrap_or(units::HumanDuration(24 * 3600));
+    let now = unix_now();
+    alert_state.update(disks, now);
+    let reconciliation = alert_state.reconcile(disks, now, renotify_interval.0);
+    if debug {
+        debug!("Alert state {}: {} to notify, {} suppressed, {} pending, {} resolved", state_path.display(),
+               reconciliation.notify.len(), reconciliation.suppressed, reconciliation.pending, reconciliation.resolved.len());
     }
+    let due = debug || !reconciliation.notify.is_empty();
 
-    for (i, disk) in disks.iter().enumerate() {
-        let total_gb = disk.total_space as f64 / (1024.0 * 1024.0 * 1024.0);
-        let available_gb = disk.available_space as f64 / (1024.0 * 1024.0 * 1024.0);
-        let used_gb = total_gb - available_gb;
-        
-        // Use HTML-colored status indicators so the issue stands out in the HTML email body.
-        // Note: subject coloring is not reliably supported across mail clients, so we use an emoji
-        // prefix in the subject and color the body instead.
-        let status_indicator = if disk.free_space_percent < threshold {
-            "<span style=\"color:red;font-weight:bold\">[LOW SPACE]</span>".to_string()
-        } else if disk.smart_status.as_deref().unwrap_or("OK").to_uppercase() != "OK" {
-            "<span style=\"color:darkorange;font-weight:bold\">[SMART FAILING]</span>".to_string()
-        } else if disk.reallocated_sectors.unwrap_or(0) > 0 || disk.pending_sectors.unwrap_or(0) > 0 || disk.uncorrectable_sectors.unwrap_or(0) > 0 || disk.temperature.unwrap_or(0) > 55 {
-            "<span style=\"color:orange;font-weight:bold\">[SMART WARNING]</span>".to_string()
-        } else {
-            "<span style=\"color:green;font-weight:bold\">[OK]</span>".to_string()
-        };
+    // Every disk with an alert (or every disk in debug mode, which always sends mail)
+    let problem_disks: Vec<&DiskInfo> = disks.iter()
+        .filter(|disk| !disk.alerts.is_empty() || debug)
+        .collect();
+    
+    if !problem_disks.is_empty() {
+        if !quiet || due {
+            println!("\n{} {} disk(s):", 
+                     "Alerts triggered for".red().bold(), 
+                     problem_disks.len().to_string().red().bold());
+            for disk in &problem_disks {
+                let mut reasons: Vec<String> = disk.alerts.iter()
+                    .map(|a| {
+                        let status = reconciliation.reason(&disk.mount_point, a.condition)
+                            .map_or("already notified", |r| r.as_str());
+                        format!("[{}] {} ({})", a.severity.label(), a.message, status)
+                    })
+                    .collect();
+                if debug {
+                    reasons.push("debug mode enabled".to_string());
+                }
 
-body.push_str(&format!(
-    "<b>Disk {}: {} {}</b>\n\
-     <b> - Mount Point:</b> {}\n\
-     <b> - File System:</b> {}\n\
-     <b> - Total Space:</b> {:.2} GB\n\
-     <b> - Used Space:</b> {:.2} GB\n\
-     <b> - Available Space:</b> {:.2} GB\n\
-     <b> - Free Space:</b> {:.2}%\n\
-     <b> - Health Check Method:</b> {}\n",
-    i + 1,
-    status_indicator,
-    &disk.display_name,
-    disk.mount_point,
-    disk.file_system,
-    total_gb,
-    used_gb,
-    available_gb,
-    disk.free_space_percent,
-    disk.health_method
-));
-
-        if let Some(val) = disk.power_on_hours {
-            body.push_str(&format!(" - Power On Hours: {}\n", val));
-        }
-        if let Some(val) = disk.reallocated_sectors {
-            body.push_str(&format!(" - Reallocated Sectors: {}\n", val));
-            if val > 0 {
-                body.push_str("   * WARNING: Reallocated sectors detected!\n");
-            }
-        }
-        if let Some(val) = disk.pending_sectors {
-            body.push_str(&format!(" - Pending Sectors: {}\n", val));
-            if val > 0 {
-                body.push_str("   * WARNING: Pending sectors detected!\n");
+                let line = reasons.join(", ");
+                match disk.severity() {
+                    Some(Severity::Critical) => println!("  {} {}: {}", "!!".red().bold(), disk.display_name.cyan(), line.red().bold()),
+                    Some(Severity::Warning) => println!("  {} {}: {}", "!".yellow().bold(), disk.display_name.cyan(), line.yellow().bold()),
+                    None => println!("  {} {}: {}", "*".dimmed(), disk.display_name.cyan(), line.dimmed()),
+                }
             }
         }
-        if let Some(val) = disk.uncorrectable_sectors {
-            body.push_str(&format!(" - Uncorrectable Sectors: {}\n", val));
-            if val > 0 {
-                body.push_str("   * WARNING: Uncorrectable sectors detected!\n");
+        
+        if !due {
+            if !quiet {
+                println!("{} {} alert(s) already notified within renotify_interval ({}).",
+                         "Notification suppressed:".yellow().bold(),
+                         reconciliation.suppressed,
+                         renotify_interval);
             }
-        }
-        if let Some(val) = disk.temperature {
-            body.push_str(&format!(" - Temperature: {} C\n", val));
-            if val > 55 {
-                body.push_str("   * WARNING: High temperature!\n");
+        } else {
+            // Send one comprehensive report with all problem disks
+            let report = notifiers::Report::disks(cfg, notifiers::Kind::Alert, system_info, disks, debug);
+            let outcome = notifiers::send(cfg, &report).await;
+            errors_occurred |= !outcome.failed.is_empty();
+            // Alerts count as notified once any channel got them out
+            if outcome.any_delivered() {
+                alert_state.mark_notified(disks, now);
+                alerts_sent += 1;
             }
         }
-
-        if let Some(serial) = &disk.serial_number {
-            body.push_str(&format!(" - Serial Number: {}\n", serial));
-        }
-        if let Some(brand) = &disk.brand {
-            body.push_str(&format!(" - Brand: {}\n", brand));
-        }
-        if let Some(model) = &disk.model {
-            body.push_str(&format!(" - Model: {}\n", model));
-        }
-        if disk.is_raid {
-            body.push_str(" - RAID: Yes (SMART status may not be accurate)\n");
-        }
-        if disk.is_raid {
-            body.push_str("   * WARNING: RAID device detected; health info may be unreliable.\n");
-        }
-        if system_info.is_virtualized {
-            body.push_str("   * WARNING: Running in virtualized environment; health info may be unreliable.\n");
+    } else if !quiet {
+        let any_unknown_smart = disks.iter().any(|d| d.smart.health.is_unknown());
+        if any_unknown_smart {
+            println!("\n{} (no warning or critical alerts, but health status is unknown for one or more disks).", 
+                     "All disks are above threshold".yellow().bold());
+        } else {
+            println!("\n{} (no warning or critical alerts and SMART status OK).", 
+                     "All disks are healthy".green().bold());
         }
-
-        body.push_str("\n");
     }
 
-    // Add closing HTML tags
-    body.push_str("</pre></body></html>");
-
-    // Build the email message; allow multiple recipients separated by commas in config.email_to
-    let mut builder = Message::builder()
-        .from(cfg.email_from.parse().map_err(|e| format!("Invalid sender email address: {e}"))?);
-
-    // Support comma-separated list of recipients in `cfg.email_to` (e.g. "a@x.com, b@y.com")
-    for addr in cfg.email_to.split(',') {
-        let addr = addr.trim();
-        if addr.is_empty() {
-            continue;
+    if !reconciliation.resolved.is_empty() {
+        println!("\n{} {} condition(s):",
+                 "Resolved".green().bold(),
+                 reconciliation.resolved.len().to_string().green().bold());
+        for record in &reconciliation.resolved {
+            println!("  {} {}: {} (was {})", "OK".green().bold(), record.display_name.cyan(),
+                     record.condition, record.severity.label());
+        }
+        if cfg.send_resolved.unwrap_or(true) {
+            let report = notifiers::Report::resolved(cfg, system_info, &reconciliation.resolved, debug);
+            let outcome = notifiers::send(cfg, &report).await;
+            errors_occurred |= !outcome.failed.is_empty();
+            if outcome.any_delivered() {
+                alerts_sent += 1;
+            } else {
+                alert_state.restore(reconciliation.resolved);
+            }
         }
-        builder = builder.to(addr.parse().map_err(|e| format!("Invalid recipient email address '{}': {}", addr, e))?);
     }
 
-    let email = builder
-        .subject(subject)
-        .header(lettre::message::header::ContentType::TEXT_HTML)
-        .body(body)
-        .map_err(|e| format!("Failed to build email message: {e}"))?;
-    
-    let use_auth = !(cfg.smtp_user.trim().is_empty() && cfg.smtp_pass.trim().is_empty());
-    let security = cfg.smtp_security.as_deref().unwrap_or("starttls").to_lowercase();
-    if debug {
-        println!("[DEBUG] smtp_security from config: {:?}", cfg.smtp_security);
+    // Keep the previous counters as baseline until the notification went out
+    if !errors_occurred {
+        alert_state.update_counters(disks, now);
     }
-    let mailer = match security.as_str() {
-        "none" => {
-            let mut builder = SmtpTransport::builder_dangerous(&cfg.smtp_server).port(cfg.smtp_port);
-            if use_auth {
-                builder = builder.credentials(Credentials::new(cfg.smtp_user.clone(), cfg.smtp_pass.clone()));
-            }
-            builder.build()
-        },
-        "ssl" => {
-            let tls = TlsParameters::new(cfg.smtp_server.clone())
-                .map_err(|e| format!("TLS parameter error: {e}"))?;
-            let mut builder = SmtpTransport::relay(&cfg.smtp_server)
-                .map_err(|e| format!("SMTP relay error: {e}"))?
-                .port(cfg.smtp_port)
-                .tls(Tls::Wrapper(tls));
-            if use_auth {
-                builder = builder.credentials(Credentials::new(cfg.smtp_user.clone(), cfg.smtp_pass.clone()));
-            }
-            builder.build()
-        },
-        _ => { // starttls (default)
-            let mut builder = SmtpTransport::relay(&cfg.smtp_server)
-                .map_err(|e| format!("SMTP relay error: {e}"))?
-                .port(cfg.smtp_port);
-            if use_auth {
-                builder = builder.credentials(Credentials::new(cfg.smtp_user.clone(), cfg.smtp_pass.clone()));
-            }
-            builder.build()
-        }
-    };
-    
-    // Send email with retry logic
-    let mut backoff = ExponentialBackoff::default();
-    backoff.max_elapsed_time = Some(Duration::from_secs(300)); // 5 minutes max
-    backoff.initial_interval = Duration::from_secs(1);
-    backoff.max_interval = Duration::from_secs(30);
-    
-    let mut attempt = 1;
-    let max_attempts = 3;
-    
-    loop {
-        match mailer.send(&email) {
-            Ok(_) => break,
-            Err(e) => {
-                error!("SMTP attempt {} failed: {}", attempt, e);
-                
-                if attempt >= max_attempts {
-                    return Err(format!("SMTP error after {} attempts: {}", max_attempts, e));
-                }
-                
-                if let Some(delay) = backoff.next_backoff() {
-                    warn!("Retrying SMTP in {:?}...", delay);
-                    tokio::time::sleep(delay).await;
-                    attempt += 1;
-                } else {
-                    return Err(format!("SMTP error: {}", e));
-                }
-            }
-        }
+    if let Err(e) = alert_state.save(state_path) {
+        warn!("{}", e);
     }
-    
-    println!("{} System report sent for {} disk(s){}", 
-             "SUCCESS".green().bold(), 
-             disks.len().to_string().cyan(),
-             if forced { " (forced)".yellow() } else if debug { " (debug)".yellow() } else { "".normal() });
-    Ok(())
+    (alerts_sent, errors_occurred)
 }
 
 #[tokio::main]
 async fn main() {
+    let cli = Cli::parse();
+
     // Load and validate configuration first to check debug setting
     let cfg = match config::load_config(config::CONFIG_PATH) {
         Ok(config) => config,
+        Err(e) if cli.output_format() == OutputFormat::Nagios => {
+            println!("{}", nagios::unknown(&format!("Configuration error: {}", e)));
+            std::process::exit(EXIT_ERROR);
+        }
         Err(e) => {
             eprintln!("{} {}", "Configuration error:".red().bold(), e);
-            std::process::exit(2);
+            std::process::exit(EXIT_ERROR);
         }
     };
 
-    // Get debug setting and initialize logging with appropriate level
+    // Get debug setting and initialize logging with appropriate level. The logger itself lets
+    // debug messages through so that the daemon can switch the level on reload.
     let debug = cfg.debug.unwrap_or(false);
-    let log_level = if debug {
-        log::LevelFilter::Debug
-    } else {
-        log::LevelFilter::Info
-    };
-    
     env_logger::Builder::from_default_env()
-        .filter_level(log_level)
+        .filter_level(log::LevelFilter::Debug)
         .init();
+    log::set_max_level(log_level(debug));
 
     if debug {
         debug!("Debug mode enabled");
@@ -794,16 +748,17 @@ async fn main() {
 
     // Initialize color support based on terminal capabilities
     init_colors();
-    
-    let cli = Cli::parse();
+
+    if let Some(Command::History(args)) = &cli.command {
+        if let Err(e) = history::run(&cfg, args, unix_now()) {
+            eprintln!("{} {}", "History error:".red().bold(), e);
+            std::process::exit(EXIT_ERROR);
+        }
+        std::process::exit(0);
+    }
 
     // Print smartmontools detection ONCE
-    let smartctl_available = if cfg!(windows) {
-        std::process::Command::new("smartctl").arg("--version").output().is_ok() ||
-        std::process::Command::new("C:\\Program Files\\smartmontools\\bin\\smartctl.exe").arg("--version").output().is_ok()
-    } else {
-        std::process::Command::new("smartctl").arg("--version").output().is_ok()
-    };
+    let smartctl_available = smartctl_available();
     if smartctl_available {
         info!("smartmontools detected - using smartctl for enhanced disk health monitoring");
     } else {
@@ -815,262 +770,264 @@ async fn main() {
     if debug {
         debug!("System info: {:#?}", system_info);
     }
-    println!("{} {} {} {} ({})", 
-             "System:".blue().bold(), 
-             system_info.os_name.green(), 
-             system_info.os_version.green(), 
-             system_info.architecture.green(),
-             system_info.hostname.cyan());
+    // Machine-readable formats keep stdout free of anything but their output
+    let console = cli.output_format() == OutputFormat::Text;
+    if console {
+        println!("{} {} {} {} ({})", 
+                 "System:".blue().bold(), 
+                 system_info.os_name.green(), 
+                 system_info.os_version.green(), 
+                 system_info.architecture.green(),
+                 system_info.hostname.cyan());
+    }
+
+    if let Some(Command::Daemon) = &cli.command {
+        std::process::exit(daemon::run(cfg, &system_info, cli.smart_timeout, cli.textfile_dir.as_deref(), debug).await);
+    }
 
     // Show loading message
-    println!("{}", "Loading information, please wait...".yellow().italic());
+    if console {
+        println!("{}", "Loading information, please wait...".yellow().italic());
+    }
     
+    // Alerts recorded on earlier runs; evaluation uses them for hysteresis and consecutive checks
+    let state_path = std::path::PathBuf::from(cfg.state_file.as_deref().unwrap_or(state::DEFAULT_STATE_PATH));
+    let mut alert_state = state::State::load(&state_path).unwrap_or_else(|e| {
+        warn!("{}; starting with an empty alert state", e);
+        state::State::default()
+    });
+
     // Get all monitored disks
-    let disks = get_monitored_disks(&cfg, debug, cli.smart_timeout).await;
+    let mut usage_history = open_history(&cfg);
+    let disks = get_monitored_disks(&cfg, debug, cli.smart_timeout, &alert_state, None, usage_history.as_ref()).await;
     
     if disks.is_empty() {
+        if cli.output_format() == OutputFormat::Nagios {
+            println!("{}", nagios::unknown("No monitored disks found"));
+            std::process::exit(EXIT_ERROR);
+        }
         eprintln!("{} This could indicate a system error or all disks are removable/network drives.", 
                   "No monitored disks found.".red().bold());
-        std::process::exit(1);
+        std::process::exit(EXIT_ERROR);
+    }
+
+    record_history(usage_history.as_mut(), &cfg, &disks, debug);
+
+    let mut errors_occurred = false;
+    if let Some(dir) = &cli.textfile_dir {
+        match metrics::write_textfile(dir, &disks, unix_now()) {
+            Ok(path) if debug => debug!("Wrote metrics to {}", path.display()),
+            Ok(_) => {}
+            Err(e) => {
+                error!("{}", e);
+                errors_occurred = true;
+            }
+        }
+    }
+
+    // Modes that don't notify still record this run's alerts, so that alert_after_checks and
+    // clear_margin hysteresis work for them too; the text mode does so in process_alerts
+    if cli.output_format() != OutputFormat::Text || cli.smart || cli.force_mail {
+        alert_state.update(&disks, unix_now());
+        // The plugin's status line is the notification for Icinga, so counter increases are
+        // reported once instead of on every check
+        if cli.output_format() == OutputFormat::Nagios {
+            alert_state.update_counters(&disks, unix_now());
+        }
+        if let Err(e) = alert_state.save(&state_path) {
+            warn!("{}", e);
+        }
+    }
+
+    // Worst severity across all disks; drives the email subject and the exit code
+    let overall_severity = disks.iter().filter_map(|d| d.severity()).max();
+
+    if cli.output_format() == OutputFormat::Json {
+        let output = JsonOutput::new(&cfg, &system_info, &disks, smartctl_available);
+        match serde_json::to_string_pretty(&output) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                error!("Failed to serialize JSON output: {}", e);
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+        std::process::exit(Severity::exit_code(overall_severity));
+    }
+
+    if cli.output_format() == OutputFormat::Nagios {
+        let (line, code) = nagios::report(&disks);
+        println!("{}", line);
+        std::process::exit(code);
+    }
+
+    if cli.output_format() == OutputFormat::Prometheus {
+        print!("{}", metrics::render(&disks, unix_now()));
+        std::process::exit(Severity::exit_code(overall_severity));
     }
 
     println!("{} {} disk(s):", "Monitoring".blue().bold(), disks.len().to_string().green());
     
     // Display disk information
     for disk in &disks {
-        let status_color = if disk.free_space_percent < 20.0 {
-            "red"
-        } else if disk.free_space_percent < 50.0 {
-            "yellow"
-        } else {
-            "green"
+        let space_severity = disk.alerts.iter()
+            .filter(|a| a.condition == Condition::LowSpace)
+            .map(|a| a.severity)
+            .max();
+        let colored_percent = match space_severity {
+            Some(Severity::Critical) => format!("{:.2}", disk.free_space_percent).red().bold(),
+            Some(Severity::Warning) => format!("{:.2}", disk.free_space_percent).yellow().bold(),
+            None => format!("{:.2}", disk.free_space_percent).green().bold(),
         };
         
-        let status_icon = if disk.free_space_percent < 20.0 {
-            "!"
-        } else if disk.free_space_percent < 50.0 {
-            "*"
-        } else {
-            "OK"
-        };
-        
-        let colored_percent = match status_color {
-            "red" => format!("{:.2}", disk.free_space_percent).red().bold(),
-            "yellow" => format!("{:.2}", disk.free_space_percent).yellow().bold(),
-            _ => format!("{:.2}", disk.free_space_percent).green().bold(),
+        let colored_icon = match disk.severity() {
+            Some(Severity::Critical) => "!!".red().bold(),
+            Some(Severity::Warning) => "!".yellow().bold(),
+            None => "OK".green().bold(),
         };
         
-        let colored_icon = match status_color {
-            "red" => status_icon.red().bold(),
-            "yellow" => status_icon.yellow().bold(),
-            _ => status_icon.green().bold(),
-        };
-        
-        let smart_status_output = if let Some(status) = &disk.smart_status {
-            if status.to_uppercase() == "OK" {
-                format!("(SMART: {})", "OK".green())
-            } else {
-                format!("(SMART: {})", status.red().bold())
-            }
-        } else {
-            "(SMART: N/A)".dimmed().to_string()
+        let smart_status_output = match disk.smart.health {
+            HealthStatus::Ok => format!("(SMART: {})", "OK".green()),
+            HealthStatus::Unknown => "(SMART: N/A)".dimmed().to_string(),
+            status => format!("(SMART: {})", status.as_str().red().bold()),
         };
 
-        let raid_output = if disk.is_raid {
+        let raid_output = if disk.smart.is_raid {
             " (RAID)".dimmed().to_string()
         } else {
             "".to_string()
         };
 
-        let method_output = match disk.health_method.as_str() {
-            "smartmontools" => "[smartmontools]".green().to_string(),
-            "WMI" => "[WMI]".green().to_string(),
-            "kernel" => "[kernel fallback]".yellow().to_string(),
-            "disabled" => "[health check disabled]".dimmed().to_string(),
+        let method_output = match disk.smart.method {
+            HealthMethod::Smartmontools => "[smartmontools]".green().to_string(),
+            HealthMethod::Wmi => "[WMI]".green().to_string(),
+            HealthMethod::Kernel => "[kernel fallback]".yellow().to_string(),
+            HealthMethod::Disabled => "[health check disabled]".dimmed().to_string(),
             _ => "[unknown method]".red().to_string(),
         };
 
-        println!("  {} {}: {}% free ({:.2} GB available, {} filesystem) {}{} {}", 
+        let inode_output = match disk.inode_free_percent {
+            Some(percent) if disk.has_alert(Condition::LowInodes) => format!(", {} inodes free", format!("{:.2}%", percent).red().bold()),
+            Some(percent) => format!(", {:.2}% inodes free", percent),
+            None => String::new(),
+        };
+
+        let threshold_output = match disk.forecast.and_then(|f| f.threshold_in) {
+            Some(secs) => format!(", threshold in ~{}", units::format_elapsed(secs)),
+            None => String::new(),
+        };
+        let forecast_output = match disk.forecast.and_then(|f| f.full_in) {
+            Some(secs) if disk.has_alert(Condition::FullSoon) => format!("{}, {}", threshold_output, format!("full in ~{}", units::format_elapsed(secs)).red().bold()),
+            Some(secs) => format!("{}, full in ~{}", threshold_output, units::format_elapsed(secs)),
+            None => threshold_output,
+        };
+
+        println!("  {} {}: {}% free ({:.2} GB available{}{}, {} filesystem) {}{} {}", 
                  colored_icon,
                  disk.display_name.cyan(), 
                  colored_percent,
                  disk.available_space as f64 / (1024.0 * 1024.0 * 1024.0),
+                 inode_output,
+                 forecast_output,
                  disk.file_system.magenta(),
                  smart_status_output,
                  raid_output,
                  method_output);
-        if disk.health_method != "smartmontools" && disk.health_method != "WMI" {
+        if !disk.smart.method.is_authoritative() {
             println!("    {}", "WARNING: Health info from fallback method; may be incomplete or unreliable.".yellow());
         }
-        if disk.is_raid {
+        if disk.smart.is_raid {
             println!("    {}", "WARNING: RAID device detected; health info may be unreliable.".yellow());
         }
         if system_info.is_virtualized {
             println!("    {}", "WARNING: Running in virtualized environment; health info may be unreliable.".yellow());
         }
-    }
-
-    // Add warnings for RAID and missing health info
-    let mut no_health_info = false;
-    let mut any_raid = false;
-    for disk in &disks {
-        if disk.smart_status.is_none() || disk.smart_status.as_deref() == Some("N/A") {
-            no_health_info = true;
+        for alert in &disk.alerts {
+            let line = format!("{}: {}", alert.severity.label(), alert.message);
+            match alert.severity {
+                Severity::Critical => println!("    {}", line.red().bold()),
+                Severity::Warning => println!("    {}", line.yellow()),
+            }
         }
-        if disk.is_raid {
-            any_raid = true;
+        for alert in &disk.pending_alerts {
+            println!("    {}", format!("PENDING ({}/{} checks) {}: {}", alert.checks, cfg.alert_after_checks.unwrap_or(1),
+                                        alert.severity.label(), alert.message).dimmed());
         }
     }
+
+    // Add warnings for RAID devices
+    let any_raid = disks.iter().any(|d| d.smart.is_raid);
     if any_raid {
         println!("{}", "WARNING: RAID device(s) detected. Health information may be unavailable or unreliable. This tool should NOT be used for health monitoring tasks on RAID systems.".red().bold());
     }
 
-    if cli.json {
-        // JSON output mode
-        #[derive(serde::Serialize)]
-        struct JsonOutput {
-            system_info: system::SystemInfo,
-            disks: Vec<DiskInfo>,
-            threshold_percent: f64,
-            smartctl_available: bool,
-            alerts: Vec<String>,
-        }
-        
-        let threshold = cfg.threshold_percent.unwrap_or(10.0);
-        let mut alerts = Vec::new();
-        
-        for disk in &disks {
-            if disk.free_space_percent < threshold {
-                alerts.push(format!("{}: Low space ({:.2}%)", disk.display_name, disk.free_space_percent));
-            }
-            if disk.smart_status.as_deref().unwrap_or("OK").to_uppercase() != "OK" {
-                alerts.push(format!("{}: SMART failure ({})", disk.display_name, disk.smart_status.as_deref().unwrap_or("N/A")));
-            }
-        }
-        
-        let output = JsonOutput {
-            system_info,
-            disks,
-            threshold_percent: threshold,
-            smartctl_available,
-            alerts,
-        };
-        
-        match serde_json::to_string_pretty(&output) {
-            Ok(json) => println!("{}", json),
-            Err(e) => {
-                error!("Failed to serialize JSON output: {}", e);
-                std::process::exit(1);
-            }
-        }
-        return;
-    }
-
     if cli.smart {
         println!("\n{}", "SMART Status Details:".blue().bold());
         for disk in &disks {
-            let status = disk.smart_status.as_deref().unwrap_or("N/A");
-            let color = if status.to_uppercase() == "OK" { "green" } else { "red" };
-            let colored_status = match color {
-                "green" => status.green().bold(),
-                _ => status.red().bold(),
+            let colored_status = match disk.smart.health {
+                HealthStatus::Ok => "OK".green().bold(),
+                HealthStatus::Unknown => "N/A".red().bold(),
+                status => status.as_str().red().bold(),
             };
             println!("  {}: {}", disk.display_name.cyan(), colored_status);
-            println!("    Serial: {}", disk.serial_number.as_deref().unwrap_or("N/A").dimmed());
-            println!("    Brand: {}", disk.brand.as_deref().unwrap_or("N/A").dimmed());
-            println!("    Model: {}", disk.model.as_deref().unwrap_or("N/A").dimmed());
-            if disk.is_raid {
+            println!("    Serial: {}", disk.smart.serial_number.as_deref().unwrap_or("N/A").dimmed());
+            println!("    Brand: {}", disk.smart.brand.as_deref().unwrap_or("N/A").dimmed());
+            println!("    Model: {}", disk.smart.model.as_deref().unwrap_or("N/A").dimmed());
+            if disk.smart.is_raid {
                 println!("    {}", "(RAID)".dimmed());
             }
-            if disk.reallocated_sectors.unwrap_or(0) > 0 {
-                println!("    {}", "WARNING: Reallocated sectors detected!".red().bold());
+            if let Some(nvme) = &disk.smart.nvme {
+                println!("    NVMe: {}% used, spare {}% (threshold {}%), {} media errors, {} unsafe shutdowns",
+                         nvme.percentage_used, nvme.available_spare, nvme.available_spare_threshold,
+                         nvme.media_errors, nvme.unsafe_shutdowns);
             }
-            if disk.pending_sectors.unwrap_or(0) > 0 {
-                println!("    {}", "WARNING: Pending sectors detected!".red().bold());
+            let counters: Vec<String> = Counter::ALL.iter()
+                .filter_map(|&c| disk.counter_text(c).map(|text| format!("{} {}", c.label(), text)))
+                .collect();
+            if !counters.is_empty() {
+                println!("    Error counters: {}", counters.join(", "));
             }
-            if disk.uncorrectable_sectors.unwrap_or(0) > 0 {
-                println!("    {}", "WARNING: Uncorrectable sectors detected!".red().bold());
+            for alert in disk.alerts.iter().filter(|a| a.condition.is_smart()) {
+                let line = format!("{}: {}!", alert.severity.label(), alert.message);
+                match alert.severity {
+                    Severity::Critical => println!("    {}", line.red().bold()),
+                    Severity::Warning => println!("    {}", line.yellow().bold()),
+                }
             }
-            if disk.temperature.unwrap_or(0) > 55 {
-                println!("    {}", "WARNING: High temperature!".red().bold());
+            if !disk.smart.smart_attributes.is_empty() {
+                println!("    {:>3} {:<24} {:<8} {:>5} {:>5} {:>6} {:<11} RAW_VALUE",
+                         "ID#", "ATTRIBUTE_NAME", "FLAGS", "VALUE", "WORST", "THRESH", "WHEN_FAILED");
+                for attr in &disk.smart.smart_attributes {
+                    let row = format!("{:>3} {:<24} {:<8} {:>5} {:>5} {:>6} {:<11} {}",
+                                      attr.id, attr.name, attr.flags, attr.value, attr.worst, attr.thresh,
+                                      if attr.when_failed.is_empty() { "-" } else { &attr.when_failed },
+                                      attr.raw_string);
+                    if attr.failing {
+                        println!("    {}", row.red().bold());
+                    } else {
+                        println!("    {}", row.dimmed());
+                    }
+                }
             }
         }
-        return;
+        std::process::exit(Severity::exit_code(overall_severity));
     }
 
     // Handle email alerts
-    let threshold = cfg.threshold_percent.unwrap_or(10.0); // Default to 10% if not specified
     let mut alerts_sent = 0;
-    let mut errors_occurred = false;
     
     if cli.force_mail {
         // Force send comprehensive system report for all disks
         println!("\n{}", "Forced mail mode: Sending comprehensive system report...".yellow().bold());
-        if let Err(e) = send_system_report(&cfg, &disks, &system_info, true, debug).await {
-            eprintln!("{} {}", "ERROR Failed to send system report:".red().bold(), e);
-            errors_occurred = true;
-        } else {
+        let report = notifiers::Report::disks(&cfg, notifiers::Kind::Forced, &system_info, &disks, debug);
+        let outcome = notifiers::send(&cfg, &report).await;
+        errors_occurred |= !outcome.failed.is_empty();
+        if outcome.any_delivered() {
             alerts_sent = 1;
         }
     } else {
-        // Check each disk against threshold and SMART status
-        let mut problem_disks = Vec::new();
-        
-        for disk in &disks {
-            let is_low_space = disk.free_space_percent < threshold;
-            let is_smart_fail = disk.smart_status.as_deref().unwrap_or("OK").to_uppercase() != "OK";
-            let send_on_unknown = cfg.send_mail_on_unknown_status.unwrap_or(false) && disk.smart_status.is_none();
-            let debug_mode = debug; // Always send mail when debug is enabled
-            let smart_enabled = cfg.smart_enabled.unwrap_or(true);
-
-            if is_low_space || (smart_enabled && (is_smart_fail || send_on_unknown)) || debug_mode {
-                problem_disks.push(disk);
-            }
-        }
-        
-        if !problem_disks.is_empty() {
-            println!("\n{} {} disk(s):", 
-                     "Alerts triggered for".red().bold(), 
-                     problem_disks.len().to_string().red().bold());
-            for disk in &problem_disks {
-                let mut reasons = Vec::new();
-                if disk.free_space_percent < threshold {
-                    reasons.push(format!("low space ({:.2}%)", disk.free_space_percent));
-                }
-                if disk.smart_status.as_deref().unwrap_or("OK").to_uppercase() != "OK" {
-                    reasons.push(format!("SMART status: {}", disk.smart_status.as_deref().unwrap_or("N/A")));
-                } else if disk.smart_status.is_none() && cfg.send_mail_on_unknown_status.unwrap_or(false) {
-                    reasons.push("SMART status: Unknown".to_string());
-                }
-                if debug {
-                    reasons.push("debug mode enabled".to_string());
-                }
-
-                println!("  {} {}: {}", 
-                         "!".red().bold(),
-                         disk.display_name.cyan(), 
-                         reasons.join(", ").red().bold());
-            }
-            
-            // Send one comprehensive report with all problem disks
-            if let Err(e) = send_system_report(&cfg, &disks, &system_info, false, debug).await {
-                eprintln!("{} {}", "ERROR Failed to send system report:".red().bold(), e);
-                errors_occurred = true;
-            } else {
-                alerts_sent = 1;
-            }
-        } else {
-            let any_unknown_smart = disks.iter().any(|d| d.smart_status.is_none());
-            if any_unknown_smart {
-                println!("\n{} (above {:.1}% threshold, but health status is unknown for one or more disks).", 
-                         "All disks are above threshold".yellow().bold(), 
-                         threshold);
-        } else {
-            println!("\n{} (above {:.1}% threshold and SMART status OK).", 
-                     "All disks are healthy".green().bold(), 
-                     threshold);
-            }
-        }
+        let (sent, errors) = process_alerts(&cfg, &disks, &system_info, &mut alert_state, &state_path, debug, false).await;
+        alerts_sent += sent;
+        errors_occurred |= errors;
     }
 
     // Summary
@@ -1082,6 +1039,7 @@ async fn main() {
     
     if errors_occurred {
         eprintln!("{}", "Some errors occurred during alert processing.".red().bold());
-        std::process::exit(2);
+        std::process::exit(EXIT_ERROR);
     }
+    std::process::exit(Severity::exit_code(overall_severity));
 }
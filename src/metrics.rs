@@ -0,0 +1,90 @@
+// Prometheus metrics.
+//
+// Renders the collected disks in the Prometheus text exposition format, either to stdout
+// (`--format prometheus`) or as diskmon.prom in a node_exporter textfile collector directory
+// (`--textfile-dir`). Every per-filesystem series is labelled with mount_point, device, serial and
+// model; serial and model are empty when SMART data doesn't provide them. Values that aren't
+// known (temperature, power-on hours, SMART counters) are left out rather than reported as 0.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::DiskInfo;
+use crate::smart::{Counter, HealthStatus};
+
+pub const TEXTFILE_NAME: &str = "diskmon.prom";
+
+// Label values may contain any character; backslash, double quote and newline must be escaped
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn labels(disk: &DiskInfo) -> String {
+    format!("mount_point=\"{}\",device=\"{}\",serial=\"{}\",model=\"{}\"",
+            escape(&disk.mount_point),
+            escape(&disk.device),
+            escape(disk.smart.serial_number.as_deref().unwrap_or("")),
+            escape(disk.smart.model.as_deref().unwrap_or("")))
+}
+
+// One metric family: HELP and TYPE lines followed by its samples. Families without samples are
+// left out entirely.
+fn gauge(out: &mut String, name: &str, help: &str, samples: impl IntoIterator<Item = (String, f64)>) {
+    let mut samples = samples.into_iter().peekable();
+    if samples.peek().is_none() {
+        return;
+    }
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    for (labels, value) in samples {
+        if labels.is_empty() {
+            let _ = writeln!(out, "{} {}", name, value);
+        } else {
+            let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+        }
+    }
+}
+
+/// All metrics for this run's disks; `now` is the run's Unix timestamp.
+pub fn render(disks: &[DiskInfo], now: i64) -> String {
+    let mut out = String::new();
+    gauge(&mut out, "diskmon_filesystem_free_bytes", "Space available to unprivileged users in bytes.",
+          disks.iter().map(|d| (labels(d), d.available_space as f64)));
+    gauge(&mut out, "diskmon_filesystem_size_bytes", "Filesystem size in bytes.",
+          disks.iter().map(|d| (labels(d), d.total_space as f64)));
+    gauge(&mut out, "diskmon_filesystem_free_percent", "Available space as a percentage of the filesystem size.",
+          disks.iter().map(|d| (labels(d), d.free_space_percent)));
+    // Filesystems without inode accounting (btrfs, ZFS) report 0 inodes and are left out
+    gauge(&mut out, "diskmon_filesystem_inodes", "Total inodes.",
+          disks.iter().filter(|d| d.total_inodes > 0).map(|d| (labels(d), d.total_inodes as f64)));
+    gauge(&mut out, "diskmon_filesystem_inodes_free", "Free inodes.",
+          disks.iter().filter(|d| d.total_inodes > 0).map(|d| (labels(d), d.free_inodes as f64)));
+    gauge(&mut out, "diskmon_smart_health", "SMART health status of the disk behind the filesystem; 1 for the current state.",
+          disks.iter().flat_map(|d| HealthStatus::ALL.iter().map(move |&status| (
+              format!("{},state=\"{}\"", labels(d), status.as_str().to_lowercase()),
+              if d.smart.health == status { 1.0 } else { 0.0 },
+          ))));
+    gauge(&mut out, "diskmon_disk_temperature_celsius", "Disk temperature in degrees Celsius.",
+          disks.iter().filter_map(|d| Some((labels(d), d.smart.temperature? as f64))));
+    gauge(&mut out, "diskmon_disk_power_on_hours", "Disk power-on hours.",
+          disks.iter().filter_map(|d| Some((labels(d), d.smart.power_on_hours? as f64))));
+    for counter in Counter::ALL {
+        gauge(&mut out, &format!("diskmon_smart_{}", counter.as_str()), &format!("SMART {}.", counter.label()),
+              disks.iter().filter_map(|d| Some((labels(d), d.smart.counter(counter)? as f64))));
+    }
+    gauge(&mut out, "diskmon_last_run_timestamp_seconds", "Unix time of the last diskmon run.",
+          [(String::new(), now as f64)]);
+    out
+}
+
+/// Write the metrics to diskmon.prom in `dir` atomically, so the collector never reads a partial
+/// file. Returns the file written.
+pub fn write_textfile(dir: &Path, disks: &[DiskInfo], now: i64) -> Result<PathBuf, String> {
+    let path = dir.join(TEXTFILE_NAME);
+    // node_exporter only reads *.prom files, so the temporary file is ignored until the rename
+    let tmp = dir.join(format!("{}.tmp", TEXTFILE_NAME));
+    fs::write(&tmp, render(disks, now)).map_err(|e| format!("Failed to write {}: {e}", tmp.display()))?;
+    fs::rename(&tmp, &path).map_err(|e| format!("Failed to replace {}: {e}", path.display()))?;
+    Ok(path)
+}
@@ -0,0 +1,138 @@
+// Nagios/Icinga plugin output.
+//
+// `--nagios` prints the single status line a plugin runner expects, e.g.
+//   DISK WARNING - /var: low space (8.20% / 4.07 GiB free, ...) | '/var'=45097156608B;44667659878;;0;49630733312 ...
+// and exits 0 (OK), 1 (WARNING), 2 (CRITICAL) or 3 (UNKNOWN). Perfdata gives the used bytes of every
+// filesystem with the used-space levels at which its warning and critical tiers fire.
+
+use crate::DiskInfo;
+use crate::alerts::Severity;
+
+fn state(severity: Option<Severity>) -> &'static str {
+    match severity {
+        Some(Severity::Critical) => "CRITICAL",
+        Some(Severity::Warning) => "WARNING",
+        None => "OK",
+    }
+}
+
+// Labels are quoted; a single quote inside a label is written twice
+fn perfdata(disk: &DiskInfo) -> String {
+    let used = disk.total_space.saturating_sub(disk.available_space);
+    let level = |severity| {
+        disk.thresholds.limit_bytes(severity, disk.total_space)
+            .map(|limit| disk.total_space.saturating_sub(limit).to_string())
+            .unwrap_or_default()
+    };
+    format!("'{}'={}B;{};{};0;{}",
+            disk.mount_point.replace('\'', "''"),
+            used,
+            level(Severity::Warning),
+            level(Severity::Critical),
+            disk.total_space)
+}
+
+/// Status line with perfdata and the plugin exit code for this run's disks.
+pub fn report(disks: &[DiskInfo]) -> (String, i32) {
+    let severity = disks.iter().filter_map(|d| d.severity()).max();
+    let summary = if severity.is_some() {
+        disks.iter()
+            .filter(|d| !d.alerts.is_empty())
+            .map(|d| {
+                let messages: Vec<&str> = d.alerts.iter().map(|a| a.message.as_str()).collect();
+                format!("{}: {}", d.mount_point, messages.join(", "))
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    } else {
+        let lowest = disks.iter().min_by(|a, b| a.free_space_percent.total_cmp(&b.free_space_percent));
+        match lowest {
+            Some(disk) => format!("{} filesystem(s) checked, lowest free {:.2}% on {}",
+                                  disks.len(), disk.free_space_percent, disk.mount_point),
+            None => "no filesystems checked".to_string(),
+        }
+    };
+    let perfdata: Vec<String> = disks.iter().map(perfdata).collect();
+    let line = format!("DISK {} - {} | {}", state(severity), summary.replace('|', "/"), perfdata.join(" "));
+    (line, Severity::exit_code(severity))
+}
+
+/// Status line for a run that couldn't check anything.
+pub fn unknown(reason: &str) -> String {
+    format!("DISK UNKNOWN - {}", reason.replace('|', "/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::Condition;
+    use crate::testing;
+
+    fn disk(mount_point: &str, available: u64) -> DiskInfo {
+        testing::disk(mount_point, 1_000, available, testing::thresholds("critical_threshold_percent: 5"))
+    }
+
+    fn alerting(mount_point: &str, available: u64, severity: Severity, message: &str) -> DiskInfo {
+        let mut disk = disk(mount_point, available);
+        disk.alerts.push(testing::alert(Condition::LowSpace, severity, message));
+        disk
+    }
+
+    #[test]
+    fn ok_line_names_lowest_free_space() {
+        let (line, code) = report(&[disk("/", 400), disk("/home", 250)]);
+        assert_eq!(line, "DISK OK - 2 filesystem(s) checked, lowest free 25.00% on /home | '/'=600B;900;950;0;1000 '/home'=750B;900;950;0;1000");
+        assert_eq!(code, 0);
+    }
+
+    #[test]
+    fn status_and_exit_code_follow_worst_alert() {
+        let disks = [
+            alerting("/var", 80, Severity::Warning, "low space"),
+            disk("/", 400),
+        ];
+        let (line, code) = report(&disks);
+        assert!(line.starts_with("DISK WARNING - /var: low space | "), "{}", line);
+        assert_eq!(code, 1);
+
+        let mut disks = disks.to_vec();
+        disks.push(alerting("/srv", 40, Severity::Critical, "low space"));
+        disks[2].alerts.push(testing::alert(Condition::Temperature, Severity::Warning, "temperature 58 C"));
+        let (line, code) = report(&disks);
+        assert!(line.starts_with("DISK CRITICAL - /var: low space; /srv: low space, temperature 58 C | "), "{}", line);
+        assert_eq!(code, 2);
+    }
+
+    #[test]
+    fn pipes_in_messages_do_not_start_perfdata() {
+        let (line, _) = report(&[alerting("/", 80, Severity::Warning, "rule 'a|b'")]);
+        assert_eq!(line.matches('|').count(), 1, "{}", line);
+        assert!(line.starts_with("DISK WARNING - /: rule 'a/b' | "));
+        assert_eq!(unknown("no disks | none"), "DISK UNKNOWN - no disks / none");
+    }
+
+    #[test]
+    fn perfdata_quotes_labels() {
+        assert_eq!(perfdata(&disk("/mnt/bob's disk", 400)), "'/mnt/bob''s disk'=600B;900;950;0;1000");
+    }
+
+    #[test]
+    fn perfdata_levels() {
+        // Without a critical tier its level stays empty
+        let mut only_warning = disk("/", 400);
+        only_warning.thresholds = testing::thresholds("threshold_percent: 20");
+        assert_eq!(perfdata(&only_warning), "'/'=600B;800;;0;1000");
+
+        // Either fires at the larger of percent and size, both at the smaller
+        let mut either = disk("/", 400);
+        either.thresholds = testing::thresholds("threshold_percent: 10\nmin_free: 300");
+        assert_eq!(perfdata(&either), "'/'=600B;700;;0;1000");
+        either.thresholds = testing::thresholds("threshold_percent: 10\nmin_free: 300\nthreshold_require: both");
+        assert_eq!(perfdata(&either), "'/'=600B;900;;0;1000");
+    }
+
+    #[test]
+    fn no_disks() {
+        assert_eq!(report(&[]), ("DISK OK - no filesystems checked | ".to_string(), 0));
+    }
+}
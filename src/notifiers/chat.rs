@@ -0,0 +1,373 @@
+// Chat notifiers: Slack, Mattermost, Microsoft Teams and Discord incoming webhooks.
+//
+// All four post the same compact summary: a title with the worst severity, one status line per
+// disk (or per cleared condition for resolved reports) and a footer with the system and time,
+// colour-coded red, orange or green. Only the payload differs:
+//   slack       attachment with a colour bar around Block Kit blocks
+//   mattermost  Slack-compatible attachment with markdown text (Mattermost has no blocks)
+//   teams       Adaptive Card for Workflows webhooks, or a legacy MessageCard (`card: messagecard`)
+//   discord     embed
+// Long reports are cut to what the platform accepts, ending with "... and N more".
+
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use serde_json::{Value, json};
+
+use super::webhook::{DEFAULT_TIMEOUT, post_json, valid_url};
+use super::{Kind, Notifier, Report};
+use crate::alerts::Severity;
+use crate::units::{ByteSize, HumanDuration};
+use crate::{format_local_time, units};
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ChatConfig {
+    pub url: String, // Incoming webhook URL
+    pub username: Option<String>, // Sender name shown in the channel, where the webhook allows it
+    pub timeout: Option<HumanDuration>, // Connect and response timeout per attempt (default: 10s)
+}
+
+impl ChatConfig {
+    pub fn validate(&self, name: &str) -> Vec<String> {
+        validate_common(name, &self.url, self.timeout)
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TeamsCard {
+    Adaptive, // Workflows ("Post to a channel when a webhook request is received")
+    MessageCard, // Legacy Office 365 connector webhooks
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct TeamsConfig {
+    pub url: String, // Incoming webhook URL
+    pub card: Option<TeamsCard>, // "adaptive" or "messagecard" (default: adaptive)
+    pub timeout: Option<HumanDuration>, // Connect and response timeout per attempt (default: 10s)
+}
+
+impl TeamsConfig {
+    pub fn validate(&self, name: &str) -> Vec<String> {
+        validate_common(name, &self.url, self.timeout)
+    }
+}
+
+fn validate_common(name: &str, url: &str, timeout: Option<HumanDuration>) -> Vec<String> {
+    let mut errors = Vec::new();
+    if !valid_url(url) {
+        errors.push(format!("{}.url (must be an http:// or https:// URL)", name));
+    }
+    if timeout.is_some_and(|t| t.0 == 0) {
+        errors.push(format!("{}.timeout (must be at least 1s)", name));
+    }
+    errors
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Slack,
+    Mattermost,
+    TeamsAdaptive,
+    TeamsMessageCard,
+    Discord,
+}
+
+pub struct ChatNotifier {
+    style: Style,
+    url: String,
+    username: Option<String>,
+    timeout: Duration,
+}
+
+impl ChatNotifier {
+    pub fn new(style: Style, settings: &ChatConfig) -> Self {
+        ChatNotifier {
+            style,
+            url: settings.url.clone(),
+            username: settings.username.clone(),
+            timeout: settings.timeout.map_or(DEFAULT_TIMEOUT, |t| t.as_duration()),
+        }
+    }
+
+    pub fn teams(settings: &TeamsConfig) -> Self {
+        ChatNotifier {
+            style: match settings.card.unwrap_or(TeamsCard::Adaptive) {
+                TeamsCard::Adaptive => Style::TeamsAdaptive,
+                TeamsCard::MessageCard => Style::TeamsMessageCard,
+            },
+            url: settings.url.clone(),
+            username: None,
+            timeout: settings.timeout.map_or(DEFAULT_TIMEOUT, |t| t.as_duration()),
+        }
+    }
+}
+
+impl Notifier for ChatNotifier {
+    fn send<'a>(&'a self, report: &'a Report<'a>) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let summary = Summary::new(report);
+            let payload = match self.style {
+                Style::Slack => slack(&summary, self.username.as_deref()),
+                Style::Mattermost => mattermost(&summary, self.username.as_deref()),
+                Style::TeamsAdaptive => teams_adaptive(&summary),
+                Style::TeamsMessageCard => teams_message_card(&summary),
+                Style::Discord => discord(&summary, self.username.as_deref()),
+            };
+            post_json(&self.url, Vec::new(), payload.to_string(), self.timeout).await
+        })
+    }
+}
+
+// Overall state of a message; resolved reports are shown as OK
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Critical,
+    Warning,
+    Ok,
+}
+
+impl Status {
+    fn from_severity(severity: Option<Severity>) -> Status {
+        match severity {
+            Some(Severity::Critical) => Status::Critical,
+            Some(Severity::Warning) => Status::Warning,
+            None => Status::Ok,
+        }
+    }
+
+    fn emoji(&self) -> &'static str {
+        match self {
+            Status::Critical => "🔴",
+            Status::Warning => "🟠",
+            Status::Ok => "🟢",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Status::Critical => "CRITICAL",
+            Status::Warning => "WARNING",
+            Status::Ok => "OK",
+        }
+    }
+
+    // Same colours as the email report
+    fn hex(&self) -> &'static str {
+        match self {
+            Status::Critical => "#D32F2F",
+            Status::Warning => "#F57C00",
+            Status::Ok => "#2E7D32",
+        }
+    }
+
+    fn adaptive_color(&self) -> &'static str {
+        match self {
+            Status::Critical => "Attention",
+            Status::Warning => "Warning",
+            Status::Ok => "Good",
+        }
+    }
+}
+
+// One status line: the mount point (or resolved condition) and what to say about it
+struct Line {
+    status: Status,
+    subject: String,
+    detail: String,
+}
+
+/// The platform-neutral content of a chat message.
+struct Summary {
+    status: Status,
+    title: String,
+    lines: Vec<Line>,
+    footer: String,
+    time: i64,
+}
+
+impl Summary {
+    fn new(report: &Report) -> Summary {
+        let system = &report.system_info;
+        let footer = format!("{} {} {} · {}", system.os_name, system.os_version, system.architecture,
+                             format_local_time(report.time));
+
+        if report.kind == Kind::Resolved {
+            let lines = report.resolved.iter().map(|record| Line {
+                status: Status::Ok,
+                subject: record.display_name.clone(),
+                detail: format!("{} cleared (was {}, active {})", record.condition, record.severity.label(),
+                                units::format_elapsed(report.time.saturating_sub(record.first_seen) as u64)),
+            }).collect();
+            return Summary {
+                status: Status::Ok,
+                title: format!("RESOLVED - {}: {} condition(s) cleared", report.display_name, report.resolved.len()),
+                lines,
+                footer,
+                time: report.time,
+            };
+        }
+
+        let status = Status::from_severity(report.severity());
+        let affected = report.disks.iter().filter(|d| !d.alerts.is_empty()).count();
+        let title = match (report.kind, status) {
+            (Kind::Forced, _) => format!("{} - {}: disk report, {} of {} disk(s) with alerts",
+                                         status.label(), report.display_name, affected, report.disks.len()),
+            (_, Status::Ok) => format!("OK - {}: all {} disk(s) healthy", report.display_name, report.disks.len()),
+            _ => format!("{} - {}: {} disk(s) need attention", status.label(), report.display_name, affected),
+        };
+        let lines = report.disks.iter().map(|disk| {
+            let mut detail = format!("{:.2}% free ({} of {})", disk.free_space_percent,
+                                     ByteSize(disk.available_space), ByteSize(disk.total_space));
+            if !disk.alerts.is_empty() {
+                let messages: Vec<&str> = disk.alerts.iter().map(|a| a.message.as_str()).collect();
+                detail.push_str(" - ");
+                detail.push_str(&messages.join("; "));
+            }
+            Line { status: Status::from_severity(disk.severity()), subject: disk.display_name.clone(), detail }
+        }).collect();
+        Summary { status, title, lines, footer, time: report.time }
+    }
+
+    /// Status lines rendered with `line`, joined by newlines and cut to `limit` characters.
+    fn text(&self, limit: usize, line: impl Fn(&Line) -> String) -> String {
+        let mut text = String::new();
+        for (i, l) in self.lines.iter().enumerate() {
+            let next = line(l);
+            let more = format!("… and {} more", self.lines.len() - i);
+            // Leave room for the "and N more" line unless this is the last one
+            let reserve = if i + 1 < self.lines.len() { more.chars().count() + 1 } else { 0 };
+            if text.chars().count() + next.chars().count() + 1 + reserve > limit {
+                text.push_str(&more);
+                return text;
+            }
+            text.push_str(&next);
+            text.push('\n');
+        }
+        text.trim_end().to_string()
+    }
+}
+
+fn truncate(text: &str, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        text.to_string()
+    } else {
+        let mut cut: String = text.chars().take(limit.saturating_sub(1)).collect();
+        cut.push('…');
+        cut
+    }
+}
+
+// Slack mrkdwn treats &, < and > as control characters
+fn slack_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// Mount points go in code spans so underscores and asterisks in paths aren't read as markdown
+fn code(text: &str) -> String {
+    format!("`{}`", text.replace('`', "'"))
+}
+
+fn with_username(mut payload: Value, key: &str, username: Option<&str>) -> Value {
+    if let Some(username) = username {
+        payload[key] = json!(username);
+    }
+    payload
+}
+
+fn slack(summary: &Summary, username: Option<&str>) -> Value {
+    // Section text is limited to 3000 characters
+    let lines = summary.text(3000, |l| format!("{} {} {}", l.status.emoji(), code(&l.subject), slack_escape(&l.detail)));
+    let payload = json!({
+        // The top-level text is the notification and fallback text and is parsed as mrkdwn too
+        "text": format!("{} {}", summary.status.emoji(), slack_escape(&summary.title)),
+        "attachments": [{
+            "color": summary.status.hex(),
+            "blocks": [
+                { "type": "section", "text": { "type": "mrkdwn", "text": format!("*{}*", truncate(&slack_escape(&summary.title), 2990)) } },
+                { "type": "section", "text": { "type": "mrkdwn", "text": if lines.is_empty() { "-".to_string() } else { lines } } },
+                { "type": "context", "elements": [{ "type": "mrkdwn", "text": slack_escape(&summary.footer) }] },
+            ],
+        }],
+    });
+    with_username(payload, "username", username)
+}
+
+fn mattermost(summary: &Summary, username: Option<&str>) -> Value {
+    let lines = summary.text(7000, |l| format!("{} {} {}", l.status.emoji(), code(&l.subject), l.detail));
+    let payload = json!({
+        "attachments": [{
+            "fallback": format!("{} {}", summary.status.emoji(), summary.title),
+            "color": summary.status.hex(),
+            "title": summary.title,
+            "text": lines,
+            "footer": summary.footer,
+        }],
+    });
+    with_username(payload, "username", username)
+}
+
+fn teams_adaptive(summary: &Summary) -> Value {
+    let facts: Vec<Value> = summary.lines.iter().take(50)
+        .map(|l| json!({ "title": format!("{} {}", l.status.emoji(), l.subject), "value": l.detail }))
+        .collect();
+    let mut body = vec![
+        json!({ "type": "TextBlock", "text": summary.title, "weight": "Bolder", "size": "Medium",
+                "color": summary.status.adaptive_color(), "wrap": true }),
+        json!({ "type": "FactSet", "facts": facts }),
+    ];
+    if summary.lines.len() > 50 {
+        body.push(json!({ "type": "TextBlock", "text": format!("… and {} more", summary.lines.len() - 50), "wrap": true }));
+    }
+    body.push(json!({ "type": "TextBlock", "text": summary.footer, "isSubtle": true, "size": "Small", "wrap": true }));
+    json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "contentUrl": null,
+            "content": {
+                "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                "type": "AdaptiveCard",
+                "version": "1.4",
+                "msteams": { "width": "Full" },
+                "body": body,
+            },
+        }],
+    })
+}
+
+fn teams_message_card(summary: &Summary) -> Value {
+    let facts: Vec<Value> = summary.lines.iter().take(50)
+        .map(|l| json!({ "name": format!("{} {}", l.status.emoji(), l.subject), "value": l.detail }))
+        .collect();
+    let mut text = summary.footer.clone();
+    if summary.lines.len() > 50 {
+        text = format!("… and {} more\n\n{}", summary.lines.len() - 50, text);
+    }
+    json!({
+        "@type": "MessageCard",
+        "@context": "https://schema.org/extensions",
+        "summary": summary.title,
+        "themeColor": summary.status.hex().trim_start_matches('#'),
+        "title": format!("{} {}", summary.status.emoji(), summary.title),
+        "sections": [{ "facts": facts, "text": text }],
+    })
+}
+
+fn discord(summary: &Summary, username: Option<&str>) -> Value {
+    // Embed titles are limited to 256 characters, descriptions to 4096
+    let lines = summary.text(4096, |l| format!("{} {} {}", l.status.emoji(), code(&l.subject), l.detail));
+    let color = u32::from_str_radix(summary.status.hex().trim_start_matches('#'), 16).unwrap_or(0);
+    let timestamp = chrono::DateTime::from_timestamp(summary.time, 0).map(|t| t.to_rfc3339());
+    let payload = json!({
+        "embeds": [{
+            "title": truncate(&format!("{} {}", summary.status.emoji(), summary.title), 256),
+            "description": lines,
+            "color": color,
+            "footer": { "text": truncate(&summary.footer, 2048) },
+            "timestamp": timestamp,
+        }],
+        "allowed_mentions": { "parse": [] },
+    });
+    with_username(payload, "username", username)
+}
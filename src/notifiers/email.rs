@@ -0,0 +1,403 @@
+// Email notifier: the HTML system report and resolved report sent over SMTP.
+//
+// Server, credentials, sender and recipients come from the top-level settings in config.yaml; a
+// `type: email` entry in the notifiers list may send to other recipients with its own email_to.
+// Nothing is sent while mail_enabled is false. The top-level settings are optional in the config
+// and only validated when an email notifier is configured.
+
+use futures::future::BoxFuture;
+use lettre::{Message, SmtpTransport, Transport, transport::smtp::authentication::Credentials, transport::smtp::client::Tls, transport::smtp::client::TlsParameters};
+
+use super::{Kind, Notifier, Report};
+use crate::alerts::{Condition, Severity};
+use crate::smart::{Counter, SmartAttribute};
+use crate::{config, format_local_time, smartctl_available, units};
+
+#[derive(serde::Deserialize, Debug, Clone, Default)]
+pub struct EmailConfig {
+    pub email_to: Option<String>, // Comma-separated recipients (default: the top-level email_to)
+}
+
+impl EmailConfig {
+    pub fn validate(&self, name: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        if let Some(email_to) = &self.email_to {
+            let recipients: Vec<&str> = email_to.split(',').map(str::trim).filter(|a| !a.is_empty()).collect();
+            if recipients.is_empty() {
+                errors.push(format!("{}.email_to (must be a valid email address)", name));
+            } else if recipients.iter().any(|a| !a.contains('@')) {
+                errors.push(format!("{}.email_to (one or more recipients appear invalid)", name));
+            }
+        }
+        errors
+    }
+}
+
+pub struct EmailNotifier {
+    mail_enabled: bool,
+    smtp_server: String,
+    smtp_port: u16,
+    smtp_user: String,
+    smtp_pass: String,
+    smtp_security: Option<String>,
+    email_from: String,
+    email_to: String,
+}
+
+impl EmailNotifier {
+    pub fn new(cfg: &config::Config, settings: &EmailConfig) -> Self {
+        EmailNotifier {
+            // validate_config requires these whenever an email notifier is in use
+            mail_enabled: cfg.mail_enabled.unwrap_or(true),
+            smtp_server: cfg.smtp_server.clone().unwrap_or_default(),
+            smtp_port: cfg.smtp_port.unwrap_or_default(),
+            smtp_user: cfg.smtp_user.clone().unwrap_or_default(),
+            smtp_pass: cfg.smtp_pass.clone().unwrap_or_default(),
+            smtp_security: cfg.smtp_security.clone(),
+            email_from: cfg.email_from.clone().unwrap_or_default(),
+            email_to: settings.email_to.clone().or_else(|| cfg.email_to.clone()).unwrap_or_default(),
+        }
+    }
+
+    fn message(&self, subject: String, body: String) -> Result<Message, String> {
+        // Build the email message; allow multiple recipients separated by commas in email_to
+        let mut builder = Message::builder()
+            .from(self.email_from.parse().map_err(|e| format!("Invalid sender email address: {e}"))?);
+
+        // Support comma-separated list of recipients in `email_to` (e.g. "a@x.com, b@y.com")
+        for addr in self.email_to.split(',') {
+            let addr = addr.trim();
+            if addr.is_empty() {
+                continue;
+            }
+            builder = builder.to(addr.parse().map_err(|e| format!("Invalid recipient email address '{}': {}", addr, e))?);
+        }
+
+        builder
+            .subject(subject)
+            .header(lettre::message::header::ContentType::TEXT_HTML)
+            .body(body)
+            .map_err(|e| format!("Failed to build email message: {e}"))
+    }
+
+    fn transport(&self, debug: bool) -> Result<SmtpTransport, String> {
+        let use_auth = !(self.smtp_user.trim().is_empty() && self.smtp_pass.trim().is_empty());
+        let security = self.smtp_security.as_deref().unwrap_or("starttls").to_lowercase();
+        if debug {
+            println!("[DEBUG] smtp_security from config: {:?}", self.smtp_security);
+        }
+        let mut builder = match security.as_str() {
+            "none" => SmtpTransport::builder_dangerous(&self.smtp_server).port(self.smtp_port),
+            "ssl" => {
+                let tls = TlsParameters::new(self.smtp_server.clone())
+                    .map_err(|e| format!("TLS parameter error: {e}"))?;
+                SmtpTransport::relay(&self.smtp_server)
+                    .map_err(|e| format!("SMTP relay error: {e}"))?
+                    .port(self.smtp_port)
+                    .tls(Tls::Wrapper(tls))
+            },
+            _ => { // starttls (default)
+                SmtpTransport::relay(&self.smtp_server)
+                    .map_err(|e| format!("SMTP relay error: {e}"))?
+                    .port(self.smtp_port)
+            }
+        };
+        if use_auth {
+            builder = builder.credentials(Credentials::new(self.smtp_user.clone(), self.smtp_pass.clone()));
+        }
+        Ok(builder.build())
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn enabled(&self) -> bool {
+        self.mail_enabled
+    }
+
+    fn send<'a>(&'a self, report: &'a Report<'a>) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let (subject, body) = match report.kind {
+                Kind::Resolved => render_resolved(report),
+                Kind::Alert | Kind::Forced => render_report(report),
+            };
+            let email = self.message(subject, body)?;
+            let mailer = self.transport(report.debug)?;
+            // lettre's SMTP transport blocks; keep it off the runtime so other notifiers proceed
+            tokio::task::spawn_blocking(move || mailer.send(&email))
+                .await
+                .map_err(|e| format!("SMTP task failed: {e}"))?
+                .map(|_| ())
+                .map_err(|e| format!("SMTP error: {e}"))
+        })
+    }
+}
+
+// Render the SMART attribute table for the email body. The report body is a <pre> block, so
+// close it around the table and reopen it afterwards.
+fn smart_attributes_html(attributes: &[SmartAttribute]) -> String {
+    let mut html = String::from(
+        "</pre><table style=\"border-collapse:collapse; font-family:monospace; font-size:12px; margin-left:16px\">\n\
+         <tr style=\"background:#eee\"><th>ID#</th><th>Attribute</th><th>Flags</th><th>Value</th><th>Worst</th>\
+         <th>Thresh</th><th>When Failed</th><th>Raw Value</th></tr>\n",
+    );
+    for attr in attributes {
+        let style = if attr.failing { " style=\"color:red;font-weight:bold\"" } else { "" };
+        html.push_str(&format!(
+            "<tr{}><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            style,
+            attr.id,
+            attr.name,
+            attr.flags,
+            attr.value,
+            attr.worst,
+            attr.thresh,
+            if attr.when_failed.is_empty() { "-" } else { &attr.when_failed },
+            attr.raw_string
+        ));
+    }
+    html.push_str("</table><pre style=\"font-family: monospace;\">");
+    html
+}
+
+/// Subject and HTML body of the system report.
+fn render_report(report: &Report) -> (String, String) {
+    let Report { display_name, system_info, disks, debug, .. } = *report;
+    let forced = report.kind == Kind::Forced;
+
+    let mut subject = if forced {
+        format!("[RAPORT ZILNIC] System Disk Report - {} ({})", display_name, format!("{} {} {}", system_info.os_name, system_info.os_version, system_info.architecture))
+    } else {
+        format!("[ALARMĂ DEPĂSIRE PRAG DE STOCARE ] System Disk Report - {} ({})", display_name, format!("{} {} {}", system_info.os_name, system_info.os_version, system_info.architecture))
+    };
+
+    let os_info = format!("{} {} {}", system_info.os_name, system_info.os_version, system_info.architecture);
+
+    let datetime = format_local_time(report.time);
+
+    // Check if smartmontools is available for the email report
+    let smartctl_available = smartctl_available();
+
+    let mut body = format!(
+        "<html><body><pre style=\"font-family: monospace;\">\n\
+         <b>System Disk Report</b>\n\n\
+         <b>Device:</b> {} ({})\n\
+         <b>System:</b> {} {}\n\
+         <b>Hostname:</b> {}\n\
+         <b>Report Time:</b> {}\n\
+         <b>Mode:</b> {}\n\
+         <b>SMART Tools:</b> {}\n\
+         <b>Virtualization:</b> {}\n\n",
+        display_name,
+        system_info.hostname,
+        os_info,
+        if system_info.is_virtualized { "(Virtualized)" } else { "" },
+        system_info.hostname,
+        datetime,
+        if forced { "Forced Report" } else if debug { "Debug Mode" } else { "Normal Scan" },
+        if smartctl_available { "smartmontools detected - enhanced disk health monitoring" } else { "smartmontools not detected - using fallback methods" },
+        if system_info.is_virtualized { "Yes - Running in virtualized environment" } else { "No - Running on physical hardware" }
+    );
+
+    // Add disk summary
+    let total_disks = disks.len();
+    let critical_disks = disks.iter().filter(|d| d.severity() == Some(Severity::Critical)).count();
+    let warning_disks = disks.iter().filter(|d| d.severity() == Some(Severity::Warning)).count();
+    let low_space_disks = disks.iter().filter(|d| d.has_alert(Condition::LowSpace)).count();
+    let low_inode_disks = disks.iter().filter(|d| d.has_alert(Condition::LowInodes)).count();
+    let smart_failing_disks = disks.iter().filter(|d| d.smart.health.is_problem()).count();
+    let nvme_problem_disks = disks.iter()
+        .filter(|d| d.alerts.iter().any(|a| matches!(a.condition, Condition::NvmeCriticalWarning | Condition::NvmeSpare | Condition::NvmeWear)))
+        .count();
+    let unknown_smart_disks = disks.iter().filter(|d| d.smart.health.is_unknown()).count();
+
+    // Mark the report with the worst severity found on any disk
+    if let Some(severity) = report.severity() {
+        // Prefix subject with a visible severity marker (emoji + label). Coloring the subject is not
+        // widely supported in mail clients, so we use an emoji and a [CRITICAL]/[WARNING] prefix instead.
+        let (emoji, color, banner) = match severity {
+            Severity::Critical => ("🔴", "red", "CRITICAL: One or more disks require immediate attention"),
+            Severity::Warning => ("🟠", "darkorange", "WARNING: One or more disks require attention"),
+        };
+        subject = format!("{} [{}] {}", emoji, severity.label(), subject);
+        // Inject a visible banner into the HTML body so it stands out in email clients.
+        // We insert the banner before the existing <pre> so the monospace report follows.
+        body = body.replacen(
+            "<html><body><pre",
+            &format!("<html><body><h2 style=\"color:{}; margin-bottom:6px\">{}</h2><pre", color, banner),
+            1,
+        );
+    } else if forced {
+        // Forced report with no alerts: add a green marker to the subject so it's obvious
+        // that this was a forced/test run and no issues were detected.
+        subject = format!("🟢 {}", subject);
+    }
+
+    body.push_str(&format!(
+        "<b>Disk Summary:\n\
+         - <b>Total Disks:</b> {}\n\
+         - <b>Critical:</b> {}\n\
+         - <b>Warning:</b> {}\n\
+         - <b>Low Space (per threshold rule):</b> {}\n\
+         - <b>Low Inodes:</b> {}\n\
+         - <b>SMART Failing:</b> {}\n\
+         - <b>SMART Unknown:</b> {}\n\
+         - <b>NVMe Health Alerts:</b> {}\n\n",
+        total_disks, critical_disks, warning_disks, low_space_disks, low_inode_disks, smart_failing_disks, unknown_smart_disks, nvme_problem_disks
+    ));
+
+    // Add warnings for RAID devices
+    let any_raid = disks.iter().any(|d| d.smart.is_raid);
+    if any_raid {
+        body.push_str("\nWARNING: RAID device(s) detected. Health information may be unavailable or unreliable. This tool should NOT be used for health monitoring tasks on RAID systems.\n");
+    }
+
+    for (i, disk) in disks.iter().enumerate() {
+        let total_gb = disk.total_space as f64 / (1024.0 * 1024.0 * 1024.0);
+        let available_gb = disk.available_space as f64 / (1024.0 * 1024.0 * 1024.0);
+        let used_gb = total_gb - available_gb;
+
+        // Use HTML-colored status indicators so the issue stands out in the HTML email body.
+        // Note: subject coloring is not reliably supported across mail clients, so we use an emoji
+        // prefix in the subject and color the body instead.
+        let status_indicator = match disk.severity() {
+            Some(Severity::Critical) => "<span style=\"color:red;font-weight:bold\">[CRITICAL]</span>".to_string(),
+            Some(Severity::Warning) => "<span style=\"color:darkorange;font-weight:bold\">[WARNING]</span>".to_string(),
+            None => "<span style=\"color:green;font-weight:bold\">[OK]</span>".to_string(),
+        };
+
+body.push_str(&format!(
+    "<b>Disk {}: {} {}</b>\n\
+     <b> - Mount Point:</b> {}\n\
+     <b> - File System:</b> {}\n\
+     <b> - Total Space:</b> {:.2} GB\n\
+     <b> - Used Space:</b> {:.2} GB\n\
+     <b> - Available Space:</b> {:.2} GB\n\
+     <b> - Free Space:</b> {:.2}%\n\
+     <b> - Threshold Rule:</b> {}\n\
+     <b> - Health Check Method:</b> {}\n",
+    i + 1,
+    status_indicator,
+    &disk.display_name,
+    disk.mount_point,
+    disk.file_system,
+    total_gb,
+    used_gb,
+    available_gb,
+    disk.free_space_percent,
+    disk.thresholds.describe(),
+    disk.smart.method
+));
+
+        if let Some(forecast) = &disk.forecast {
+            body.push_str(&format!(" - Forecast: {}\n", forecast.describe()));
+        }
+
+        match disk.inode_free_percent {
+            Some(percent) => body.push_str(&format!(" - Inodes Used: {} of {} ({:.2}% free)\n",
+                                                    disk.total_inodes - disk.free_inodes, disk.total_inodes, percent)),
+            None => body.push_str(" - Inodes: N/A (not reported by this filesystem)\n"),
+        }
+
+        if let Some(val) = disk.smart.power_on_hours {
+            body.push_str(&format!(" - Power On Hours: {}\n", val));
+        }
+        if let Some(val) = disk.counter_text(Counter::ReallocatedSectors) {
+            body.push_str(&format!(" - Reallocated Sectors: {}\n", val));
+        }
+        if let Some(val) = disk.counter_text(Counter::PendingSectors) {
+            body.push_str(&format!(" - Pending Sectors: {}\n", val));
+        }
+        if let Some(val) = disk.counter_text(Counter::UncorrectableSectors) {
+            body.push_str(&format!(" - Uncorrectable Sectors: {}\n", val));
+        }
+        if let Some(val) = disk.counter_text(Counter::CrcErrors) {
+            body.push_str(&format!(" - CRC Errors: {}\n", val));
+        }
+        if let Some(val) = disk.smart.temperature {
+            body.push_str(&format!(" - Temperature: {} C\n", val));
+        }
+
+        if let Some(nvme) = &disk.smart.nvme {
+            body.push_str(&format!(" - NVMe Critical Warning: 0x{:02X}\n", nvme.critical_warning));
+            body.push_str(&format!(" - NVMe Available Spare: {}% (threshold {}%)\n", nvme.available_spare, nvme.available_spare_threshold));
+            body.push_str(&format!(" - NVMe Percentage Used: {}%\n", nvme.percentage_used));
+            body.push_str(&format!(" - NVMe Data Units Read/Written: {} / {}\n", nvme.data_units_read, nvme.data_units_written));
+            body.push_str(&format!(" - NVMe Media Errors: {}\n", disk.counter_text(Counter::MediaErrors).unwrap_or_default()));
+            body.push_str(&format!(" - NVMe Unsafe Shutdowns: {}\n", nvme.unsafe_shutdowns));
+            body.push_str(&format!(" - NVMe Warning/Critical Temperature Time: {} / {} min\n", nvme.warning_temp_time, nvme.critical_comp_time));
+        }
+        for alert in &disk.alerts {
+            let color = match alert.severity {
+                Severity::Critical => "red",
+                Severity::Warning => "darkorange",
+            };
+            body.push_str(&format!("   * <span style=\"color:{}\">{}: {}!</span>\n", color, alert.severity.label(), alert.message));
+        }
+
+        if let Some(serial) = &disk.smart.serial_number {
+            body.push_str(&format!(" - Serial Number: {}\n", serial));
+        }
+        if let Some(brand) = &disk.smart.brand {
+            body.push_str(&format!(" - Brand: {}\n", brand));
+        }
+        if let Some(model) = &disk.smart.model {
+            body.push_str(&format!(" - Model: {}\n", model));
+        }
+        if disk.smart.is_raid {
+            body.push_str(" - RAID: Yes (SMART status may not be accurate)\n");
+            body.push_str("   * WARNING: RAID device detected; health info may be unreliable.\n");
+        }
+        if system_info.is_virtualized {
+            body.push_str("   * WARNING: Running in virtualized environment; health info may be unreliable.\n");
+        }
+        if !disk.smart.smart_attributes.is_empty() {
+            body.push_str(&smart_attributes_html(&disk.smart.smart_attributes));
+        }
+
+        body.push('\n');
+    }
+
+    // Add closing HTML tags
+    body.push_str("</pre></body></html>");
+
+    (subject, body)
+}
+
+/// Subject and HTML body listing alert conditions that have cleared since they were notified.
+fn render_resolved(report: &Report) -> (String, String) {
+    let Report { display_name, system_info, resolved, time: now, .. } = *report;
+    let os_info = format!("{} {} {}", system_info.os_name, system_info.os_version, system_info.architecture);
+    let subject = format!("🟢 [RESOLVED] System Disk Report - {} ({})", display_name, os_info);
+
+    let mut body = format!(
+        "<html><body><h2 style=\"color:green; margin-bottom:6px\">RESOLVED: {} condition(s) cleared</h2>\
+         <pre style=\"font-family: monospace;\">\n\
+         <b>Device:</b> {} ({})\n\
+         <b>System:</b> {}\n\
+         <b>Report Time:</b> {}\n\n",
+        resolved.len(),
+        display_name,
+        system_info.hostname,
+        os_info,
+        format_local_time(now)
+    );
+    for record in resolved {
+        body.push_str(&format!(
+            "<b>{}</b> <span style=\"color:green;font-weight:bold\">[RESOLVED]</span>\n\
+             <b> - Mount Point:</b> {}\n\
+             <b> - Condition:</b> {} (was {})\n\
+             <b> - Last Alert:</b> {}\n\
+             <b> - Active Since:</b> {} ({})\n\n",
+            record.display_name,
+            record.mount_point,
+            record.condition,
+            record.severity.label(),
+            record.message,
+            format_local_time(record.first_seen),
+            units::format_elapsed(now.saturating_sub(record.first_seen) as u64)
+        ));
+    }
+    body.push_str("</pre></body></html>");
+
+    (subject, body)
+}
@@ -0,0 +1,467 @@
+// Notification channels.
+//
+// A run sends up to three kinds of notification: the alert report (when alerts are new, escalated
+// or due for a reminder), the forced or daily report, and the resolved report. Each is described by
+// a Report and handed to every configured notifier. A notifier only renders and delivers it once;
+// retries with exponential backoff and the per-notifier result reporting live here, so a new
+// channel only has to implement Notifier::send.
+//
+// Without a `notifiers:` list in config.yaml the email notifier is used on its own, configured by
+// the top-level SMTP settings as before. Those settings are only required while email is in use.
+
+pub mod chat;
+pub mod email;
+pub mod push;
+pub mod syslog;
+pub mod webhook;
+
+use std::time::Duration;
+
+use backoff::{ExponentialBackoff, backoff::Backoff};
+use colored::*;
+use futures::future::{BoxFuture, join_all};
+use log::warn;
+
+use crate::alerts::Severity;
+use crate::state::AlertRecord;
+use crate::units::HumanDuration;
+use crate::{DiskInfo, config, system};
+
+/// What a notification is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Alert, // Alerts that are new, escalated or due for a reminder (or every run in debug mode)
+    Forced, // --force-mail or the daemon's daily report
+    Resolved, // Notified alerts that have cleared
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Alert => "alert",
+            Kind::Forced => "report",
+            Kind::Resolved => "resolved",
+        }
+    }
+}
+
+/// Everything a notifier needs to render one notification.
+pub struct Report<'a> {
+    pub kind: Kind,
+    pub cfg: &'a config::Config,
+    pub display_name: &'a str, // friendly_name, or the hostname
+    pub system_info: &'a system::SystemInfo,
+    pub disks: &'a [DiskInfo], // All monitored disks (empty for Kind::Resolved)
+    pub resolved: &'a [AlertRecord], // Cleared alerts (only for Kind::Resolved)
+    pub time: i64,
+    pub debug: bool,
+}
+
+impl<'a> Report<'a> {
+    pub fn disks(cfg: &'a config::Config, kind: Kind, system_info: &'a system::SystemInfo, disks: &'a [DiskInfo], debug: bool) -> Self {
+        Report {
+            kind,
+            cfg,
+            display_name: cfg.friendly_name.as_deref().unwrap_or(&system_info.hostname),
+            system_info,
+            disks,
+            resolved: &[],
+            time: crate::unix_now(),
+            debug,
+        }
+    }
+
+    pub fn resolved(cfg: &'a config::Config, system_info: &'a system::SystemInfo, resolved: &'a [AlertRecord], debug: bool) -> Self {
+        Report {
+            kind: Kind::Resolved,
+            cfg,
+            display_name: cfg.friendly_name.as_deref().unwrap_or(&system_info.hostname),
+            system_info,
+            disks: &[],
+            resolved,
+            time: crate::unix_now(),
+            debug,
+        }
+    }
+
+    /// Worst severity among the disks' alerts; None for resolved reports and healthy disks.
+    pub fn severity(&self) -> Option<Severity> {
+        self.disks.iter().filter_map(|d| d.severity()).max()
+    }
+
+    /// Short description for console and log lines.
+    pub fn describe(&self) -> String {
+        match self.kind {
+            Kind::Resolved => format!("resolved report for {} condition(s)", self.resolved.len()),
+            Kind::Forced => format!("system report for {} disk(s) (forced)", self.disks.len()),
+            Kind::Alert if self.debug => format!("system report for {} disk(s) (debug)", self.disks.len()),
+            Kind::Alert => format!("system report for {} disk(s)", self.disks.len()),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Default)]
+pub struct RetryConfig {
+    pub attempts: Option<u32>, // Delivery attempts including the first (default: 3)
+    pub initial_interval: Option<HumanDuration>, // Delay before the first retry (default: 1s)
+    pub max_interval: Option<HumanDuration>, // Upper bound for the growing delay (default: 30s)
+}
+
+#[derive(Debug, Clone)]
+struct RetryPolicy {
+    attempts: u32,
+    initial_interval: Duration,
+    max_interval: Duration,
+}
+
+impl RetryPolicy {
+    fn from_config(retry: Option<&RetryConfig>) -> Self {
+        let retry = retry.cloned().unwrap_or_default();
+        RetryPolicy {
+            attempts: retry.attempts.unwrap_or(3),
+            initial_interval: retry.initial_interval.map_or(Duration::from_secs(1), |d| d.as_duration()),
+            max_interval: retry.max_interval.map_or(Duration::from_secs(30), |d| d.as_duration()),
+        }
+    }
+
+    fn backoff(&self) -> ExponentialBackoff {
+        // current_interval is the first delay; it doesn't follow initial_interval on its own
+        ExponentialBackoff {
+            current_interval: self.initial_interval,
+            initial_interval: self.initial_interval,
+            max_interval: self.max_interval,
+            max_elapsed_time: Some(Duration::from_secs(300)), // 5 minutes max
+            ..ExponentialBackoff::default()
+        }
+    }
+}
+
+/// One entry of the `notifiers:` list.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct NotifierConfig {
+    pub name: Option<String>, // Shown in console output and logs (default: the type)
+    pub enabled: Option<bool>, // Set to false to keep the entry but not send (default: true)
+    pub min_severity: Option<Severity>, // Only send alert and resolved reports at or above this severity (default: all)
+    pub retry: Option<RetryConfig>,
+    #[serde(flatten)]
+    pub kind: NotifierKind,
+}
+
+/// Channel-specific settings, selected by `type`.
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum NotifierKind {
+    Email(email::EmailConfig),
+    Webhook(webhook::WebhookConfig),
+    Slack(chat::ChatConfig),
+    Mattermost(chat::ChatConfig),
+    Teams(chat::TeamsConfig),
+    Discord(chat::ChatConfig),
+    Ntfy(push::NtfyConfig),
+    Gotify(push::GotifyConfig),
+    Pushover(push::PushoverConfig),
+    Syslog(syslog::SyslogConfig),
+    Journald(syslog::JournaldConfig),
+}
+
+impl NotifierKind {
+    fn type_name(&self) -> &'static str {
+        match self {
+            NotifierKind::Email(_) => "email",
+            NotifierKind::Webhook(_) => "webhook",
+            NotifierKind::Slack(_) => "slack",
+            NotifierKind::Mattermost(_) => "mattermost",
+            NotifierKind::Teams(_) => "teams",
+            NotifierKind::Discord(_) => "discord",
+            NotifierKind::Ntfy(_) => "ntfy",
+            NotifierKind::Gotify(_) => "gotify",
+            NotifierKind::Pushover(_) => "pushover",
+            NotifierKind::Syslog(_) => "syslog",
+            NotifierKind::Journald(_) => "journald",
+        }
+    }
+}
+
+impl NotifierConfig {
+    pub fn display_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.kind.type_name().to_string())
+    }
+
+    pub fn validate(&self, index: usize) -> Vec<String> {
+        let name = format!("notifiers[{}]", index);
+        let mut errors = Vec::new();
+        if self.name.as_ref().is_some_and(|n| n.trim().is_empty()) {
+            errors.push(format!("{}.name (must not be empty)", name));
+        }
+        if let Some(retry) = &self.retry {
+            if retry.attempts == Some(0) {
+                errors.push(format!("{}.retry.attempts (must be at least 1)", name));
+            }
+            if let (Some(initial), Some(max)) = (retry.initial_interval, retry.max_interval)
+                && initial > max
+            {
+                errors.push(format!("{}.retry.initial_interval (must not be above max_interval)", name));
+            }
+        }
+        match &self.kind {
+            NotifierKind::Email(settings) => errors.extend(settings.validate(&name)),
+            NotifierKind::Webhook(settings) => errors.extend(settings.validate(&name)),
+            NotifierKind::Slack(settings) | NotifierKind::Mattermost(settings) | NotifierKind::Discord(settings) => {
+                errors.extend(settings.validate(&name))
+            }
+            NotifierKind::Teams(settings) => errors.extend(settings.validate(&name)),
+            NotifierKind::Ntfy(settings) => errors.extend(settings.validate(&name)),
+            NotifierKind::Gotify(settings) => errors.extend(settings.validate(&name)),
+            NotifierKind::Pushover(settings) => errors.extend(settings.validate(&name)),
+            NotifierKind::Syslog(settings) => errors.extend(settings.validate(&name)),
+            NotifierKind::Journald(settings) => errors.extend(settings.validate(&name)),
+        }
+        errors
+    }
+
+    fn build(&self, cfg: &config::Config) -> Channel {
+        let notifier: Box<dyn Notifier> = match &self.kind {
+            NotifierKind::Email(settings) => Box::new(email::EmailNotifier::new(cfg, settings)),
+            NotifierKind::Webhook(settings) => Box::new(webhook::WebhookNotifier::new(settings)),
+            NotifierKind::Slack(settings) => Box::new(chat::ChatNotifier::new(chat::Style::Slack, settings)),
+            NotifierKind::Mattermost(settings) => Box::new(chat::ChatNotifier::new(chat::Style::Mattermost, settings)),
+            NotifierKind::Teams(settings) => Box::new(chat::ChatNotifier::teams(settings)),
+            NotifierKind::Discord(settings) => Box::new(chat::ChatNotifier::new(chat::Style::Discord, settings)),
+            NotifierKind::Ntfy(settings) => Box::new(push::PushNotifier::ntfy(settings)),
+            NotifierKind::Gotify(settings) => Box::new(push::PushNotifier::gotify(settings)),
+            NotifierKind::Pushover(settings) => Box::new(push::PushNotifier::pushover(settings)),
+            NotifierKind::Syslog(settings) => Box::new(syslog::SyslogNotifier::new(settings)),
+            NotifierKind::Journald(settings) => Box::new(syslog::JournaldNotifier::new(settings)),
+        };
+        Channel {
+            name: self.display_name(),
+            enabled: self.enabled.unwrap_or(true),
+            min_severity: self.min_severity,
+            retry: RetryPolicy::from_config(self.retry.as_ref()),
+            notifier,
+        }
+    }
+}
+
+/// A notification channel: renders a report and delivers it.
+pub trait Notifier: Send + Sync {
+    /// False when the channel is switched off by a setting of its own (mail_enabled: false for email).
+    fn enabled(&self) -> bool {
+        true
+    }
+
+    /// Render the report and deliver it once. Retries are handled by the caller.
+    fn send<'a>(&'a self, report: &'a Report<'a>) -> BoxFuture<'a, Result<(), String>>;
+}
+
+// A notifier with the settings every entry of the list shares
+struct Channel {
+    name: String,
+    enabled: bool,
+    min_severity: Option<Severity>,
+    retry: RetryPolicy,
+    notifier: Box<dyn Notifier>,
+}
+
+impl Channel {
+    // Forced reports are always sent; alert and resolved reports only when severe enough
+    fn wants(&self, report: &Report) -> bool {
+        let Some(min) = self.min_severity else { return true };
+        match report.kind {
+            Kind::Forced => true,
+            Kind::Alert => report.severity().is_some_and(|s| s >= min),
+            Kind::Resolved => report.resolved.iter().any(|r| r.severity >= min),
+        }
+    }
+
+    async fn deliver(&self, report: &Report<'_>) -> Result<(), String> {
+        let mut backoff = self.retry.backoff();
+        let mut attempt = 1;
+        loop {
+            match self.notifier.send(report).await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    warn!("{}: attempt {} failed: {}", self.name, attempt, e);
+                    if attempt >= self.retry.attempts {
+                        return Err(format!("{} (after {} attempt(s))", e, attempt));
+                    }
+                    match backoff.next_backoff() {
+                        Some(delay) => {
+                            warn!("{}: retrying in {:?}...", self.name, delay);
+                            tokio::time::sleep(delay).await;
+                            attempt += 1;
+                        }
+                        None => return Err(e),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Settings of the notifiers that can send email: the enabled `type: email` entries, or the implicit
+/// email notifier without a `notifiers:` list. The top-level SMTP settings are only used by these.
+pub fn email_notifiers(cfg: &config::Config) -> Vec<&email::EmailConfig> {
+    static IMPLICIT: email::EmailConfig = email::EmailConfig { email_to: None };
+    match &cfg.notifiers {
+        Some(list) => list.iter()
+            .filter(|n| n.enabled != Some(false))
+            .filter_map(|n| match &n.kind {
+                NotifierKind::Email(settings) => Some(settings),
+                _ => None,
+            })
+            .collect(),
+        None => vec![&IMPLICIT],
+    }
+}
+
+// The notifiers configured in config.yaml: the `notifiers:` list, or email alone without one
+fn from_config(cfg: &config::Config) -> Vec<Channel> {
+    match &cfg.notifiers {
+        Some(list) => list.iter().map(|n| n.build(cfg)).collect(),
+        None => vec![Channel {
+            name: "email".to_string(),
+            enabled: true,
+            min_severity: None,
+            retry: RetryPolicy::from_config(None),
+            notifier: Box::new(email::EmailNotifier::new(cfg, &email::EmailConfig::default())),
+        }],
+    }
+}
+
+/// Result of sending one report through all notifiers.
+#[derive(Debug, Default)]
+pub struct Outcome {
+    pub delivered: usize, // Notifiers that sent the report
+    pub skipped: usize, // Disabled notifiers and those whose min_severity the report is below
+    pub failed: Vec<(String, String)>, // (notifier, error) after all retries
+}
+
+impl Outcome {
+    /// True when at least one notifier got the report out. When none failed because every notifier
+    /// was skipped (test mode) or none is configured, the report counts as handled, so alert state
+    /// behaves as it would with notifications on instead of renotifying on every run.
+    pub fn any_delivered(&self) -> bool {
+        self.delivered > 0 || self.failed.is_empty()
+    }
+}
+
+enum Delivery {
+    Disabled,
+    BelowMinSeverity(Severity),
+    Done(Result<(), String>),
+}
+
+/// Send a report through every configured notifier concurrently and print each one's result.
+pub async fn send(cfg: &config::Config, report: &Report<'_>) -> Outcome {
+    let channels = from_config(cfg);
+    let description = report.describe();
+    let results = join_all(channels.iter().map(|channel| async move {
+        let delivery = if !channel.enabled || !channel.notifier.enabled() {
+            Delivery::Disabled
+        } else if !channel.wants(report) {
+            Delivery::BelowMinSeverity(channel.min_severity.unwrap_or(Severity::Warning))
+        } else {
+            Delivery::Done(channel.deliver(report).await)
+        };
+        (channel.name.as_str(), delivery)
+    })).await;
+
+    let mut outcome = Outcome::default();
+    for (name, delivery) in results {
+        match delivery {
+            Delivery::Disabled => {
+                println!("{} [{}] Not sending {} (notifier disabled).", "[TEST MODE]".yellow().bold(), name.cyan(), description);
+                outcome.skipped += 1;
+            }
+            Delivery::BelowMinSeverity(min) => {
+                println!("{} [{}] Not sending {} (below min_severity {}).", "SKIPPED".dimmed(), name.cyan(), description, min.as_str());
+                outcome.skipped += 1;
+            }
+            Delivery::Done(Ok(())) => {
+                println!("{} [{}] Sent {}", "SUCCESS".green().bold(), name.cyan(), description);
+                outcome.delivered += 1;
+            }
+            Delivery::Done(Err(e)) => {
+                eprintln!("{} [{}] Failed to send {}: {}", "ERROR".red().bold(), name, description, e);
+                outcome.failed.push((name.to_string(), e));
+            }
+        }
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::Condition;
+    use crate::testing;
+
+    struct Stub;
+
+    impl Notifier for Stub {
+        fn send<'a>(&'a self, _report: &'a Report<'a>) -> BoxFuture<'a, Result<(), String>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn channel(min_severity: Option<Severity>) -> Channel {
+        Channel { name: "stub".to_string(), enabled: true, min_severity, retry: RetryPolicy::from_config(None), notifier: Box::new(Stub) }
+    }
+
+    fn outcome(delivered: usize, skipped: usize, failed: usize) -> Outcome {
+        Outcome { delivered, skipped, failed: vec![("stub".to_string(), "timed out".to_string()); failed] }
+    }
+
+    #[test]
+    fn any_delivered_counts_skipped_and_missing_notifiers_as_handled() {
+        assert!(outcome(1, 0, 0).any_delivered());
+        assert!(outcome(1, 1, 1).any_delivered());
+        assert!(outcome(0, 2, 0).any_delivered());
+        assert!(outcome(0, 0, 0).any_delivered());
+        assert!(!outcome(0, 0, 1).any_delivered());
+        assert!(!outcome(0, 1, 1).any_delivered());
+    }
+
+    #[test]
+    fn min_severity_filters_alert_and_resolved_reports() {
+        let cfg = testing::config("{}");
+        let system_info = system::SystemInfo {
+            os_name: "Linux".to_string(),
+            os_version: "6.1".to_string(),
+            architecture: "x86_64".to_string(),
+            hostname: "nas".to_string(),
+            is_virtualized: false,
+        };
+        let mut disk = testing::disk("/srv", 1_000, 80, testing::thresholds("{}"));
+        disk.alerts.push(testing::alert(Condition::LowSpace, Severity::Warning, "low disk space"));
+        let disks = [disk];
+        let alert = Report::disks(&cfg, Kind::Alert, &system_info, &disks, false);
+        let forced = Report::disks(&cfg, Kind::Forced, &system_info, &disks, false);
+        let healthy = Report::disks(&cfg, Kind::Alert, &system_info, &[], false);
+
+        assert!(channel(None).wants(&alert));
+        assert!(channel(Some(Severity::Warning)).wants(&alert));
+        assert!(!channel(Some(Severity::Critical)).wants(&alert));
+        assert!(!channel(Some(Severity::Warning)).wants(&healthy));
+        assert!(channel(Some(Severity::Critical)).wants(&forced));
+
+        let record = |severity| AlertRecord {
+            mount_point: "/srv".to_string(),
+            display_name: "/srv".to_string(),
+            condition: Condition::LowSpace,
+            severity,
+            message: "low disk space".to_string(),
+            first_seen: 1_000,
+            last_seen: 2_000,
+            notified_severity: Some(severity),
+            last_notified: Some(1_000),
+            checks: 2,
+            cleared: Some(3_000),
+        };
+        let warning = [record(Severity::Warning)];
+        let mixed = [record(Severity::Warning), record(Severity::Critical)];
+        assert!(!channel(Some(Severity::Critical)).wants(&Report::resolved(&cfg, &system_info, &warning, false)));
+        assert!(channel(Some(Severity::Critical)).wants(&Report::resolved(&cfg, &system_info, &mixed, false)));
+    }
+}
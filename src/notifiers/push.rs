@@ -0,0 +1,405 @@
+// Push notifiers: ntfy, Gotify and Pushover.
+//
+// Phone notifications have little room, so every report becomes one short message: the title
+// names the status, the host and the worst offending disk ("CRITICAL on nas: /srv (+1 more)"),
+// the message its alerts and a line for each other affected disk. Severity maps to the service's
+// priority (and to tags on ntfy), so critical alerts can break through do-not-disturb while
+// warnings stay quiet.
+
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use serde_json::{Value, json};
+
+use super::webhook::{DEFAULT_TIMEOUT, post_json, valid_url};
+use super::{Kind, Notifier, Report};
+use crate::DiskInfo;
+use crate::alerts::Severity;
+use crate::units::HumanDuration;
+
+const PUSHOVER_API: &str = "https://api.pushover.net/1/messages.json";
+
+// Lines of other affected disks or cleared conditions after the first
+const MAX_EXTRA_LINES: usize = 4;
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct NtfyConfig {
+    pub url: String, // Topic URL, e.g. https://ntfy.sh/my-disks
+    pub token: Option<String>, // Access token for protected topics
+    pub timeout: Option<HumanDuration>, // Connect and response timeout per attempt (default: 10s)
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct GotifyConfig {
+    pub url: String, // Server URL, e.g. https://gotify.example.com
+    pub token: String, // Application token
+    pub timeout: Option<HumanDuration>, // Connect and response timeout per attempt (default: 10s)
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct PushoverConfig {
+    pub user_key: String, // User or group key
+    pub app_token: String, // Application API token
+    pub device: Option<String>, // Only notify this device (default: all of the user's devices)
+    pub url: Option<String>, // API endpoint (default: https://api.pushover.net/1/messages.json)
+    pub timeout: Option<HumanDuration>, // Connect and response timeout per attempt (default: 10s)
+}
+
+// "https://ntfy.sh/my-disks" -> ("https://ntfy.sh", "my-disks")
+fn split_topic(url: &str) -> Option<(&str, &str)> {
+    let url = url.trim().trim_end_matches('/');
+    let (server, topic) = url.rsplit_once('/')?;
+    (!server.ends_with('/') && !topic.is_empty()).then_some((server, topic))
+}
+
+fn validate_timeout(name: &str, timeout: Option<HumanDuration>, errors: &mut Vec<String>) {
+    if timeout.is_some_and(|t| t.0 == 0) {
+        errors.push(format!("{}.timeout (must be at least 1s)", name));
+    }
+}
+
+impl NtfyConfig {
+    pub fn validate(&self, name: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        if !valid_url(&self.url) || split_topic(&self.url).is_none() {
+            errors.push(format!("{}.url (must be a topic URL such as https://ntfy.sh/my-disks)", name));
+        }
+        if self.token.as_ref().is_some_and(|t| t.trim().is_empty()) {
+            errors.push(format!("{}.token (must not be empty)", name));
+        }
+        validate_timeout(name, self.timeout, &mut errors);
+        errors
+    }
+}
+
+impl GotifyConfig {
+    pub fn validate(&self, name: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        if !valid_url(&self.url) {
+            errors.push(format!("{}.url (must be an http:// or https:// URL)", name));
+        }
+        if self.token.trim().is_empty() {
+            errors.push(format!("{}.token (must not be empty)", name));
+        }
+        validate_timeout(name, self.timeout, &mut errors);
+        errors
+    }
+}
+
+impl PushoverConfig {
+    pub fn validate(&self, name: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.user_key.trim().is_empty() {
+            errors.push(format!("{}.user_key (must not be empty)", name));
+        }
+        if self.app_token.trim().is_empty() {
+            errors.push(format!("{}.app_token (must not be empty)", name));
+        }
+        if self.url.as_deref().is_some_and(|url| !valid_url(url)) {
+            errors.push(format!("{}.url (must be an http:// or https:// URL)", name));
+        }
+        validate_timeout(name, self.timeout, &mut errors);
+        errors
+    }
+}
+
+enum Service {
+    Ntfy { server: String, topic: String, token: Option<String> },
+    Gotify { url: String, token: String },
+    Pushover { url: String, user_key: String, app_token: String, device: Option<String> },
+}
+
+pub struct PushNotifier {
+    service: Service,
+    timeout: Duration,
+}
+
+fn timeout(setting: Option<HumanDuration>) -> Duration {
+    setting.map_or(DEFAULT_TIMEOUT, |t| t.as_duration())
+}
+
+impl PushNotifier {
+    pub fn ntfy(settings: &NtfyConfig) -> Self {
+        let (server, topic) = split_topic(&settings.url).unwrap_or((settings.url.as_str(), ""));
+        PushNotifier {
+            service: Service::Ntfy { server: server.to_string(), topic: topic.to_string(), token: settings.token.clone() },
+            timeout: timeout(settings.timeout),
+        }
+    }
+
+    pub fn gotify(settings: &GotifyConfig) -> Self {
+        PushNotifier {
+            service: Service::Gotify {
+                url: format!("{}/message", settings.url.trim().trim_end_matches('/')),
+                token: settings.token.clone(),
+            },
+            timeout: timeout(settings.timeout),
+        }
+    }
+
+    pub fn pushover(settings: &PushoverConfig) -> Self {
+        PushNotifier {
+            service: Service::Pushover {
+                url: settings.url.clone().unwrap_or_else(|| PUSHOVER_API.to_string()),
+                user_key: settings.user_key.clone(),
+                app_token: settings.app_token.clone(),
+                device: settings.device.clone(),
+            },
+            timeout: timeout(settings.timeout),
+        }
+    }
+
+    // Request URL, extra headers and JSON body for this service
+    fn request(&self, message: &Message) -> (String, Vec<(String, String)>, Value) {
+        match &self.service {
+            Service::Ntfy { server, topic, token } => {
+                // https://docs.ntfy.sh/publish/#publish-as-json
+                let (priority, tag) = match message.severity {
+                    Some(Severity::Critical) => (5, "rotating_light"),
+                    Some(Severity::Warning) => (4, "warning"),
+                    None => (3, "white_check_mark"),
+                };
+                let headers = token.iter().map(|t| ("Authorization".to_string(), format!("Bearer {}", t.trim()))).collect();
+                let body = json!({
+                    "topic": topic,
+                    "title": message.title,
+                    "message": message.body,
+                    "priority": priority,
+                    "tags": [tag, "diskmon"],
+                });
+                (server.clone(), headers, body)
+            }
+            Service::Gotify { url, token } => {
+                // Gotify's Android app pops up from priority 8 and stays silent below 4
+                let priority = match message.severity {
+                    Some(Severity::Critical) => 8,
+                    Some(Severity::Warning) => 5,
+                    None => 2,
+                };
+                let headers = vec![("X-Gotify-Key".to_string(), token.trim().to_string())];
+                let body = json!({ "title": message.title, "message": message.body, "priority": priority });
+                (url.clone(), headers, body)
+            }
+            Service::Pushover { url, user_key, app_token, device } => {
+                // High priority (1) bypasses quiet hours; emergency (2) would need acknowledgement
+                let priority = match message.severity {
+                    Some(Severity::Critical) => 1,
+                    Some(Severity::Warning) => 0,
+                    None => -1,
+                };
+                let mut body = json!({
+                    "token": app_token.trim(),
+                    "user": user_key.trim(),
+                    "title": message.title,
+                    "message": message.body,
+                    "priority": priority,
+                    "timestamp": message.time,
+                });
+                if let Some(device) = device {
+                    body["device"] = json!(device);
+                }
+                (url.clone(), Vec::new(), body)
+            }
+        }
+    }
+}
+
+impl Notifier for PushNotifier {
+    fn send<'a>(&'a self, report: &'a Report<'a>) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let message = Message::new(report);
+            let (url, headers, body) = self.request(&message);
+            post_json(&url, headers, body.to_string(), self.timeout).await
+        })
+    }
+}
+
+/// One short push message for a report.
+struct Message {
+    severity: Option<Severity>,
+    title: String,
+    body: String,
+    time: i64,
+}
+
+// The disk to name in the title: most severe alert first, then least free space
+fn worst_disk(disks: &[DiskInfo]) -> Option<&DiskInfo> {
+    disks.iter()
+        .filter(|d| !d.alerts.is_empty())
+        .max_by(|a, b| a.severity().cmp(&b.severity()).then(b.free_space_percent.total_cmp(&a.free_space_percent)))
+}
+
+fn more(count: usize) -> String {
+    if count > 0 { format!(" (+{} more)", count) } else { String::new() }
+}
+
+fn alert_messages(disk: &DiskInfo) -> String {
+    disk.alerts.iter().map(|a| a.message.as_str()).collect::<Vec<_>>().join("; ")
+}
+
+impl Message {
+    fn new(report: &Report) -> Message {
+        let host = report.display_name;
+        if report.kind == Kind::Resolved {
+            let mut lines: Vec<String> = report.resolved.iter().take(MAX_EXTRA_LINES + 1)
+                .map(|r| format!("{}: {} cleared (was {})", r.display_name, r.condition, r.severity.label()))
+                .collect();
+            if report.resolved.len() > lines.len() {
+                lines.push(format!("… and {} more", report.resolved.len() - lines.len()));
+            }
+            let first = report.resolved.first().map_or("", |r| r.display_name.as_str());
+            return Message {
+                severity: None,
+                title: format!("RESOLVED on {}: {}{}", host, first, more(report.resolved.len().saturating_sub(1))),
+                body: lines.join("\n"),
+                time: report.time,
+            };
+        }
+
+        let severity = report.severity();
+        let Some(worst) = worst_disk(report.disks) else {
+            // Nothing to report on (forced report or debug run on healthy disks)
+            let lowest = report.disks.iter().min_by(|a, b| a.free_space_percent.total_cmp(&b.free_space_percent));
+            let body = match lowest {
+                Some(disk) => format!("{} disk(s) healthy, lowest free space {:.2}% on {}",
+                                      report.disks.len(), disk.free_space_percent, disk.display_name),
+                None => "No disks checked".to_string(),
+            };
+            return Message { severity, title: format!("OK on {}", host), body, time: report.time };
+        };
+
+        let others: Vec<&DiskInfo> = report.disks.iter()
+            .filter(|d| !d.alerts.is_empty() && d.mount_point != worst.mount_point)
+            .collect();
+        let label = severity.map_or("OK", |s| s.label());
+        let mut lines = vec![alert_messages(worst)];
+        lines.extend(others.iter().take(MAX_EXTRA_LINES).map(|d| format!("{}: {}", d.display_name, alert_messages(d))));
+        if others.len() > MAX_EXTRA_LINES {
+            lines.push(format!("… and {} more", others.len() - MAX_EXTRA_LINES));
+        }
+        Message {
+            severity,
+            title: format!("{} on {}: {}{}", label, host, worst.display_name, more(others.len())),
+            body: lines.join("\n"),
+            time: report.time,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::Condition;
+    use crate::state::AlertRecord;
+    use crate::system::SystemInfo;
+    use crate::testing;
+
+    fn system_info() -> SystemInfo {
+        SystemInfo {
+            os_name: "Linux".to_string(),
+            os_version: "6.1".to_string(),
+            architecture: "x86_64".to_string(),
+            hostname: "nas".to_string(),
+            is_virtualized: false,
+        }
+    }
+
+    fn disk(mount_point: &str, free_space_percent: f64, alerts: &[(Severity, &str)]) -> DiskInfo {
+        let total: u64 = 1 << 40;
+        let mut disk = testing::disk(mount_point, total, (total as f64 * free_space_percent / 100.0) as u64,
+                                     testing::thresholds("critical_threshold_percent: 5"));
+        disk.free_space_percent = free_space_percent;
+        disk.alerts = alerts.iter().map(|(severity, message)| testing::alert(Condition::LowSpace, *severity, message)).collect();
+        disk
+    }
+
+    fn resolved(mount_point: &str) -> AlertRecord {
+        AlertRecord {
+            mount_point: mount_point.to_string(),
+            display_name: mount_point.to_string(),
+            condition: Condition::Temperature,
+            severity: Severity::Warning,
+            message: "Temperature 58°C".to_string(),
+            first_seen: 1_700_000_000,
+            last_seen: 1_700_003_600,
+            notified_severity: Some(Severity::Warning),
+            last_notified: Some(1_700_000_000),
+            checks: 3,
+            cleared: Some(1_700_003_900),
+        }
+    }
+
+    fn message(kind: Kind, disks: &[DiskInfo], resolved: &[AlertRecord]) -> Message {
+        let cfg = testing::config("{}");
+        let system_info = system_info();
+        let report = Report { kind, cfg: &cfg, display_name: "nas", system_info: &system_info, disks, resolved, time: 1_700_000_000, debug: false };
+        Message::new(&report)
+    }
+
+    #[test]
+    fn split_topic_url() {
+        assert_eq!(split_topic("https://ntfy.sh/my-disks"), Some(("https://ntfy.sh", "my-disks")));
+        assert_eq!(split_topic(" https://ntfy.example.com/push/disks/ "), Some(("https://ntfy.example.com/push", "disks")));
+        assert_eq!(split_topic("https://ntfy.sh/"), None);
+        assert_eq!(split_topic("ntfy.sh"), None);
+    }
+
+    #[test]
+    fn title_names_the_worst_disk() {
+        let disks = [
+            disk("/", 40.0, &[]),
+            disk("/home", 8.0, &[(Severity::Warning, "Low disk space: 8.00% free")]),
+            disk("/srv", 3.0, &[(Severity::Critical, "Low disk space: 3.00% free"), (Severity::Warning, "Temperature 58°C")]),
+            disk("/var", 9.0, &[(Severity::Warning, "Low disk space: 9.00% free")]),
+        ];
+        let message = message(Kind::Alert, &disks, &[]);
+        assert_eq!(message.severity, Some(Severity::Critical));
+        assert_eq!(message.title, "CRITICAL on nas: /srv (+2 more)");
+        assert_eq!(message.body, "Low disk space: 3.00% free; Temperature 58°C\n/home: Low disk space: 8.00% free\n/var: Low disk space: 9.00% free");
+        assert_eq!(message.time, 1_700_000_000);
+    }
+
+    #[test]
+    fn same_severity_prefers_least_free_space() {
+        let disks = [
+            disk("/home", 8.0, &[(Severity::Warning, "Low disk space: 8.00% free")]),
+            disk("/var", 6.5, &[(Severity::Warning, "Low disk space: 6.50% free")]),
+        ];
+        assert_eq!(message(Kind::Alert, &disks, &[]).title, "WARNING on nas: /var (+1 more)");
+    }
+
+    #[test]
+    fn body_lists_at_most_four_other_disks() {
+        let disks: Vec<DiskInfo> = (0..7).map(|i| disk(&format!("/data{}", i), 5.0 + i as f64, &[(Severity::Warning, "low")])).collect();
+        let message = message(Kind::Alert, &disks, &[]);
+        assert_eq!(message.title, "WARNING on nas: /data0 (+6 more)");
+        let lines: Vec<&str> = message.body.lines().collect();
+        assert_eq!(lines.len(), 1 + MAX_EXTRA_LINES + 1);
+        assert_eq!(lines[1], "/data1: low");
+        assert_eq!(lines[MAX_EXTRA_LINES + 1], "… and 2 more");
+    }
+
+    #[test]
+    fn healthy_report() {
+        let disks = [disk("/", 40.0, &[]), disk("/home", 12.5, &[])];
+        let message = message(Kind::Forced, &disks, &[]);
+        assert_eq!(message.severity, None);
+        assert_eq!(message.title, "OK on nas");
+        assert_eq!(message.body, "2 disk(s) healthy, lowest free space 12.50% on /home");
+        assert_eq!(self::message(Kind::Forced, &[], &[]).body, "No disks checked");
+    }
+
+    #[test]
+    fn resolved_report() {
+        let records: Vec<AlertRecord> = (0..7).map(|i| resolved(&format!("/data{}", i))).collect();
+        let message = message(Kind::Resolved, &[], &records);
+        assert_eq!(message.severity, None);
+        assert_eq!(message.title, "RESOLVED on nas: /data0 (+6 more)");
+        let lines: Vec<&str> = message.body.lines().collect();
+        assert_eq!(lines.len(), MAX_EXTRA_LINES + 2);
+        assert_eq!(lines[0], "/data0: temperature cleared (was WARNING)");
+        assert_eq!(lines[MAX_EXTRA_LINES + 1], "… and 2 more");
+
+        assert_eq!(self::message(Kind::Resolved, &[], &records[..1]).title, "RESOLVED on nas: /data0");
+    }
+}
@@ -0,0 +1,492 @@
+// Log notifiers: syslog (RFC 5424) and the systemd journal.
+//
+// Every alert of a report becomes one structured log record, so alerts survive on boxes that
+// cannot send mail and log shippers can pick them up. Resolved reports log one record per cleared
+// condition, and a report without alerts logs a single summary. The fields are the same for both
+// outputs: journald gets them as DISKMON_MOUNT, DISKMON_SEVERITY, ... (so that
+// `journalctl DISKMON_SEVERITY=critical` works), syslog as the structured data element
+// [diskmon@32473 mount="..." severity="..." ...].
+//
+// Records are sent one datagram each from a blocking task. When a send fails part-way, the retry
+// of the same report resumes at the record that failed instead of logging the earlier ones again.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, SecondsFormat};
+use futures::future::BoxFuture;
+
+use super::{Kind, Notifier, Report};
+use crate::DiskInfo;
+use crate::alerts::{Alert, Severity};
+use crate::state::AlertRecord;
+
+const DEFAULT_ADDRESS: &str = "/dev/log";
+const DEFAULT_APP_NAME: &str = "diskmon";
+#[cfg(target_os = "linux")]
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+// Structured data ID; 32473 is the private enterprise number reserved for examples (RFC 5612)
+const SD_ID: &str = "diskmon@32473";
+
+const FACILITIES: &[(&str, u8)] = &[
+    ("kern", 0), ("user", 1), ("mail", 2), ("daemon", 3), ("auth", 4), ("syslog", 5), ("lpr", 6),
+    ("news", 7), ("uucp", 8), ("cron", 9), ("authpriv", 10), ("ftp", 11), ("local0", 16), ("local1", 17),
+    ("local2", 18), ("local3", 19), ("local4", 20), ("local5", 21), ("local6", 22), ("local7", 23),
+];
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct SyslogConfig {
+    pub address: Option<String>, // Socket path or udp://host:port (default: /dev/log)
+    pub facility: Option<String>, // daemon, user, local0-local7, ... (default: daemon)
+    pub app_name: Option<String>, // APP-NAME of each record (default: diskmon)
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct JournaldConfig {
+    pub app_name: Option<String>, // SYSLOG_IDENTIFIER of each record (default: diskmon)
+}
+
+fn facility_code(name: &str) -> Option<u8> {
+    FACILITIES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name.trim())).map(|(_, code)| *code)
+}
+
+// APP-NAME is at most 48 printable ASCII characters (RFC 5424 section 6.2.5)
+fn valid_app_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 48 && name.bytes().all(|b| b.is_ascii_graphic())
+}
+
+enum Address {
+    Unix(String),
+    Udp(String),
+}
+
+fn parse_address(address: &str) -> Option<Address> {
+    let address = address.trim();
+    if address.starts_with('/') {
+        return Some(Address::Unix(address.to_string()));
+    }
+    let target = address.strip_prefix("udp://")?;
+    let (host, port) = target.rsplit_once(':')?;
+    (!host.is_empty() && port.parse::<u16>().is_ok()).then(|| Address::Udp(target.to_string()))
+}
+
+impl SyslogConfig {
+    pub fn validate(&self, name: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.address.as_deref().is_some_and(|a| parse_address(a).is_none()) {
+            errors.push(format!("{}.address (must be a socket path or udp://host:port)", name));
+        }
+        if self.facility.as_deref().is_some_and(|f| facility_code(f).is_none()) {
+            errors.push(format!("{}.facility (must be daemon, user, local0-local7 or another syslog facility)", name));
+        }
+        if self.app_name.as_deref().is_some_and(|a| !valid_app_name(a)) {
+            errors.push(format!("{}.app_name (must be 1-48 printable characters without spaces)", name));
+        }
+        errors
+    }
+}
+
+impl JournaldConfig {
+    pub fn validate(&self, name: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.app_name.as_deref().is_some_and(|a| !valid_app_name(a)) {
+            errors.push(format!("{}.app_name (must be 1-48 printable characters without spaces)", name));
+        }
+        errors
+    }
+}
+
+/// One log record: a syslog severity, the message and the structured fields.
+struct Record {
+    priority: u8, // 2 crit, 4 warning, 5 notice, 6 info
+    message: String,
+    fields: Vec<(&'static str, String)>,
+}
+
+fn priority(severity: Severity) -> u8 {
+    match severity {
+        Severity::Critical => 2,
+        Severity::Warning => 4,
+    }
+}
+
+fn alert_record(report: &Report, disk: &DiskInfo, alert: &Alert) -> Record {
+    let mut fields = vec![
+        ("event", report.kind.as_str().to_string()),
+        ("host", report.display_name.to_string()),
+        ("mount", disk.mount_point.clone()),
+        ("device", disk.device.clone()),
+        ("condition", alert.condition.to_string()),
+        ("severity", alert.severity.as_str().to_string()),
+        ("free_percent", format!("{:.2}", disk.free_space_percent)),
+        ("free_bytes", disk.available_space.to_string()),
+    ];
+    if let Some(serial) = &disk.smart.serial_number {
+        fields.push(("serial", serial.clone()));
+    }
+    Record {
+        priority: priority(alert.severity),
+        message: format!("{} {}: {}", alert.severity.label(), disk.display_name, alert.message),
+        fields,
+    }
+}
+
+fn resolved_record(report: &Report, record: &AlertRecord) -> Record {
+    let active = crate::units::format_elapsed((report.time - record.first_seen).max(0) as u64);
+    Record {
+        priority: 5,
+        message: format!("RESOLVED {}: {} cleared (was {}, active {})",
+                         record.display_name, record.condition, record.severity.label(), active),
+        fields: vec![
+            ("event", report.kind.as_str().to_string()),
+            ("host", report.display_name.to_string()),
+            ("mount", record.mount_point.clone()),
+            ("condition", record.condition.to_string()),
+            ("severity", "ok".to_string()),
+            ("previous_severity", record.severity.as_str().to_string()),
+        ],
+    }
+}
+
+fn records(report: &Report) -> Vec<Record> {
+    if report.kind == Kind::Resolved {
+        return report.resolved.iter().map(|r| resolved_record(report, r)).collect();
+    }
+    let records: Vec<Record> = report.disks.iter()
+        .flat_map(|disk| disk.alerts.iter().map(move |alert| alert_record(report, disk, alert)))
+        .collect();
+    if !records.is_empty() {
+        return records;
+    }
+    // Forced or debug report without alerts: one line saying so
+    vec![Record {
+        priority: 6,
+        message: format!("OK: all {} disk(s) healthy", report.disks.len()),
+        fields: vec![
+            ("event", report.kind.as_str().to_string()),
+            ("host", report.display_name.to_string()),
+            ("severity", "ok".to_string()),
+        ],
+    }]
+}
+
+impl Record {
+    /// RFC 5424: <PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID [SD] MSG. The timestamp is the
+    /// report's, so a retry renders the same message.
+    fn rfc5424(&self, facility: u8, time: i64, hostname: &str, app_name: &str, msgid: &str) -> String {
+        let params: String = self.fields.iter()
+            .map(|(name, value)| format!(" {}=\"{}\"", name, sd_escape(value)))
+            .collect();
+        let timestamp = DateTime::from_timestamp(time, 0)
+            .map_or("-".to_string(), |t| t.with_timezone(&Local).to_rfc3339_opts(SecondsFormat::Secs, false));
+        format!("<{}>1 {} {} {} {} {} [{}{}] {}",
+                facility * 8 + self.priority,
+                timestamp,
+                header_field(hostname, 255),
+                app_name,
+                std::process::id(),
+                msgid,
+                SD_ID,
+                params,
+                self.message)
+    }
+
+    /// The journal's native protocol: KEY=value lines, or KEY, a little-endian length and the
+    /// raw value for values spanning several lines.
+    fn journal_entry(&self, app_name: &str) -> Vec<u8> {
+        let mut entry = Vec::new();
+        let mut field = |key: &str, value: &str| {
+            entry.extend_from_slice(key.as_bytes());
+            if value.contains('\n') {
+                entry.push(b'\n');
+                entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+            } else {
+                entry.push(b'=');
+            }
+            entry.extend_from_slice(value.as_bytes());
+            entry.push(b'\n');
+        };
+        field("MESSAGE", &self.message);
+        field("PRIORITY", &self.priority.to_string());
+        field("SYSLOG_IDENTIFIER", app_name);
+        for (name, value) in &self.fields {
+            field(&format!("DISKMON_{}", name.to_ascii_uppercase()), value);
+        }
+        entry
+    }
+}
+
+// PARAM-VALUE escapes '"', '\' and ']' (RFC 5424 section 6.3.3)
+fn sd_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Header fields are printable ASCII, "-" when unknown
+fn header_field(value: &str, max: usize) -> String {
+    let value: String = value.chars().filter(|c| c.is_ascii_graphic()).take(max).collect();
+    if value.is_empty() { "-".to_string() } else { value }
+}
+
+// Delivery progress of the last report whose records were only partly sent
+#[derive(Default)]
+struct Resume(Mutex<Option<(Vec<Vec<u8>>, usize)>>);
+
+impl Resume {
+    /// Send the datagrams with `send` on a blocking thread, skipping those already delivered by
+    /// an earlier attempt for the same datagrams. `send` reports how many it sent before failing.
+    async fn send<F>(&self, datagrams: Vec<Vec<u8>>, send: F) -> Result<(), String>
+    where
+        F: FnOnce(&[Vec<u8>]) -> Result<(), (usize, String)> + Send + 'static,
+    {
+        let start = match &*self.0.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some((pending, sent)) if *pending == datagrams => *sent,
+            _ => 0,
+        };
+        let remaining = datagrams[start..].to_vec();
+        let result = tokio::task::spawn_blocking(move || send(&remaining))
+            .await
+            .unwrap_or_else(|e| Err((0, format!("Log task failed: {e}"))));
+        let mut resume = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        match result {
+            Ok(()) => {
+                *resume = None;
+                Ok(())
+            }
+            Err((sent, e)) => {
+                *resume = Some((datagrams, start + sent));
+                Err(e)
+            }
+        }
+    }
+}
+
+pub struct SyslogNotifier {
+    address: String,
+    facility: u8,
+    app_name: String,
+    resume: Resume,
+}
+
+impl SyslogNotifier {
+    pub fn new(settings: &SyslogConfig) -> Self {
+        SyslogNotifier {
+            address: settings.address.clone().unwrap_or_else(|| DEFAULT_ADDRESS.to_string()),
+            facility: settings.facility.as_deref().and_then(facility_code).unwrap_or(3),
+            app_name: settings.app_name.clone().unwrap_or_else(|| DEFAULT_APP_NAME.to_string()),
+            resume: Resume::default(),
+        }
+    }
+}
+
+fn send_datagrams(address: &str, datagrams: &[Vec<u8>]) -> Result<(), (usize, String)> {
+    match parse_address(address) {
+        Some(Address::Unix(path)) => send_unix(&path, datagrams),
+        Some(Address::Udp(target)) => {
+            let socket = std::net::UdpSocket::bind(if target.starts_with('[') { "[::]:0" } else { "0.0.0.0:0" })
+                .map_err(|e| (0, format!("Failed to open UDP socket: {e}")))?;
+            socket.set_write_timeout(Some(Duration::from_secs(5))).map_err(|e| (0, e.to_string()))?;
+            for (sent, datagram) in datagrams.iter().enumerate() {
+                socket.send_to(datagram, target.as_str())
+                    .map_err(|e| (sent, format!("Failed to send to {}: {e}", target)))?;
+            }
+            Ok(())
+        }
+        None => Err((0, format!("Invalid syslog address: {}", address))),
+    }
+}
+
+impl Notifier for SyslogNotifier {
+    fn send<'a>(&'a self, report: &'a Report<'a>) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let datagrams: Vec<Vec<u8>> = records(report).iter()
+                .map(|r| r.rfc5424(self.facility, report.time, &report.system_info.hostname, &self.app_name, report.kind.as_str()))
+                .map(String::into_bytes)
+                .collect();
+            let address = self.address.clone();
+            self.resume.send(datagrams, move |datagrams| send_datagrams(&address, datagrams)).await
+        })
+    }
+}
+
+pub struct JournaldNotifier {
+    app_name: String,
+    resume: Resume,
+}
+
+impl JournaldNotifier {
+    pub fn new(settings: &JournaldConfig) -> Self {
+        JournaldNotifier {
+            app_name: settings.app_name.clone().unwrap_or_else(|| DEFAULT_APP_NAME.to_string()),
+            resume: Resume::default(),
+        }
+    }
+}
+
+impl Notifier for JournaldNotifier {
+    fn send<'a>(&'a self, report: &'a Report<'a>) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let entries: Vec<Vec<u8>> = records(report).iter().map(|r| r.journal_entry(&self.app_name)).collect();
+            self.resume.send(entries, send_journal).await
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn send_journal(entries: &[Vec<u8>]) -> Result<(), (usize, String)> {
+    send_unix(JOURNAL_SOCKET, entries)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn send_journal(_entries: &[Vec<u8>]) -> Result<(), (usize, String)> {
+    Err((0, "journald is only available on Linux".to_string()))
+}
+
+#[cfg(unix)]
+fn send_unix(path: &str, datagrams: &[Vec<u8>]) -> Result<(), (usize, String)> {
+    use std::os::unix::net::UnixDatagram;
+
+    let socket = UnixDatagram::unbound().map_err(|e| (0, format!("Failed to open socket: {e}")))?;
+    socket.set_write_timeout(Some(Duration::from_secs(5))).map_err(|e| (0, e.to_string()))?;
+    for (sent, datagram) in datagrams.iter().enumerate() {
+        socket.send_to(datagram, path).map_err(|e| (sent, format!("Failed to send to {}: {e}", path)))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send_unix(path: &str, _datagrams: &[Vec<u8>]) -> Result<(), (usize, String)> {
+    Err((0, format!("Unix sockets are not available on this platform ({}); use udp://host:port", path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn record(message: &str) -> Record {
+        Record {
+            priority: 2,
+            message: message.to_string(),
+            fields: vec![("mount", "/srv".to_string()), ("serial", "WD-\"x\\y]".to_string())],
+        }
+    }
+
+    #[test]
+    fn rfc5424_header_and_structured_data() {
+        let line = record("CRITICAL /srv: Low disk space").rfc5424(3, 1_700_000_000, "nas box", "diskmon", "alert");
+        let (pri, rest) = line.split_once(' ').unwrap();
+        assert_eq!(pri, "<26>1");
+        let (timestamp, rest) = rest.split_once(' ').unwrap();
+        let parsed = DateTime::parse_from_rfc3339(timestamp).unwrap();
+        assert_eq!(parsed.timestamp(), 1_700_000_000);
+        assert!(!timestamp.contains('.'), "{}", timestamp);
+        assert_eq!(rest, format!(
+            "nasbox diskmon {} alert [diskmon@32473 mount=\"/srv\" serial=\"WD-\\\"x\\\\y\\]\"] CRITICAL /srv: Low disk space",
+            std::process::id()
+        ));
+    }
+
+    #[test]
+    fn rfc5424_without_valid_time_or_hostname() {
+        let line = record("x").rfc5424(16, i64::MAX, " ", "diskmon", "report");
+        assert!(line.starts_with("<130>1 - - diskmon "), "{}", line);
+    }
+
+    #[test]
+    fn journal_entry_fields() {
+        let entry = record("CRITICAL /srv: Low disk space").journal_entry("diskmon");
+        assert_eq!(
+            String::from_utf8(entry).unwrap(),
+            "MESSAGE=CRITICAL /srv: Low disk space\nPRIORITY=2\nSYSLOG_IDENTIFIER=diskmon\n\
+             DISKMON_MOUNT=/srv\nDISKMON_SERIAL=WD-\"x\\y]\n"
+        );
+    }
+
+    #[test]
+    fn journal_entry_multiline_value_uses_length_prefix() {
+        let entry = Record { priority: 5, message: "two\nlines".to_string(), fields: Vec::new() }.journal_entry("diskmon");
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&9u64.to_le_bytes());
+        expected.extend_from_slice(b"two\nlines\nPRIORITY=5\nSYSLOG_IDENTIFIER=diskmon\n");
+        assert_eq!(entry, expected);
+    }
+
+    #[test]
+    fn escaping_and_header_fields() {
+        assert_eq!(sd_escape("a\"b\\c]d[e"), "a\\\"b\\\\c\\]d[e");
+        assert_eq!(header_field("nas.example.com", 255), "nas.example.com");
+        assert_eq!(header_field("my nas\u{e9}", 255), "mynas");
+        assert_eq!(header_field("abcdef", 3), "abc");
+        assert_eq!(header_field("", 255), "-");
+    }
+
+    #[test]
+    fn addresses_facilities_and_app_names() {
+        assert!(matches!(parse_address(" /dev/log "), Some(Address::Unix(path)) if path == "/dev/log"));
+        assert!(matches!(parse_address("udp://logs.example.com:514"), Some(Address::Udp(target)) if target == "logs.example.com:514"));
+        assert!(matches!(parse_address("udp://[::1]:5514"), Some(Address::Udp(_))));
+        assert!(parse_address("udp://logs:syslog").is_none());
+        assert!(parse_address("udp://:514").is_none());
+        assert!(parse_address("tcp://logs:514").is_none());
+        assert!(parse_address("dev/log").is_none());
+
+        assert_eq!(facility_code("daemon"), Some(3));
+        assert_eq!(facility_code(" LOCAL7 "), Some(23));
+        assert_eq!(facility_code("local8"), None);
+
+        assert!(valid_app_name("diskmon"));
+        assert!(!valid_app_name("disk mon"));
+        assert!(!valid_app_name(""));
+        assert!(!valid_app_name(&"x".repeat(49)));
+    }
+
+    #[tokio::test]
+    async fn resume_skips_delivered_datagrams() {
+        let resume = Resume::default();
+        let datagrams: Vec<Vec<u8>> = vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()];
+        let sent = Arc::new(Mutex::new(Vec::new()));
+
+        // Fails after the first datagram
+        let log = Arc::clone(&sent);
+        let result = resume.send(datagrams.clone(), move |batch| {
+            log.lock().unwrap().push(batch[0].clone());
+            Err((1, "connection refused".to_string()))
+        }).await;
+        assert_eq!(result, Err("connection refused".to_string()));
+
+        // The retry starts at the datagram that failed
+        let log = Arc::clone(&sent);
+        resume.send(datagrams.clone(), move |batch| {
+            log.lock().unwrap().extend(batch.iter().cloned());
+            Ok(())
+        }).await.unwrap();
+        assert_eq!(*sent.lock().unwrap(), datagrams);
+
+        // Once delivered, the same datagrams are sent in full again
+        let log = Arc::clone(&sent);
+        resume.send(datagrams.clone(), move |batch| {
+            log.lock().unwrap().push(batch.concat());
+            Ok(())
+        }).await.unwrap();
+        assert_eq!(sent.lock().unwrap().last().unwrap(), b"abc");
+    }
+
+    #[tokio::test]
+    async fn resume_starts_over_for_other_datagrams() {
+        let resume = Resume::default();
+        let failed = resume.send(vec![b"a".to_vec(), b"b".to_vec()], |_| Err((1, "refused".to_string()))).await;
+        assert!(failed.is_err());
+        let result = resume.send(vec![b"x".to_vec(), b"y".to_vec()], |batch| {
+            if batch.len() == 2 { Ok(()) } else { Err((0, format!("got {} datagrams", batch.len()))) }
+        }).await;
+        assert_eq!(result, Ok(()));
+    }
+}
@@ -0,0 +1,201 @@
+// Webhook notifier: POSTs the `--json` document to a URL.
+//
+// The body is the JsonOutput of this run (system info, disks, alerts, status) with three extra
+// fields: `event` ("alert", "report" or "resolved"), `display_name` and `time`, plus `resolved`
+// listing the cleared conditions for resolved events. With a `secret`, the body is signed with
+// HMAC-SHA256 and the signature sent as "sha256=<hex>" in X-Diskmon-Signature (or
+// `signature_header`), so the receiver can check it came from us. Any 2xx response counts as
+// delivered; everything else is retried like the other notifiers.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use openssl::{hash::MessageDigest, pkey::PKey, sign::Signer};
+
+use super::{Notifier, Report};
+use crate::state::AlertRecord;
+use crate::units::HumanDuration;
+use crate::{JsonOutput, smartctl_available};
+
+const DEFAULT_SIGNATURE_HEADER: &str = "X-Diskmon-Signature";
+pub(super) const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct WebhookConfig {
+    pub url: String, // http:// or https:// endpoint
+    pub headers: Option<BTreeMap<String, String>>, // Extra request headers, e.g. Authorization
+    pub secret: Option<String>, // Shared secret for the HMAC-SHA256 signature (default: unsigned)
+    pub signature_header: Option<String>, // Header carrying the signature (default: X-Diskmon-Signature)
+    pub timeout: Option<HumanDuration>, // Connect and response timeout per attempt (default: 10s)
+}
+
+// Header names are tokens: visible ASCII without separators
+fn valid_header_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|b| b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b))
+}
+
+pub(super) fn valid_url(url: &str) -> bool {
+    let url = url.trim();
+    (url.starts_with("http://") || url.starts_with("https://")) && ureq::post(url).request_url().is_ok()
+}
+
+impl WebhookConfig {
+    pub fn validate(&self, name: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        if !valid_url(&self.url) {
+            errors.push(format!("{}.url (must be an http:// or https:// URL)", name));
+        }
+        for (header, value) in self.headers.iter().flatten() {
+            if !valid_header_name(header) || value.contains(['\r', '\n']) {
+                errors.push(format!("{}.headers.{} (invalid header)", name, header));
+            }
+        }
+        if self.secret.as_ref().is_some_and(|s| s.is_empty()) {
+            errors.push(format!("{}.secret (must not be empty)", name));
+        }
+        if self.signature_header.as_deref().is_some_and(|h| !valid_header_name(h)) {
+            errors.push(format!("{}.signature_header (invalid header name)", name));
+        }
+        if self.timeout.is_some_and(|t| t.0 == 0) {
+            errors.push(format!("{}.timeout (must be at least 1s)", name));
+        }
+        errors
+    }
+}
+
+#[derive(serde::Serialize)]
+struct Payload<'a> {
+    event: &'static str,
+    display_name: &'a str,
+    time: i64,
+    #[serde(flatten)]
+    output: JsonOutput<'a>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    resolved: &'a [AlertRecord],
+}
+
+/// HMAC-SHA256 of `body` as lowercase hex.
+fn sign(secret: &str, body: &[u8]) -> Result<String, String> {
+    let key = PKey::hmac(secret.as_bytes()).map_err(|e| format!("HMAC key error: {e}"))?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key).map_err(|e| format!("HMAC error: {e}"))?;
+    signer.update(body).map_err(|e| format!("HMAC error: {e}"))?;
+    let digest = signer.sign_to_vec().map_err(|e| format!("HMAC error: {e}"))?;
+    Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+pub struct WebhookNotifier {
+    settings: WebhookConfig,
+}
+
+impl WebhookNotifier {
+    pub fn new(settings: &WebhookConfig) -> Self {
+        WebhookNotifier { settings: settings.clone() }
+    }
+
+    fn body(report: &Report) -> Result<String, String> {
+        let payload = Payload {
+            event: report.kind.as_str(),
+            display_name: report.display_name,
+            time: report.time,
+            output: JsonOutput::new(report.cfg, report.system_info, report.disks, smartctl_available()),
+            resolved: report.resolved,
+        };
+        serde_json::to_string(&payload).map_err(|e| format!("Failed to serialize webhook payload: {e}"))
+    }
+}
+
+/// POST a JSON body to `url`. Any 2xx response counts as delivered.
+pub(super) async fn post_json(url: &str, headers: Vec<(String, String)>, body: String, timeout: Duration) -> Result<(), String> {
+    let tls = ureq::native_tls::TlsConnector::new().map_err(|e| format!("TLS setup failed: {e}"))?;
+    let agent = ureq::AgentBuilder::new()
+        .timeout(timeout)
+        .tls_connector(Arc::new(tls))
+        .user_agent(concat!("diskmon-mail/", env!("CARGO_PKG_VERSION")))
+        .build();
+    let mut request = agent.post(url.trim()).set("Content-Type", "application/json");
+    for (header, value) in &headers {
+        request = request.set(header, value);
+    }
+    // ureq blocks; keep it off the runtime so other notifiers proceed
+    let result = tokio::task::spawn_blocking(move || match request.send_string(&body) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(code, response)) => Err(describe_status(code, response)),
+        Err(e) => Err(e.to_string()),
+    }).await;
+    result.map_err(|e| format!("HTTP task failed: {e}"))?
+}
+
+// Keep the start of an error response for the log; gateways tend to explain rejections there
+fn describe_status(code: u16, response: ureq::Response) -> String {
+    let status = response.status_text().to_string();
+    let body = response.into_string().unwrap_or_default();
+    let body: String = body.trim().chars().take(200).collect();
+    if body.is_empty() {
+        format!("HTTP {} {}", code, status)
+    } else {
+        format!("HTTP {} {}: {}", code, status, body)
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn send<'a>(&'a self, report: &'a Report<'a>) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let body = Self::body(report)?;
+            let mut headers = vec![("X-Diskmon-Event".to_string(), report.kind.as_str().to_string())];
+            headers.extend(self.settings.headers.iter().flatten().map(|(h, v)| (h.clone(), v.clone())));
+            if let Some(secret) = &self.settings.secret {
+                let header = self.settings.signature_header.as_deref().unwrap_or(DEFAULT_SIGNATURE_HEADER);
+                headers.push((header.to_string(), format!("sha256={}", sign(secret, body.as_bytes())?)));
+            }
+            let timeout = self.settings.timeout.map_or(DEFAULT_TIMEOUT, |t| t.as_duration());
+            post_json(&self.settings.url, headers, body, timeout).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_matches_known_hmac_sha256() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?").unwrap(),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            sign("key", b"The quick brown fox jumps over the lazy dog").unwrap(),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn header_names_are_tokens() {
+        assert!(valid_header_name("X-Diskmon-Signature"));
+        assert!(valid_header_name("Authorization"));
+        assert!(!valid_header_name(""));
+        assert!(!valid_header_name("X Signature"));
+        assert!(!valid_header_name("X-Signature:"));
+    }
+
+    #[test]
+    fn validate_reports_each_bad_setting() {
+        let config: WebhookConfig = serde_yaml::from_str(
+            "url: ftp://example.com/hook\nheaders: {\"Bad Header\": x, X-Ok: \"a\\r\\nb\"}\nsecret: \"\"\nsignature_header: \"a:b\"\ntimeout: 0\n",
+        ).unwrap();
+        assert_eq!(config.validate("webhook"), vec![
+            "webhook.url (must be an http:// or https:// URL)",
+            "webhook.headers.Bad Header (invalid header)",
+            "webhook.headers.X-Ok (invalid header)",
+            "webhook.secret (must not be empty)",
+            "webhook.signature_header (invalid header name)",
+            "webhook.timeout (must be at least 1s)",
+        ]);
+
+        let config: WebhookConfig = serde_yaml::from_str("url: https://example.com/hook\nsecret: s3cret\n").unwrap();
+        assert!(config.validate("webhook").is_empty());
+    }
+}
@@ -0,0 +1,436 @@
+// Per-filesystem threshold rules.
+//
+// `rules:` in config.yaml is an ordered list. The first rule whose selectors all match a
+// filesystem supplies its thresholds; filesystems that match no rule use the global
+// threshold_percent and min_free. A tier can combine a percentage with an absolute size
+// (`min_free: 20GiB`) and fire when either or both are crossed. An active alert only clears once
+// free space is clear_margin percentage points above the tier that fired it.
+//
+// A rule replaces a tier as a whole: setting warning_percent or warning_min_free drops the global
+// warning size (a 100 GiB volume shouldn't inherit a global min_free of 500GiB), and overriding
+// the warning tier without a critical one drops the global critical tier, which could otherwise
+// fire before the rule's own warning.
+
+use glob::Pattern;
+
+use crate::alerts::Severity;
+use crate::system::FilesystemInfo;
+use crate::units::{ByteSize, HumanDuration};
+
+/// How a tier combines its percentage and absolute thresholds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Require {
+    /// Fire when either threshold is crossed
+    #[default]
+    Either,
+    /// Fire only when both thresholds are crossed (e.g. under 10% AND under 500GiB)
+    Both,
+}
+
+/// One entry of the `rules:` list.
+#[derive(serde::Deserialize, Debug, Clone, Default)]
+pub struct Rule {
+    pub name: Option<String>,
+    pub mount_point: Option<String>, // Glob, e.g. "/var/lib/postgresql*"
+    pub device: Option<String>, // Glob against the device path or its kernel name, e.g. "/dev/md*" or "nvme*"
+    pub fstype: Option<String>, // Glob, e.g. "xfs" or "ext*"
+    pub label: Option<String>, // Glob against the filesystem label
+    pub warning_percent: Option<f64>, // Alert below this free percentage (default: threshold_percent)
+    pub critical_percent: Option<f64>, // Alert as critical below this free percentage (default: critical_threshold_percent, unless the rule sets a warning threshold)
+    #[serde(alias = "min_free")]
+    pub warning_min_free: Option<ByteSize>, // Alert below this much available space (default: min_free, unless the rule sets warning_percent)
+    pub critical_min_free: Option<ByteSize>, // Alert as critical below this much available space (default: critical_min_free, unless the rule sets a warning threshold)
+    pub require: Option<Require>, // Combine percentage and size thresholds with "either" or "both" (default: threshold_require)
+    pub clear_margin: Option<f64>, // Percentage points above a threshold before an active alert clears (default: clear_margin)
+    pub alert_if_full_within: Option<HumanDuration>, // Alert when the forecast says full within this long (default: alert_if_full_within)
+    pub critical_if_full_within: Option<HumanDuration>, // Report as critical when full within this long (default: critical_if_full_within)
+}
+
+impl Rule {
+    /// Name used in reports: the configured name or the rule's position in the list.
+    pub fn display_name(&self, index: usize) -> String {
+        self.name.clone().unwrap_or_else(|| format!("rules[{}]", index))
+    }
+
+    fn selectors(&self) -> [(&'static str, Option<&String>); 4] {
+        [
+            ("mount_point", self.mount_point.as_ref()),
+            ("device", self.device.as_ref()),
+            ("fstype", self.fstype.as_ref()),
+            ("label", self.label.as_ref()),
+        ]
+    }
+
+    /// A rule matches when every selector it sets matches the filesystem.
+    pub fn matches(&self, fs: &FilesystemInfo) -> bool {
+        let glob = |pattern: &str, value: &str| Pattern::new(pattern).is_ok_and(|p| p.matches(value));
+        let device_short = fs.device.rsplit('/').next().unwrap_or(&fs.device);
+        self.mount_point.as_deref().is_none_or(|p| glob(p, &fs.mount_point))
+            && self.device.as_deref().is_none_or(|p| glob(p, &fs.device) || glob(p, device_short))
+            && self.fstype.as_deref().is_none_or(|p| glob(p, &fs.fs_type))
+            && self.label.as_deref().is_none_or(|p| fs.label.as_deref().is_some_and(|l| glob(p, l)))
+    }
+
+    /// Thresholds this rule puts into effect, with what it leaves unset taken from `defaults`.
+    pub fn thresholds(&self, index: usize, defaults: &Thresholds) -> Thresholds {
+        let sets_warning = self.warning_percent.is_some() || self.warning_min_free.is_some();
+        let sets_critical = self.critical_percent.is_some() || self.critical_min_free.is_some();
+        let (critical_percent, critical_min_free) = if sets_critical {
+            (self.critical_percent, self.critical_min_free)
+        } else if sets_warning {
+            (None, None)
+        } else {
+            (defaults.critical_percent, defaults.critical_min_free)
+        };
+        Thresholds {
+            rule: self.display_name(index),
+            warning_percent: self.warning_percent.unwrap_or(defaults.warning_percent),
+            critical_percent,
+            warning_min_free: if sets_warning { self.warning_min_free } else { defaults.warning_min_free },
+            critical_min_free,
+            require: self.require.unwrap_or(defaults.require),
+            clear_margin: self.clear_margin.unwrap_or(defaults.clear_margin),
+            alert_if_full_within: self.alert_if_full_within.or(defaults.alert_if_full_within),
+            critical_if_full_within: self.critical_if_full_within.or(defaults.critical_if_full_within),
+        }
+    }
+
+    /// Configuration problems with this rule, in the same style as validate_config. Warning and
+    /// critical pairs are checked as they resolve against `defaults`, so a rule that only sets one
+    /// side is compared with the global value it inherits.
+    pub fn validate(&self, index: usize, defaults: &Thresholds) -> Vec<String> {
+        let name = self.display_name(index);
+        let mut errors = Vec::new();
+        if self.selectors().iter().all(|(_, v)| v.is_none()) {
+            errors.push(format!("{} (must set at least one of mount_point, device, fstype, label)", name));
+        }
+        for (key, value) in self.selectors() {
+            if let Some(pattern) = value
+                && let Err(e) = Pattern::new(pattern)
+            {
+                errors.push(format!("{}.{} (invalid glob '{}': {})", name, key, pattern, e));
+            }
+        }
+        for (key, value) in [("warning_percent", self.warning_percent), ("critical_percent", self.critical_percent)] {
+            if value.is_some_and(|v| !(1.0..=100.0).contains(&v)) {
+                errors.push(format!("{}.{} (must be between 1.0 and 100.0)", name, key));
+            }
+        }
+        let resolved = self.thresholds(index, defaults);
+        if let Some(critical) = resolved.critical_percent
+            && critical > resolved.warning_percent
+            && (self.warning_percent.is_some() || self.critical_percent.is_some())
+        {
+            errors.push(format!("{}.critical_percent (critical {}% must not be above warning {}%)",
+                                name, critical, resolved.warning_percent));
+        }
+        if self.clear_margin.is_some_and(|m| !(0.0..=100.0).contains(&m)) {
+            errors.push(format!("{}.clear_margin (must be between 0.0 and 100.0)", name));
+        }
+        if let (Some(warning), Some(critical)) = (resolved.warning_min_free, resolved.critical_min_free)
+            && critical > warning
+            && (self.warning_min_free.is_some() || self.critical_min_free.is_some())
+        {
+            errors.push(format!("{}.critical_min_free (critical {} must not be above warning {})", name, critical, warning));
+        }
+        if let (Some(warning), Some(critical)) = (resolved.alert_if_full_within, resolved.critical_if_full_within)
+            && critical > warning
+            && (self.alert_if_full_within.is_some() || self.critical_if_full_within.is_some())
+        {
+            errors.push(format!("{}.critical_if_full_within (critical {} must not be above warning {})", name, critical, warning));
+        }
+        errors
+    }
+}
+
+/// Thresholds in effect for one filesystem and the rule they came from.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Thresholds {
+    pub rule: String, // "default" when no rule matched
+    pub warning_percent: f64,
+    pub critical_percent: Option<f64>,
+    pub warning_min_free: Option<ByteSize>,
+    pub critical_min_free: Option<ByteSize>,
+    pub require: Require,
+    pub clear_margin: f64,
+    pub alert_if_full_within: Option<HumanDuration>,
+    pub critical_if_full_within: Option<HumanDuration>,
+}
+
+impl Thresholds {
+    /// The global thresholds from config.yaml, used by filesystems that match no rule.
+    pub fn defaults(cfg: &crate::config::Config) -> Self {
+        Thresholds {
+            rule: "default".to_string(),
+            warning_percent: cfg.threshold_percent.unwrap_or(10.0),
+            critical_percent: cfg.critical_threshold_percent,
+            warning_min_free: cfg.min_free,
+            critical_min_free: cfg.critical_min_free,
+            require: cfg.threshold_require.unwrap_or_default(),
+            clear_margin: cfg.clear_margin.unwrap_or(0.0),
+            alert_if_full_within: cfg.alert_if_full_within,
+            critical_if_full_within: cfg.critical_if_full_within,
+        }
+    }
+
+    // Whether a tier is crossed. Unset thresholds don't take part; a tier with nothing set never fires.
+    fn crossed(&self, percent: Option<f64>, min_free: Option<ByteSize>, free_percent: f64, available: u64) -> bool {
+        let checks: Vec<bool> = [
+            percent.map(|p| free_percent < p),
+            min_free.map(|m| available < m.0),
+        ].into_iter().flatten().collect();
+        match self.require {
+            Require::Either => checks.iter().any(|c| *c),
+            Require::Both => !checks.is_empty() && checks.iter().all(|c| *c),
+        }
+    }
+
+    pub fn space_severity(&self, free_percent: f64, available: u64) -> Option<Severity> {
+        if self.crossed(self.critical_percent, self.critical_min_free, free_percent, available) {
+            Some(Severity::Critical)
+        } else if self.crossed(Some(self.warning_percent), self.warning_min_free, free_percent, available) {
+            Some(Severity::Warning)
+        } else {
+            None
+        }
+    }
+
+    /// Free bytes below which a tier is crossed, None when the tier has no threshold set.
+    pub fn limit_bytes(&self, severity: Severity, total: u64) -> Option<u64> {
+        let (percent, min_free) = match severity {
+            Severity::Critical => (self.critical_percent, self.critical_min_free),
+            Severity::Warning => (Some(self.warning_percent), self.warning_min_free),
+        };
+        let percent_bytes = percent.map(|p| (total as f64 * p / 100.0) as u64);
+        match (percent_bytes, min_free.map(|m| m.0)) {
+            (Some(by_percent), Some(by_size)) => Some(match self.require {
+                Require::Either => by_percent.max(by_size),
+                Require::Both => by_percent.min(by_size),
+            }),
+            (by_percent, by_size) => by_percent.or(by_size),
+        }
+    }
+
+    /// Severity for a filesystem that already has an active low-space alert: each tier stays crossed
+    /// until free space is clear_margin above it. Size thresholds clear at the same margin, taken
+    /// as a percentage of the filesystem size.
+    pub fn held_space_severity(&self, free_percent: f64, available: u64, total: u64) -> Option<Severity> {
+        let margin_bytes = (total as f64 * self.clear_margin / 100.0) as u64;
+        self.space_severity(free_percent - self.clear_margin, available.saturating_sub(margin_bytes))
+    }
+
+    fn describe_tier(&self, percent: Option<f64>, min_free: Option<ByteSize>) -> String {
+        let joiner = match self.require {
+            Require::Either => " or ",
+            Require::Both => " and ",
+        };
+        let parts: Vec<String> = [percent.map(|p| format!("{}%", p)), min_free.map(|m| m.to_string())]
+            .into_iter()
+            .flatten()
+            .collect();
+        parts.join(joiner)
+    }
+
+    /// Human-readable reason for a low-space alert, naming the rule that triggered it.
+    pub fn space_reason(&self, severity: Severity, free_percent: f64, available: u64) -> String {
+        let limit = match severity {
+            Severity::Critical => self.describe_tier(self.critical_percent, self.critical_min_free),
+            Severity::Warning => self.describe_tier(Some(self.warning_percent), self.warning_min_free),
+        };
+        let clears = if self.clear_margin > 0.0 {
+            format!(", clears {}% above", self.clear_margin)
+        } else {
+            String::new()
+        };
+        format!("low space ({:.2}% / {} free, {} below {}{}, rule '{}')",
+                free_percent, ByteSize(available), severity.as_str(), limit, clears, self.rule)
+    }
+
+    pub fn describe(&self) -> String {
+        let warning = self.describe_tier(Some(self.warning_percent), self.warning_min_free);
+        let critical = self.describe_tier(self.critical_percent, self.critical_min_free);
+        if critical.is_empty() {
+            format!("{} (warning < {})", self.rule, warning)
+        } else {
+            format!("{} (warning < {}, critical < {})", self.rule, warning, critical)
+        }
+    }
+}
+
+/// Pick the thresholds for a filesystem: the first matching rule wins, and what it leaves unset
+/// comes from the global defaults (see Rule::thresholds).
+pub fn resolve(rules: &[Rule], defaults: &Thresholds, fs: &FilesystemInfo) -> Thresholds {
+    match rules.iter().enumerate().find(|(_, rule)| rule.matches(fs)) {
+        Some((index, rule)) => rule.thresholds(index, defaults),
+        None => defaults.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const DEFAULTS: &str = "threshold_percent: 10\ncritical_threshold_percent: 5\nmin_free: 20GiB\ncritical_min_free: 5GiB\nclear_margin: 1";
+
+    fn rules(yaml: &str) -> Vec<Rule> {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn fs(mount_point: &str, device: &str) -> FilesystemInfo {
+        testing::filesystem(mount_point, device, 1 << 40, 1 << 39)
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = rules(r#"
+- name: postgres
+  mount_point: "/var/lib/postgresql*"
+  warning_percent: 20
+- name: var
+  mount_point: "/var*"
+  warning_percent: 15
+- device: "md*"
+  warning_percent: 30
+"#);
+        let defaults = testing::thresholds(DEFAULTS);
+        let resolve = |mount_point, device| resolve(&rules, &defaults, &fs(mount_point, device));
+        assert_eq!(resolve("/var/lib/postgresql/16", "/dev/sdb1").rule, "postgres");
+        assert_eq!(resolve("/var/log", "/dev/sdb1").rule, "var");
+        assert_eq!(resolve("/var/log", "/dev/md0").rule, "var");
+        assert_eq!(resolve("/srv", "/dev/md0").rule, "rules[2]");
+
+        let fallback = resolve("/home", "/dev/sda2");
+        assert_eq!(fallback.rule, "default");
+        assert_eq!((fallback.warning_percent, fallback.critical_percent), (10.0, Some(5.0)));
+        assert_eq!((fallback.warning_min_free, fallback.critical_min_free), (Some(ByteSize(20 << 30)), Some(ByteSize(5 << 30))));
+    }
+
+    #[test]
+    fn every_selector_must_match() {
+        let rule = &rules("- fstype: \"ext*\"\n  label: backup*\n  warning_percent: 20")[0];
+        let mut backup = fs("/backup", "/dev/sdc1");
+        assert!(!rule.matches(&backup));
+        backup.label = Some("backup-2024".to_string());
+        assert!(rule.matches(&backup));
+        backup.fs_type = "xfs".to_string();
+        assert!(!rule.matches(&backup));
+        // Devices match by path or kernel name
+        let nvme = &rules("- device: nvme*\n  warning_percent: 20")[0];
+        assert!(nvme.matches(&fs("/", "/dev/nvme0n1p2")));
+        assert!(!nvme.matches(&fs("/", "/dev/sda1")));
+    }
+
+    #[test]
+    fn rule_replaces_whole_tiers() {
+        let defaults = testing::thresholds(DEFAULTS);
+        let thresholds = |yaml: &str| rules(yaml)[0].thresholds(0, &defaults);
+
+        // Setting a warning threshold drops the inherited size threshold and the critical tier
+        let warning = thresholds("- mount_point: /srv\n  warning_percent: 20");
+        assert_eq!((warning.warning_percent, warning.warning_min_free), (20.0, None));
+        assert_eq!((warning.critical_percent, warning.critical_min_free), (None, None));
+
+        let size = thresholds("- mount_point: /srv\n  min_free: 100GiB");
+        assert_eq!((size.warning_percent, size.warning_min_free), (10.0, Some(ByteSize(100 << 30))));
+        assert_eq!(size.critical_percent, None);
+
+        // Setting only the critical tier keeps the inherited warning tier
+        let critical = thresholds("- mount_point: /srv\n  critical_percent: 2");
+        assert_eq!((critical.warning_percent, critical.warning_min_free), (10.0, Some(ByteSize(20 << 30))));
+        assert_eq!((critical.critical_percent, critical.critical_min_free), (Some(2.0), None));
+
+        // A rule without thresholds only renames the defaults and can change other settings
+        let other = thresholds("- name: quiet\n  mount_point: /srv\n  clear_margin: 3\n  require: both");
+        assert_eq!((other.rule.as_str(), other.warning_percent, other.critical_percent), ("quiet", 10.0, Some(5.0)));
+        assert_eq!((other.clear_margin, other.require), (3.0, Require::Both));
+    }
+
+    #[test]
+    fn validate_resolved_pairs() {
+        let defaults = testing::thresholds(DEFAULTS);
+        let errors = |yaml: &str| rules(yaml)[0].validate(0, &defaults);
+
+        assert!(errors("- mount_point: /srv\n  warning_percent: 20\n  critical_percent: 10").is_empty());
+        assert_eq!(errors("- name: srv\n  mount_point: /srv\n  warning_percent: 10\n  critical_percent: 20"),
+                   vec!["srv.critical_percent (critical 20% must not be above warning 10%)"]);
+        // Compared with the inherited warning threshold
+        assert_eq!(errors("- mount_point: /srv\n  critical_percent: 15"),
+                   vec!["rules[0].critical_percent (critical 15% must not be above warning 10%)"]);
+        assert_eq!(errors("- mount_point: /srv\n  critical_min_free: 50GiB"),
+                   vec!["rules[0].critical_min_free (critical 50.00 GiB must not be above warning 20.00 GiB)"]);
+        assert_eq!(errors("- mount_point: /srv\n  alert_if_full_within: 1d\n  critical_if_full_within: 2d"),
+                   vec!["rules[0].critical_if_full_within (critical 2d must not be above warning 1d)"]);
+        // A rule that sets no thresholds isn't blamed for the global ones
+        let global = testing::thresholds("threshold_percent: 5\ncritical_threshold_percent: 10");
+        assert!(rules("- mount_point: /srv\n  clear_margin: 2")[0].validate(0, &global).is_empty());
+    }
+
+    #[test]
+    fn validate_selectors_and_ranges() {
+        let defaults = testing::thresholds(DEFAULTS);
+        assert_eq!(rules("- warning_percent: 20")[0].validate(0, &defaults),
+                   vec!["rules[0] (must set at least one of mount_point, device, fstype, label)"]);
+        let errors = rules("- mount_point: \"/srv[\"\n  warning_percent: 150\n  clear_margin: -1")[0].validate(0, &defaults);
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert!(errors[0].starts_with("rules[0].mount_point (invalid glob '/srv['"));
+        assert_eq!(errors[1], "rules[0].warning_percent (must be between 1.0 and 100.0)");
+        assert_eq!(errors[2], "rules[0].clear_margin (must be between 0.0 and 100.0)");
+    }
+
+    // 1000 byte filesystem: 10% is 100 bytes, 5% is 50
+    fn space(yaml: &str, available: u64) -> Option<Severity> {
+        testing::thresholds(yaml).space_severity(available as f64 / 10.0, available)
+    }
+
+    #[test]
+    fn either_fires_when_any_threshold_is_crossed() {
+        let yaml = "threshold_percent: 10\nmin_free: 150\ncritical_threshold_percent: 5\ncritical_min_free: 20";
+        assert_eq!(space(yaml, 150), None);
+        assert_eq!(space(yaml, 149), Some(Severity::Warning)); // Size only
+        assert_eq!(space(yaml, 99), Some(Severity::Warning));
+        assert_eq!(space(yaml, 50), Some(Severity::Warning));
+        assert_eq!(space(yaml, 49), Some(Severity::Critical)); // Percent only
+        assert_eq!(space(yaml, 19), Some(Severity::Critical));
+    }
+
+    #[test]
+    fn both_fires_only_when_every_threshold_is_crossed() {
+        let yaml = "threshold_percent: 10\nmin_free: 150\ncritical_threshold_percent: 5\ncritical_min_free: 20\nthreshold_require: both";
+        assert_eq!(space(yaml, 149), None); // Size only
+        assert_eq!(space(yaml, 100), None);
+        assert_eq!(space(yaml, 99), Some(Severity::Warning));
+        assert_eq!(space(yaml, 49), Some(Severity::Warning)); // Critical percent only
+        assert_eq!(space(yaml, 20), Some(Severity::Warning));
+        assert_eq!(space(yaml, 19), Some(Severity::Critical));
+    }
+
+    #[test]
+    fn unset_thresholds_do_not_take_part() {
+        // "both" with a single threshold set behaves like that threshold alone
+        let yaml = "threshold_percent: 10\ncritical_min_free: 20\nthreshold_require: both";
+        assert_eq!(space(yaml, 100), None);
+        assert_eq!(space(yaml, 99), Some(Severity::Warning));
+        assert_eq!(space(yaml, 19), Some(Severity::Critical));
+        // A critical tier with nothing set never fires
+        assert_eq!(space("threshold_percent: 10", 0), Some(Severity::Warning));
+    }
+
+    #[test]
+    fn limit_bytes_follows_require() {
+        let either = testing::thresholds("threshold_percent: 10\nmin_free: 150\ncritical_threshold_percent: 5\ncritical_min_free: 20");
+        assert_eq!(either.limit_bytes(Severity::Warning, 1_000), Some(150));
+        assert_eq!(either.limit_bytes(Severity::Critical, 1_000), Some(50));
+
+        let both = Thresholds { require: Require::Both, ..either };
+        assert_eq!(both.limit_bytes(Severity::Warning, 1_000), Some(100));
+        assert_eq!(both.limit_bytes(Severity::Critical, 1_000), Some(20));
+
+        let percent_only = testing::thresholds("threshold_percent: 10\nthreshold_require: both");
+        assert_eq!(percent_only.limit_bytes(Severity::Warning, 1_000), Some(100));
+        assert_eq!(percent_only.limit_bytes(Severity::Critical, 1_000), None);
+        let size_only = testing::thresholds("critical_min_free: 20");
+        assert_eq!(size_only.limit_bytes(Severity::Critical, 1_000), Some(20));
+    }
+}
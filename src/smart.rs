@@ -0,0 +1,285 @@
+use std::fmt;
+
+/// Overall health verdict for a physical disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HealthStatus {
+    Ok,
+    Warning,
+    Failing,
+    #[default]
+    Unknown,
+}
+
+impl HealthStatus {
+    pub const ALL: [HealthStatus; 4] = [HealthStatus::Ok, HealthStatus::Warning, HealthStatus::Failing, HealthStatus::Unknown];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthStatus::Ok => "OK",
+            HealthStatus::Warning => "WARNING",
+            HealthStatus::Failing => "FAILING",
+            HealthStatus::Unknown => "UNKNOWN",
+        }
+    }
+
+    /// Map a free-form status label (as reported by WMI or older tooling) to a status.
+    pub fn from_label(label: &str) -> Self {
+        match label.trim().to_uppercase().as_str() {
+            "OK" | "PASSED" | "HEALTHY" => HealthStatus::Ok,
+            "FAILING" | "FAILED" | "PRED FAIL" | "UNHEALTHY" => HealthStatus::Failing,
+            "" | "N/A" | "UNKNOWN" => HealthStatus::Unknown,
+            _ => HealthStatus::Warning,
+        }
+    }
+
+    /// Ordering used to pick the worst of several reports (Unknown < Ok < Warning < Failing).
+    pub fn severity(&self) -> u8 {
+        match self {
+            HealthStatus::Unknown => 0,
+            HealthStatus::Ok => 1,
+            HealthStatus::Warning => 2,
+            HealthStatus::Failing => 3,
+        }
+    }
+
+    /// True when the disk reported a degraded or failing state.
+    pub fn is_problem(&self) -> bool {
+        matches!(self, HealthStatus::Warning | HealthStatus::Failing)
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        *self == HealthStatus::Unknown
+    }
+}
+
+impl fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How the health information for a disk was obtained.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize)]
+pub enum HealthMethod {
+    #[serde(rename = "smartmontools")]
+    Smartmontools,
+    #[serde(rename = "WMI")]
+    Wmi,
+    #[serde(rename = "kernel")]
+    Kernel,
+    #[serde(rename = "disabled")]
+    Disabled,
+    #[serde(rename = "timeout")]
+    Timeout,
+    #[serde(rename = "error")]
+    Error,
+    #[default]
+    #[serde(rename = "unknown")]
+    Unknown,
+}
+
+impl HealthMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthMethod::Smartmontools => "smartmontools",
+            HealthMethod::Wmi => "WMI",
+            HealthMethod::Kernel => "kernel",
+            HealthMethod::Disabled => "disabled",
+            HealthMethod::Timeout => "timeout",
+            HealthMethod::Error => "error",
+            HealthMethod::Unknown => "unknown",
+        }
+    }
+
+    /// Methods that read real SMART data rather than guessing from kernel counters.
+    pub fn is_authoritative(&self) -> bool {
+        matches!(self, HealthMethod::Smartmontools | HealthMethod::Wmi)
+    }
+}
+
+impl fmt::Display for HealthMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One row of the ATA SMART attribute table.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct SmartAttribute {
+    pub id: u8,
+    pub name: String,
+    pub flags: String,
+    pub value: u64,
+    pub worst: u64,
+    pub thresh: u64,
+    pub raw_value: u64,
+    pub raw_string: String,
+    pub when_failed: String, // "", "now" or "past" as reported by smartctl
+    pub failing: bool,
+}
+
+impl SmartAttribute {
+    /// An attribute fails when its normalized value is at or below the vendor threshold.
+    /// A threshold of 0 means the attribute is informational and can never fail.
+    pub fn is_below_threshold(value: u64, thresh: u64) -> bool {
+        thresh > 0 && value <= thresh
+    }
+}
+
+/// NVMe SMART/Health Information log (log page 0x02).
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct NvmeHealth {
+    pub critical_warning: u8,
+    pub available_spare: u8,
+    pub available_spare_threshold: u8,
+    pub percentage_used: u8,
+    pub data_units_read: u64,
+    pub data_units_written: u64,
+    pub media_errors: u64,
+    pub unsafe_shutdowns: u64,
+    pub warning_temp_time: u64,
+    pub critical_comp_time: u64,
+}
+
+impl NvmeHealth {
+    /// Decode the critical_warning bit field (NVMe base spec, SMART/Health log byte 0).
+    pub fn critical_warnings(&self) -> Vec<&'static str> {
+        const BITS: [&str; 6] = [
+            "available spare below threshold",
+            "temperature outside limits",
+            "reliability degraded",
+            "media placed in read-only mode",
+            "volatile memory backup failed",
+            "persistent memory region read-only",
+        ];
+        BITS.iter()
+            .enumerate()
+            .filter(|(bit, _)| self.critical_warning & (1 << bit) != 0)
+            .map(|(_, desc)| *desc)
+            .collect()
+    }
+
+    pub fn spare_below_threshold(&self) -> bool {
+        self.available_spare_threshold > 0 && self.available_spare < self.available_spare_threshold
+    }
+}
+
+/// Error counters remembered per drive serial so that increases between runs can be reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Counter {
+    ReallocatedSectors,
+    PendingSectors,
+    UncorrectableSectors,
+    CrcErrors,
+    MediaErrors,
+}
+
+impl Counter {
+    pub const ALL: [Counter; 5] = [
+        Counter::ReallocatedSectors,
+        Counter::PendingSectors,
+        Counter::UncorrectableSectors,
+        Counter::CrcErrors,
+        Counter::MediaErrors,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Counter::ReallocatedSectors => "reallocated_sectors",
+            Counter::PendingSectors => "pending_sectors",
+            Counter::UncorrectableSectors => "uncorrectable_sectors",
+            Counter::CrcErrors => "crc_errors",
+            Counter::MediaErrors => "media_errors",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Counter::ReallocatedSectors => "reallocated sectors",
+            Counter::PendingSectors => "pending sectors",
+            Counter::UncorrectableSectors => "uncorrectable sectors",
+            Counter::CrcErrors => "CRC errors",
+            Counter::MediaErrors => "media errors",
+        }
+    }
+}
+
+/// A tracked counter's value on the previous run and now.
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct CounterChange {
+    pub counter: Counter,
+    pub previous: u64,
+    pub current: u64,
+}
+
+impl CounterChange {
+    pub fn increase(&self) -> u64 {
+        self.current.saturating_sub(self.previous)
+    }
+}
+
+impl fmt::Display for CounterChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.current == self.previous {
+            write!(f, "{}", self.current)
+        } else {
+            write!(f, "{} \u{2192} {}", self.previous, self.current)
+        }
+    }
+}
+
+/// Health and identity information collected for the physical disk behind a filesystem.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct SmartReport {
+    pub health: HealthStatus,
+    pub method: HealthMethod,
+    pub serial_number: Option<String>,
+    pub brand: Option<String>,
+    pub model: Option<String>,
+    pub is_raid: bool,
+    pub power_on_hours: Option<u64>,
+    pub reallocated_sectors: Option<u64>,
+    pub temperature: Option<i64>,
+    pub pending_sectors: Option<u64>,
+    pub uncorrectable_sectors: Option<u64>,
+    pub smart_attributes: Vec<SmartAttribute>,
+    pub nvme: Option<NvmeHealth>,
+}
+
+impl SmartReport {
+    /// An empty report recording only how (or why not) the disk was probed.
+    pub fn unavailable(method: HealthMethod) -> Self {
+        SmartReport {
+            method,
+            ..Default::default()
+        }
+    }
+
+    pub fn attribute(&self, id: u8) -> Option<&SmartAttribute> {
+        self.smart_attributes.iter().find(|a| a.id == id)
+    }
+
+    pub fn failing_attributes(&self) -> impl Iterator<Item = &SmartAttribute> {
+        self.smart_attributes.iter().filter(|a| a.failing)
+    }
+
+    /// Attributes that are not failing yet but whose normalized value is within `margin` points
+    /// of the vendor threshold. Informational attributes (threshold 0) are never included.
+    pub fn degrading_attributes(&self, margin: u64) -> impl Iterator<Item = &SmartAttribute> {
+        self.smart_attributes.iter().filter(move |a| {
+            !a.failing && margin > 0 && a.thresh > 0 && a.value <= a.thresh + margin
+        })
+    }
+
+    pub fn counter(&self, counter: Counter) -> Option<u64> {
+        match counter {
+            Counter::ReallocatedSectors => self.reallocated_sectors,
+            Counter::PendingSectors => self.pending_sectors,
+            Counter::UncorrectableSectors => self.uncorrectable_sectors,
+            Counter::CrcErrors => self.attribute(199).map(|a| a.raw_value), // UDMA_CRC_Error_Count
+            Counter::MediaErrors => self.nvme.as_ref().map(|n| n.media_errors),
+        }
+    }
+}
@@ -0,0 +1,479 @@
+// Alert state between runs.
+//
+// The state file records which (disk, condition) pairs were already notified, at which severity
+// and when. A run only mails alerts that are new, got worse or are due for a reminder after
+// renotify_interval, and reports conditions that have cleared since the last run as resolved.
+// Alerts still waiting for alert_after_checks consecutive runs are recorded but never notified.
+// Runs that don't notify (--json, --nagios, --format prometheus, --smart, --force-mail) still
+// record their alerts, so consecutive checks and hysteresis work in every mode; alerts that clear
+// on such a run are kept until a notifying run reports them as resolved.
+// SMART error counters are kept per drive serial so that increases between runs stand out.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::DiskInfo;
+use crate::alerts::{Condition, Severity};
+use crate::smart::{Counter, CounterChange, SmartReport};
+
+#[cfg(windows)]
+pub const DEFAULT_STATE_PATH: &str = "C:\\ProgramData\\diskmon\\state.json";
+#[cfg(not(windows))]
+pub const DEFAULT_STATE_PATH: &str = "/var/lib/diskmon/state.json";
+
+const STATE_VERSION: u32 = 1;
+
+/// One active alert as remembered in the state file. Timestamps are Unix seconds.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AlertRecord {
+    pub mount_point: String,
+    pub display_name: String,
+    pub condition: Condition,
+    pub severity: Severity, // Severity on the latest run
+    pub message: String,
+    pub first_seen: i64,
+    pub last_seen: i64,
+    pub notified_severity: Option<Severity>, // None until a notification went out
+    pub last_notified: Option<i64>,
+    #[serde(default)]
+    pub checks: u32, // Consecutive runs the condition has held
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cleared: Option<i64>, // When a notified alert cleared; reported as resolved by the next notifying run
+}
+
+/// SMART error counters of one drive as of the last completed run.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CounterRecord {
+    pub counters: BTreeMap<Counter, u64>,
+    pub updated: i64,
+}
+
+/// Severity and consecutive check count per condition recorded for one disk on the previous run.
+pub type DiskHistory = HashMap<Condition, (Severity, u32)>;
+
+/// Why an alert is part of this run's notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notify {
+    New,
+    Escalated,
+    Reminder,
+}
+
+impl Notify {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Notify::New => "new",
+            Notify::Escalated => "escalated",
+            Notify::Reminder => "reminder",
+        }
+    }
+}
+
+impl fmt::Display for Notify {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Outcome of comparing this run's alerts with the stored state.
+#[derive(Debug, Default)]
+pub struct Reconciliation {
+    pub notify: BTreeMap<String, Notify>, // Keyed like State::alerts
+    pub suppressed: usize, // Active alerts already notified within renotify_interval
+    pub pending: usize, // Alerts waiting for alert_after_checks
+    pub resolved: Vec<AlertRecord>, // Notified alerts that are no longer active
+}
+
+impl Reconciliation {
+    pub fn reason(&self, mount_point: &str, condition: Condition) -> Option<Notify> {
+        self.notify.get(&key(mount_point, condition)).copied()
+    }
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct State {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub alerts: BTreeMap<String, AlertRecord>,
+    #[serde(default)]
+    pub counters: BTreeMap<String, CounterRecord>, // Keyed by drive serial number
+}
+
+/// State file key for an alert: the mount point identifies the disk across runs.
+pub fn key(mount_point: &str, condition: Condition) -> String {
+    format!("{}|{}", mount_point, condition)
+}
+
+impl State {
+    /// Load the state file; a missing file is an empty state (first run).
+    pub fn load(path: &Path) -> Result<State, String> {
+        match fs::read_to_string(path) {
+            Ok(data) => serde_json::from_str(&data)
+                .map_err(|e| format!("Failed to parse state file {}: {e}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(State::default()),
+            Err(e) => Err(format!("Failed to read state file {}: {e}", path.display())),
+        }
+    }
+
+    /// Write the state file atomically (temporary file + rename), creating its directory.
+    pub fn save(&mut self, path: &Path) -> Result<(), String> {
+        self.version = STATE_VERSION;
+        if let Some(dir) = path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create state directory {}: {e}", dir.display()))?;
+        }
+        let data = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize alert state: {e}"))?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, data).map_err(|e| format!("Failed to write state file {}: {e}", tmp.display()))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Failed to replace state file {}: {e}", path.display()))
+    }
+
+    pub fn history(&self, mount_point: &str) -> DiskHistory {
+        self.alerts.values()
+            .filter(|record| record.mount_point == mount_point && record.cleared.is_none())
+            .map(|record| (record.condition, (record.severity, record.checks)))
+            .collect()
+    }
+
+    /// Tracked counters of a drive compared with the last completed run. Empty for drives without
+    /// a serial number or seen for the first time.
+    pub fn counter_changes(&self, smart: &SmartReport) -> Vec<CounterChange> {
+        let Some(record) = smart.serial_number.as_ref().and_then(|serial| self.counters.get(serial)) else {
+            return Vec::new();
+        };
+        Counter::ALL.iter()
+            .filter_map(|&counter| Some(CounterChange {
+                counter,
+                previous: *record.counters.get(&counter)?,
+                current: smart.counter(counter)?,
+            }))
+            .collect()
+    }
+
+    /// Remember the current counters of every drive as the baseline for the next run.
+    pub fn update_counters(&mut self, disks: &[DiskInfo], now: i64) {
+        for disk in disks {
+            let Some(serial) = &disk.smart.serial_number else { continue };
+            let counters: BTreeMap<Counter, u64> = Counter::ALL.iter()
+                .filter_map(|&counter| Some((counter, disk.smart.counter(counter)?)))
+                .collect();
+            if !counters.is_empty() {
+                self.counters.insert(serial.clone(), CounterRecord { counters, updated: now });
+            }
+        }
+    }
+
+    /// Record this run's alerts (severity, message and consecutive checks) without deciding on any
+    /// notification, so that every run counts towards alert_after_checks and hysteresis. Cleared
+    /// alerts are dropped, except notified ones: those stay marked as cleared until a notifying
+    /// run reports them as resolved.
+    pub fn update(&mut self, disks: &[DiskInfo], now: i64) {
+        let mut active = HashSet::new();
+        let current = disks.iter().flat_map(|disk| {
+            let firing = disk.alerts.iter().map(move |alert| (disk, alert, true));
+            let pending = disk.pending_alerts.iter().map(move |alert| (disk, alert, false));
+            firing.chain(pending)
+        });
+        for (disk, alert, firing) in current {
+            let key = key(&disk.mount_point, alert.condition);
+            let record = self.alerts.entry(key.clone()).or_insert_with(|| AlertRecord {
+                mount_point: disk.mount_point.clone(),
+                display_name: disk.display_name.clone(),
+                condition: alert.condition,
+                severity: alert.severity,
+                message: String::new(),
+                first_seen: now,
+                last_seen: now,
+                notified_severity: None,
+                last_notified: None,
+                checks: 0,
+                cleared: None,
+            });
+            record.display_name = disk.display_name.clone();
+            record.severity = alert.severity;
+            record.message = alert.message.clone();
+            record.last_seen = now;
+            record.checks = alert.checks;
+            record.cleared = None;
+            // Remember a drop in severity so that worsening again counts as an escalation
+            if firing && record.notified_severity.is_some_and(|notified| alert.severity < notified) {
+                record.notified_severity = Some(alert.severity);
+            }
+            active.insert(key);
+        }
+
+        // Alerts that never made it into a notification, and one-off events, clear silently
+        self.alerts.retain(|key, record| {
+            active.contains(key) || (record.notified_severity.is_some() && !record.condition.is_event())
+        });
+        for (key, record) in &mut self.alerts {
+            if !active.contains(key) && record.cleared.is_none() {
+                record.cleared = Some(now);
+            }
+        }
+    }
+
+    /// Decide which of this run's alerts need a notification, after `update` recorded them.
+    /// Cleared alerts are taken out of the state and returned as resolved.
+    pub fn reconcile(&mut self, disks: &[DiskInfo], now: i64, renotify_interval: u64) -> Reconciliation {
+        let mut result = Reconciliation::default();
+        for disk in disks {
+            result.pending += disk.pending_alerts.len();
+            for alert in &disk.alerts {
+                let key = key(&disk.mount_point, alert.condition);
+                let Some(record) = self.alerts.get(&key) else { continue };
+                let reason = match (record.notified_severity, record.last_notified) {
+                    (Some(notified), _) if alert.severity > notified => Some(Notify::Escalated),
+                    (Some(_), Some(last)) if now.saturating_sub(last) < renotify_interval as i64 => None,
+                    (Some(_), Some(_)) => Some(Notify::Reminder),
+                    _ => Some(Notify::New),
+                };
+                match reason {
+                    Some(reason) => {
+                        result.notify.insert(key, reason);
+                    }
+                    None => result.suppressed += 1,
+                }
+            }
+        }
+
+        let cleared: Vec<String> = self.alerts.iter()
+            .filter(|(_, record)| record.cleared.is_some())
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in cleared {
+            result.resolved.extend(self.alerts.remove(&key));
+        }
+        result
+    }
+
+    /// Record that every firing alert was included in a notification sent at `now`.
+    pub fn mark_notified(&mut self, disks: &[DiskInfo], now: i64) {
+        let firing = disks.iter().flat_map(|d| d.alerts.iter().map(move |a| key(&d.mount_point, a.condition)));
+        for key in firing {
+            if let Some(record) = self.alerts.get_mut(&key) {
+                record.notified_severity = Some(record.severity);
+                record.last_notified = Some(now);
+            }
+        }
+    }
+
+    /// Put resolved records back, e.g. after the resolved notification failed, so the next run
+    /// reports them again.
+    pub fn restore(&mut self, records: Vec<AlertRecord>) {
+        for record in records {
+            self.alerts.entry(key(&record.mount_point, record.condition)).or_insert(record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const DAY: u64 = 86_400;
+
+    fn disk(alerts: &[(Condition, Severity)]) -> DiskInfo {
+        let mut disk = testing::disk("/srv", 1_000, 50, testing::thresholds("{}"));
+        disk.alerts = alerts.iter().map(|&(condition, severity)| testing::alert(condition, severity, "alert")).collect();
+        disk
+    }
+
+    // One notifying run: record the alerts, decide, and mark them notified as a delivered report would
+    fn run(state: &mut State, disks: &[DiskInfo], now: i64) -> Reconciliation {
+        state.update(disks, now);
+        let result = state.reconcile(disks, now, DAY);
+        if !result.notify.is_empty() {
+            state.mark_notified(disks, now);
+        }
+        result
+    }
+
+    fn low_space(severity: Severity) -> Vec<DiskInfo> {
+        vec![disk(&[(Condition::LowSpace, severity)])]
+    }
+
+    #[test]
+    fn first_alert_is_new() {
+        let mut state = State::default();
+        let result = run(&mut state, &low_space(Severity::Warning), 1_000);
+        assert_eq!(result.reason("/srv", Condition::LowSpace), Some(Notify::New));
+        assert_eq!(result.suppressed, 0);
+
+        let record = &state.alerts[&key("/srv", Condition::LowSpace)];
+        assert_eq!((record.first_seen, record.last_seen), (1_000, 1_000));
+        assert_eq!((record.notified_severity, record.last_notified), (Some(Severity::Warning), Some(1_000)));
+    }
+
+    #[test]
+    fn repeat_is_suppressed_until_renotify_interval() {
+        let mut state = State::default();
+        let disks = low_space(Severity::Warning);
+        run(&mut state, &disks, 1_000);
+
+        let result = run(&mut state, &disks, 1_000 + DAY as i64 - 1);
+        assert!(result.notify.is_empty());
+        assert_eq!(result.suppressed, 1);
+        assert_eq!(state.alerts[&key("/srv", Condition::LowSpace)].last_notified, Some(1_000));
+
+        let result = run(&mut state, &disks, 1_000 + DAY as i64);
+        assert_eq!(result.reason("/srv", Condition::LowSpace), Some(Notify::Reminder));
+        assert_eq!(state.alerts[&key("/srv", Condition::LowSpace)].last_notified, Some(1_000 + DAY as i64));
+    }
+
+    #[test]
+    fn escalation_notifies_immediately() {
+        let mut state = State::default();
+        run(&mut state, &low_space(Severity::Warning), 1_000);
+        let result = run(&mut state, &low_space(Severity::Critical), 1_060);
+        assert_eq!(result.reason("/srv", Condition::LowSpace), Some(Notify::Escalated));
+        assert_eq!(state.alerts[&key("/srv", Condition::LowSpace)].notified_severity, Some(Severity::Critical));
+
+        // Back to warning is quiet, but worsening again is another escalation
+        assert!(run(&mut state, &low_space(Severity::Warning), 1_120).notify.is_empty());
+        let result = run(&mut state, &low_space(Severity::Critical), 1_180);
+        assert_eq!(result.reason("/srv", Condition::LowSpace), Some(Notify::Escalated));
+    }
+
+    #[test]
+    fn notified_alert_resolves_once() {
+        let mut state = State::default();
+        run(&mut state, &low_space(Severity::Critical), 1_000);
+
+        let result = run(&mut state, &[disk(&[])], 2_000);
+        assert_eq!(result.resolved.len(), 1);
+        assert_eq!((result.resolved[0].condition, result.resolved[0].severity), (Condition::LowSpace, Severity::Critical));
+        assert!(state.alerts.is_empty());
+        assert!(run(&mut state, &[disk(&[])], 3_000).resolved.is_empty());
+    }
+
+    #[test]
+    fn unnotified_and_event_alerts_clear_silently() {
+        let mut state = State::default();
+        let disks = [disk(&[(Condition::CounterIncrease, Severity::Warning), (Condition::Temperature, Severity::Warning)])];
+        state.update(&disks, 1_000);
+        state.reconcile(&disks, 1_000, DAY);
+        state.alerts.get_mut(&key("/srv", Condition::CounterIncrease)).unwrap().notified_severity = Some(Severity::Warning);
+
+        let result = run(&mut state, &[disk(&[])], 2_000);
+        assert!(result.resolved.is_empty());
+        assert!(state.alerts.is_empty());
+    }
+
+    #[test]
+    fn pending_alerts_are_recorded_but_not_notified() {
+        let mut state = State::default();
+        let mut pending = disk(&[]);
+        pending.pending_alerts.push(testing::alert(Condition::LowSpace, Severity::Warning, "alert"));
+        let result = run(&mut state, &[pending], 1_000);
+        assert!(result.notify.is_empty());
+        assert_eq!(result.pending, 1);
+        assert_eq!(state.history("/srv").get(&Condition::LowSpace), Some(&(Severity::Warning, 1)));
+        assert_eq!(state.alerts[&key("/srv", Condition::LowSpace)].notified_severity, None);
+    }
+
+    #[test]
+    fn mark_notified_only_touches_firing_alerts() {
+        let mut state = State::default();
+        let mut disks = low_space(Severity::Warning);
+        disks[0].pending_alerts.push(testing::alert(Condition::Temperature, Severity::Critical, "alert"));
+        state.update(&disks, 1_000);
+        state.mark_notified(&disks, 1_500);
+        let space = &state.alerts[&key("/srv", Condition::LowSpace)];
+        assert_eq!((space.notified_severity, space.last_notified), (Some(Severity::Warning), Some(1_500)));
+        let temperature = &state.alerts[&key("/srv", Condition::Temperature)];
+        assert_eq!((temperature.notified_severity, temperature.last_notified), (None, None));
+    }
+
+    #[test]
+    fn update_without_notifying_keeps_cleared_alerts_for_the_next_report() {
+        let mut state = State::default();
+        run(&mut state, &low_space(Severity::Warning), 1_000);
+
+        // A --json or --nagios run sees the alert clear
+        state.update(&[disk(&[])], 2_000);
+        let record = &state.alerts[&key("/srv", Condition::LowSpace)];
+        assert_eq!(record.cleared, Some(2_000));
+        assert!(state.history("/srv").is_empty());
+
+        let result = run(&mut state, &[disk(&[])], 3_000);
+        assert_eq!(result.resolved.len(), 1);
+        assert!(state.alerts.is_empty());
+    }
+
+    #[test]
+    fn failed_resolved_report_is_restored() {
+        let mut state = State::default();
+        run(&mut state, &low_space(Severity::Warning), 1_000);
+        let result = run(&mut state, &[disk(&[])], 2_000);
+        state.restore(result.resolved);
+        assert_eq!(run(&mut state, &[disk(&[])], 3_000).resolved.len(), 1);
+    }
+
+    fn smart(serial: Option<&str>, reallocated_sectors: u64) -> SmartReport {
+        SmartReport {
+            serial_number: serial.map(str::to_string),
+            reallocated_sectors: Some(reallocated_sectors),
+            pending_sectors: Some(0),
+            ..Default::default()
+        }
+    }
+
+    // State whose baseline for drive "S1" is 2 reallocated and 0 pending sectors
+    fn baseline() -> State {
+        let mut state = State::default();
+        let mut disk = disk(&[]);
+        disk.smart = smart(Some("S1"), 2);
+        state.update_counters(&[disk], 1_000);
+        state
+    }
+
+    fn changes(state: &State, smart: &SmartReport) -> Vec<(Counter, u64, u64, u64)> {
+        state.counter_changes(smart).iter().map(|c| (c.counter, c.previous, c.current, c.increase())).collect()
+    }
+
+    #[test]
+    fn counter_changes_against_the_previous_run() {
+        let state = baseline();
+        assert_eq!(changes(&state, &smart(Some("S1"), 5)),
+                   [(Counter::ReallocatedSectors, 2, 5, 3), (Counter::PendingSectors, 0, 0, 0)]);
+        assert_eq!(changes(&state, &smart(Some("S1"), 2)),
+                   [(Counter::ReallocatedSectors, 2, 2, 0), (Counter::PendingSectors, 0, 0, 0)]);
+        // A lower value (replaced drive, reset counter) is not an increase
+        assert_eq!(changes(&state, &smart(Some("S1"), 1))[0], (Counter::ReallocatedSectors, 2, 1, 0));
+        // Counters without a baseline are skipped
+        let mut nvme = smart(Some("S1"), 2);
+        nvme.nvme = Some(Default::default());
+        assert_eq!(changes(&state, &nvme).len(), 2);
+    }
+
+    #[test]
+    fn counter_changes_need_a_known_serial() {
+        let state = baseline();
+        assert!(changes(&state, &smart(Some("S2"), 5)).is_empty());
+        assert!(changes(&state, &smart(None, 5)).is_empty());
+        assert!(changes(&State::default(), &smart(Some("S1"), 5)).is_empty());
+    }
+
+    #[test]
+    fn counter_increase_is_reported_as_previous_and_current() {
+        let state = baseline();
+        let limits = crate::alerts::Limits::from_config(&testing::config("{}"));
+        let fs = testing::filesystem("/srv", "/dev/sda1", 1_000, 500);
+        let disk = DiskInfo::new("/srv".to_string(), fs, testing::thresholds("{}"), None, smart(Some("S1"), 5), &limits, &state);
+        assert_eq!(disk.counter_text(Counter::ReallocatedSectors).as_deref(), Some("2 \u{2192} 5"));
+        assert_eq!(disk.counter_text(Counter::PendingSectors).as_deref(), Some("0"));
+        assert_eq!(disk.counter_text(Counter::CrcErrors), None);
+        let alert = disk.alerts.iter().find(|a| a.condition == Condition::CounterIncrease).unwrap();
+        assert_eq!((alert.severity, alert.message.as_str()),
+                   (Severity::Warning, "SMART counters increased: reallocated sectors 2 \u{2192} 5 (+3)"));
+    }
+}
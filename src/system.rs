@@ -1,6 +1,8 @@
 use hostname::get as get_hostname;
 use sysinfo::System;
 
+use crate::smart::SmartReport;
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct SystemInfo {
     pub os_name: String,
@@ -10,6 +12,40 @@ pub struct SystemInfo {
     pub is_virtualized: bool,
 }
 
+/// A mounted filesystem with its size and inode counts (all sizes in bytes).
+#[derive(Debug, Clone)]
+pub struct FilesystemInfo {
+    pub mount_point: String,
+    pub device: String,
+    pub label: Option<String>,
+    pub fs_type: String,
+    pub mount_options: String,
+    pub total_space: u64,
+    pub free_space: u64,
+    pub available_space: u64,
+    pub total_inodes: u64,
+    pub free_inodes: u64,
+}
+
+impl FilesystemInfo {
+    /// Space available to unprivileged users, as a percentage of the total size.
+    pub fn free_space_percent(&self) -> f64 {
+        if self.total_space == 0 {
+            return 0.0;
+        }
+        (self.available_space as f64 / self.total_space as f64) * 100.0
+    }
+
+    /// Free inodes as a percentage of the total, or None when the filesystem does not report
+    /// inode counts (btrfs, ZFS and most FAT/NTFS mounts return zero).
+    pub fn inode_free_percent(&self) -> Option<f64> {
+        if self.total_inodes == 0 {
+            return None;
+        }
+        Some((self.free_inodes as f64 / self.total_inodes as f64) * 100.0)
+    }
+}
+
 pub fn get_system_info() -> SystemInfo {
     let hostname = get_hostname()
         .ok()
@@ -56,18 +92,60 @@ pub fn get_is_virtualized() -> bool {
     false
 }
 
-pub fn get_smart_status(disk_name: &str, debug: bool) -> (Option<String>, Option<String>, Option<String>, Option<String>, bool, Option<u64>, Option<u64>, Option<i64>, Option<u64>, Option<u64>, String) {
+/// Enumerate local filesystems natively. Returns an empty list on platforms without a native
+/// enumerator, in which case the caller falls back to sysinfo.
+#[cfg(target_os = "linux")]
+pub fn list_filesystems(debug: bool) -> Result<Vec<FilesystemInfo>, String> {
+    crate::linux::mounts::list_filesystems(debug)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn list_filesystems(_debug: bool) -> Result<Vec<FilesystemInfo>, String> {
+    Ok(Vec::new())
+}
+
+/// Names of every block device underneath `device` (partitions, dm/md devices, device-mapper
+/// names and physical disks). Used to match excluded_disks entries.
+#[cfg(target_os = "linux")]
+pub fn device_aliases(device: &str) -> Vec<String> {
+    match crate::linux::block::resolve(device) {
+        Some(stack) => {
+            let mut aliases = stack.dm_names();
+            aliases.extend(stack.members);
+            aliases
+        }
+        None => Vec::new(),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn device_aliases(_device: &str) -> Vec<String> {
+    Vec::new()
+}
+
+/// SMART data for a disk. `timeout` bounds the external tools called on Linux.
+pub fn get_smart_status(disk_name: &str, debug: bool, timeout: std::time::Duration) -> SmartReport {
     #[cfg(target_os = "linux")]
     {
-        return crate::linux::get_smart_status(disk_name, debug);
+        crate::linux::get_smart_status(disk_name, debug, timeout)
     }
     #[cfg(target_os = "windows")]
     {
-        let (a, b, c, d, e) = crate::windows::get_smart_status(disk_name, debug);
-        (a, b, c, d, e, None, None, None, None, None, "WMI".to_string())
+        let _ = timeout;
+        let (status, serial_number, brand, model, is_raid) = crate::windows::get_smart_status(disk_name, debug);
+        SmartReport {
+            health: status.as_deref().map(crate::smart::HealthStatus::from_label).unwrap_or_default(),
+            method: crate::smart::HealthMethod::Wmi,
+            serial_number,
+            brand,
+            model,
+            is_raid,
+            ..Default::default()
+        }
     }
     #[cfg(not(any(target_os = "linux", target_os = "windows")))]
     {
-        (None, None, None, None, false, None, None, None, None, None, "unknown".to_string())
+        let _ = (disk_name, debug, timeout);
+        SmartReport::unavailable(crate::smart::HealthMethod::Unknown)
     }
 }
@@ -0,0 +1,57 @@
+// systemd service notifications (see sd_notify(3)).
+//
+// When the daemon runs as a Type=notify unit, systemd passes a datagram socket in NOTIFY_SOCKET
+// that accepts newline-separated assignments such as READY=1, STATUS=... or WATCHDOG=1. With
+// WatchdogSec= set, WATCHDOG_USEC tells how often the service must ping before systemd considers
+// it hung and restarts it. Outside systemd the variables are unset and everything here is a no-op.
+
+use std::env;
+use std::time::Duration;
+
+use log::warn;
+
+/// Send a state update to the service manager, e.g. "READY=1" or "STATUS=...".
+pub fn notify(state: &str) {
+    let Some(socket) = env::var_os("NOTIFY_SOCKET") else { return };
+    if let Err(e) = send(&socket, state) {
+        warn!("Failed to notify systemd ({}): {}", state.lines().next().unwrap_or(""), e);
+    }
+}
+
+#[cfg(unix)]
+fn send(socket: &std::ffi::OsStr, state: &str) -> std::io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::UnixDatagram;
+
+    let datagram = UnixDatagram::unbound()?;
+    match socket.as_bytes() {
+        // Abstract socket namespace (Linux only)
+        #[cfg(target_os = "linux")]
+        [b'@', name @ ..] => {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            datagram.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        _ => {
+            datagram.send_to(state.as_bytes(), socket)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send(_socket: &std::ffi::OsStr, _state: &str) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// How often to send WATCHDOG=1: half the unit's WatchdogSec, or None when the watchdog is off
+/// or meant for another process.
+pub fn watchdog_interval() -> Option<Duration> {
+    if let Ok(pid) = env::var("WATCHDOG_PID")
+        && pid.trim().parse::<u32>().ok() != Some(std::process::id())
+    {
+        return None;
+    }
+    let usec: u64 = env::var("WATCHDOG_USEC").ok()?.trim().parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
@@ -0,0 +1,61 @@
+// Builders shared by the unit tests.
+
+use crate::DiskInfo;
+use crate::alerts::{Alert, Condition, Severity};
+use crate::config::Config;
+use crate::rules::Thresholds;
+use crate::smart::SmartReport;
+use crate::system::FilesystemInfo;
+
+/// A config parsed from YAML; "{}" gives every default.
+pub fn config(yaml: &str) -> Config {
+    serde_yaml::from_str(yaml).expect("invalid test config")
+}
+
+/// The global thresholds of a config given as YAML.
+pub fn thresholds(yaml: &str) -> Thresholds {
+    Thresholds::defaults(&config(yaml))
+}
+
+/// A mounted ext4 filesystem without inode counts.
+pub fn filesystem(mount_point: &str, device: &str, total_space: u64, available_space: u64) -> FilesystemInfo {
+    FilesystemInfo {
+        mount_point: mount_point.to_string(),
+        device: device.to_string(),
+        label: None,
+        fs_type: "ext4".to_string(),
+        mount_options: String::new(),
+        total_space,
+        free_space: available_space,
+        available_space,
+        total_inodes: 0,
+        free_inodes: 0,
+    }
+}
+
+/// A filesystem without inode counts, SMART data or alerts.
+pub fn disk(mount_point: &str, total_space: u64, available_space: u64, thresholds: Thresholds) -> DiskInfo {
+    DiskInfo {
+        mount_point: mount_point.to_string(),
+        device: "/dev/sda1".to_string(),
+        display_name: mount_point.to_string(),
+        label: None,
+        free_space_percent: available_space as f64 / total_space as f64 * 100.0,
+        total_space,
+        available_space,
+        file_system: "ext4".to_string(),
+        total_inodes: 0,
+        free_inodes: 0,
+        inode_free_percent: None,
+        thresholds,
+        forecast: None,
+        smart: SmartReport::default(),
+        counter_changes: Vec::new(),
+        alerts: Vec::new(),
+        pending_alerts: Vec::new(),
+    }
+}
+
+pub fn alert(condition: Condition, severity: Severity, message: &str) -> Alert {
+    Alert { condition, severity, message: message.to_string(), checks: 1 }
+}
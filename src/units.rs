@@ -0,0 +1,231 @@
+// Human-readable units for config.yaml values.
+//
+// Sizes ("20GiB") and durations ("24h") deserialize from either a plain number or a string with
+// a unit suffix, so config files can use whichever is clearer.
+
+use std::fmt;
+use std::time::Duration;
+
+/// A byte count that can be written in config.yaml as a plain number or a human-readable size
+/// such as "500MiB", "20 GiB", "1.5T" or "200GB". Binary (KiB, MiB, ...) and bare K/M/G/T
+/// suffixes are powers of 1024, KB/MB/GB/TB are powers of 1000.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "UnitRepr", into = "u64")]
+pub struct ByteSize(pub u64);
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum UnitRepr {
+    Number(u64),
+    Text(String),
+}
+
+impl TryFrom<UnitRepr> for ByteSize {
+    type Error = String;
+
+    fn try_from(repr: UnitRepr) -> Result<Self, Self::Error> {
+        match repr {
+            UnitRepr::Number(bytes) => Ok(ByteSize(bytes)),
+            UnitRepr::Text(text) => parse_size(&text).map(ByteSize),
+        }
+    }
+}
+
+impl From<ByteSize> for u64 {
+    fn from(size: ByteSize) -> u64 {
+        size.0
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+        let mut value = self.0 as f64;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            write!(f, "{} B", self.0)
+        } else {
+            write!(f, "{:.2} {}", value, UNITS[unit])
+        }
+    }
+}
+
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let split = text.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let value: f64 = number.parse().map_err(|_| format!("invalid size '{}'", text))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        other => return Err(format!("invalid size unit '{}' in '{}' (use B, KiB, MiB, GiB, TiB, KB, MB, GB or TB)", other, text)),
+    };
+    Ok((value * multiplier as f64) as u64)
+}
+
+/// A duration written as a number of seconds or with a unit suffix: "90s", "15m", "24h", "7d",
+/// "2w". Serializes as seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "UnitRepr", into = "u64")]
+pub struct HumanDuration(pub u64);
+
+impl HumanDuration {
+    pub fn as_duration(&self) -> Duration {
+        Duration::from_secs(self.0)
+    }
+}
+
+impl TryFrom<UnitRepr> for HumanDuration {
+    type Error = String;
+
+    fn try_from(repr: UnitRepr) -> Result<Self, Self::Error> {
+        match repr {
+            UnitRepr::Number(secs) => Ok(HumanDuration(secs)),
+            UnitRepr::Text(text) => parse_duration(&text).map(HumanDuration),
+        }
+    }
+}
+
+impl From<HumanDuration> for u64 {
+    fn from(duration: HumanDuration) -> u64 {
+        duration.0
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [(&str, u64); 4] = [("w", 604_800), ("d", 86_400), ("h", 3_600), ("m", 60)];
+        match UNITS.iter().find(|(_, secs)| self.0 >= *secs && self.0.is_multiple_of(*secs)) {
+            Some((unit, secs)) => write!(f, "{}{}", self.0 / secs, unit),
+            None => write!(f, "{}s", self.0),
+        }
+    }
+}
+
+pub fn parse_duration(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let value: u64 = number.parse().map_err(|_| format!("invalid duration '{}'", text))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "s" | "sec" | "secs" => 1,
+        "m" | "min" | "mins" => 60,
+        "h" | "hour" | "hours" => 3_600,
+        "d" | "day" | "days" => 86_400,
+        "w" | "week" | "weeks" => 604_800,
+        other => return Err(format!("invalid duration unit '{}' in '{}' (use s, m, h, d or w)", other, text)),
+    };
+    Ok(value.saturating_mul(multiplier))
+}
+
+/// Approximate elapsed time for reports, e.g. "2d 4h", "35m" or "12s".
+pub fn format_elapsed(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86_400, secs % 86_400 / 3_600, secs % 3_600 / 60);
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{}s", secs),
+        (0, 0, m) => format!("{}m", m),
+        (0, h, m) => format!("{}h {}m", h, m),
+        (d, h, _) => format!("{}d {}h", d, h),
+    }
+}
+
+impl std::str::FromStr for HumanDuration {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        parse_duration(text).map(HumanDuration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_size_units() {
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("10b"), Ok(10));
+        assert_eq!(parse_size("500MiB"), Ok(500 << 20));
+        assert_eq!(parse_size("20 GiB"), Ok(20 << 30));
+        assert_eq!(parse_size(" 2k "), Ok(2048));
+        assert_eq!(parse_size("1.5T"), Ok(3 << 39));
+        assert_eq!(parse_size("200GB"), Ok(200_000_000_000));
+        assert_eq!(parse_size("1.5tb"), Ok(1_500_000_000_000));
+    }
+
+    #[test]
+    fn parse_size_rejects_bad_input() {
+        assert!(parse_size("").is_err());
+        assert!(parse_size("GiB").is_err());
+        assert!(parse_size("1.2.3G").is_err());
+        assert!(parse_size("-5GiB").is_err());
+        assert!(parse_size("10 XB").unwrap_err().contains("invalid size unit 'xb'"));
+    }
+
+    #[test]
+    fn byte_size_from_yaml_number_or_text() {
+        assert_eq!(serde_yaml::from_str::<ByteSize>("4096").unwrap(), ByteSize(4096));
+        assert_eq!(serde_yaml::from_str::<ByteSize>("\"5GiB\"").unwrap(), ByteSize(5 << 30));
+        assert!(serde_yaml::from_str::<ByteSize>("\"lots\"").is_err());
+    }
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("90"), Ok(90));
+        assert_eq!(parse_duration("90s"), Ok(90));
+        assert_eq!(parse_duration("15m"), Ok(900));
+        assert_eq!(parse_duration(" 24 hours "), Ok(86_400));
+        assert_eq!(parse_duration("7D"), Ok(604_800));
+        assert_eq!(parse_duration("2w"), Ok(1_209_600));
+        assert_eq!(parse_duration("99999999999999999w"), Ok(u64::MAX));
+    }
+
+    #[test]
+    fn parse_duration_rejects_bad_input() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("1.5h").is_err());
+        assert!(parse_duration("-5m").is_err());
+        assert!(parse_duration("3 fortnights").unwrap_err().contains("invalid duration unit 'fortnights'"));
+    }
+
+    #[test]
+    fn human_duration_display_uses_largest_exact_unit() {
+        assert_eq!(HumanDuration(0).to_string(), "0s");
+        assert_eq!(HumanDuration(45).to_string(), "45s");
+        assert_eq!(HumanDuration(90).to_string(), "90s");
+        assert_eq!(HumanDuration(300).to_string(), "5m");
+        assert_eq!(HumanDuration(5_400).to_string(), "90m");
+        assert_eq!(HumanDuration(86_400).to_string(), "1d");
+        assert_eq!(HumanDuration(1_209_600).to_string(), "2w");
+        for secs in [0, 45, 300, 5_400, 86_400, 1_209_600] {
+            assert_eq!(HumanDuration(secs).to_string().parse::<HumanDuration>(), Ok(HumanDuration(secs)));
+        }
+    }
+
+    #[test]
+    fn human_duration_from_yaml_number_or_text() {
+        assert_eq!(serde_yaml::from_str::<HumanDuration>("600").unwrap(), HumanDuration(600));
+        assert_eq!(serde_yaml::from_str::<HumanDuration>("\"6h\"").unwrap(), HumanDuration(21_600));
+        assert!(serde_yaml::from_str::<HumanDuration>("\"soon\"").is_err());
+    }
+
+    #[test]
+    fn format_elapsed_rounds_to_two_units() {
+        assert_eq!(format_elapsed(12), "12s");
+        assert_eq!(format_elapsed(35 * 60 + 10), "35m");
+        assert_eq!(format_elapsed(3_600 + 5 * 60), "1h 5m");
+        assert_eq!(format_elapsed(2 * 86_400 + 4 * 3_600 + 59 * 60), "2d 4h");
+    }
+}